@@ -0,0 +1,175 @@
+use godot::engine::AnimationPlayer;
+use godot::prelude::*;
+
+// How far one step of advance_animation goes at most, so method and signal
+// tracks (hitboxes keyed to frames) see every key on the way, like they
+// would at 60 fps.
+const STEP: f64 = 1.0 / 60.0;
+
+fn describe(player: &Gd<AnimationPlayer>) -> String {
+    let animation = player.get_assigned_animation().to_string();
+
+    if animation.is_empty() {
+        return "no animation assigned".to_string();
+    }
+
+    format!(
+        "{:?} at {:.3}s of {:.3}s ({})",
+        animation,
+        player.get_current_animation_position(),
+        player.get_current_animation_length(),
+        if player.is_playing() { "playing" } else { "stopped" }
+    )
+}
+
+// Moves the player `seconds` forward within the current frame, in steps of
+// at most 1/60s, regardless of the frame rate or time scale.
+#[track_caller]
+pub fn advance_animation(player: &Gd<AnimationPlayer>, seconds: f64) {
+    assert!(seconds >= 0.0, "advance_animation: can't go back in time ({}s)", seconds);
+
+    if !player.is_playing() {
+        panic!("advance_animation: {} isn't playing anything ({})", player.get_path(), describe(player));
+    }
+
+    let mut player = player.clone();
+    let mut remaining = seconds;
+
+    while remaining > 0.0 {
+        let step = remaining.min(STEP);
+        player.advance(step);
+        remaining -= step;
+    }
+}
+
+// Plays `animation` and jumps to `time` in it, applying every track as of
+// that time. The player is left there, paused.
+#[track_caller]
+pub fn seek_animation(player: &Gd<AnimationPlayer>, animation: &str, time: f64) {
+    let mut player = player.clone();
+
+    if !player.has_animation(animation.into()) {
+        let animations: Vec<String> = player
+            .get_animation_list()
+            .to_vec()
+            .iter()
+            .map(|name| format!("{:?}", name.to_string()))
+            .collect();
+
+        panic!(
+            "{} has no animation {:?}. It has: {}",
+            player.get_path(),
+            animation,
+            animations.join(", ")
+        );
+    }
+
+    player.play_ex().name(animation.into()).done();
+    player.seek_ex(time).update(true).done();
+    player.pause();
+}
+
+#[track_caller]
+pub fn assert_animation(player: &Gd<AnimationPlayer>, animation: &str) {
+    if player.get_assigned_animation().to_string() != animation {
+        panic!(
+            "expected {} to be on animation {:?}, but it's {}",
+            player.get_path(),
+            animation,
+            describe(player)
+        );
+    }
+}
+
+#[track_caller]
+pub fn assert_animation_position(player: &Gd<AnimationPlayer>, position: f64, epsilon: f64) {
+    let actual = player.get_current_animation_position();
+
+    if (actual - position).abs() > epsilon {
+        panic!(
+            "expected {} to be at {}s (epsilon {}), but it's {}",
+            player.get_path(),
+            position,
+            epsilon,
+            describe(player)
+        );
+    }
+}
+
+fn values_match(actual: &Variant, expected: &Variant) -> bool {
+    match (actual.try_to::<f64>(), expected.try_to::<f64>()) {
+        (Ok(actual), Ok(expected)) => (actual - expected).abs() <= 1e-4,
+        _ => actual == expected,
+    }
+}
+
+// Seeks to `time` in `animation` and checks the property at `property_path`
+// (e.g. "Hitbox/Shape:disabled", relative to the player's root node).
+#[track_caller]
+pub fn assert_animated_value(
+    player: &Gd<AnimationPlayer>,
+    animation: &str,
+    time: f64,
+    property_path: &str,
+    expected: Variant,
+) {
+    let (node_path, property) = property_path.split_once(':').unwrap_or_else(|| {
+        panic!("assert_animated_value: {:?} should look like \"Node/Path:property\"", property_path)
+    });
+
+    seek_animation(player, animation, time);
+
+    let root = player.get_node_or_null(player.get_root_node()).unwrap_or_else(|| {
+        panic!("{}'s root_node {} doesn't exist", player.get_path(), player.get_root_node())
+    });
+
+    let node = root.get_node_or_null(node_path.into()).unwrap_or_else(|| {
+        panic!("assert_animated_value: no node {:?} under {}", node_path, root.get_path())
+    });
+
+    let actual = node.get_indexed(property.into());
+
+    if !values_match(&actual, &expected) {
+        panic!(
+            "expected {} to be {} at {}s of {:?}, but it's {}",
+            property_path,
+            crate::pretty::variant(&expected),
+            time,
+            animation,
+            crate::pretty::variant(&actual)
+        );
+    }
+}
+
+#[macro_export]
+macro_rules! assert_animation {
+    ($player:expr, $animation:expr) => {
+        $crate::animation::assert_animation(&$player, $animation)
+    };
+}
+
+// assert_animation_position!(player, 0.5) or with an epsilon,
+// assert_animation_position!(player, 0.5, 0.02)
+#[macro_export]
+macro_rules! assert_animation_position {
+    ($player:expr, $position:expr) => {
+        $crate::animation::assert_animation_position(&$player, $position, 0.001)
+    };
+    ($player:expr, $position:expr, $epsilon:expr) => {
+        $crate::animation::assert_animation_position(&$player, $position, $epsilon)
+    };
+}
+
+// assert_animated_value!(player, "attack", 0.2, "Hitbox/Shape:disabled", false)
+#[macro_export]
+macro_rules! assert_animated_value {
+    ($player:expr, $animation:expr, $time:expr, $property_path:expr, $expected:expr) => {
+        $crate::animation::assert_animated_value(
+            &$player,
+            $animation,
+            $time,
+            $property_path,
+            godot::prelude::ToGodot::to_variant(&$expected),
+        )
+    };
+}
@@ -0,0 +1,202 @@
+use godot::prelude::*;
+use std::fmt::Display;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tolerance {
+    // |a - b| <= epsilon
+    Absolute(f64),
+    // |a - b| <= epsilon * max(|a|, |b|)
+    Relative(f64),
+    // a and b are at most this many representable floats apart
+    Ulps(u64),
+}
+
+impl Display for Tolerance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tolerance::Absolute(epsilon) => write!(f, "epsilon {}", epsilon),
+            Tolerance::Relative(epsilon) => write!(f, "relative epsilon {}", epsilon),
+            Tolerance::Ulps(ulps) => write!(f, "{} ulps", ulps),
+        }
+    }
+}
+
+// Component-wise comparison used by the `assert_approx_eq!` family. Returns
+// a description of the first component that is outside the tolerance.
+pub trait ApproxEq {
+    fn diverging_component(&self, other: &Self, tolerance: Tolerance) -> Option<String>;
+}
+
+trait Scalar: Copy + Display {
+    fn to_f64(self) -> f64;
+    fn ulps_between(self, other: Self) -> u64;
+}
+
+impl Scalar for f32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn ulps_between(self, other: Self) -> u64 {
+        if self.is_nan() || other.is_nan() {
+            return u64::MAX;
+        }
+
+        // Map the sign-magnitude bit patterns onto a monotonic integer line.
+        let ordered = |value: f32| {
+            let bits = value.to_bits();
+            if bits & 0x8000_0000 != 0 {
+                -((bits & 0x7fff_ffff) as i64)
+            } else {
+                bits as i64
+            }
+        };
+
+        ordered(self).abs_diff(ordered(other))
+    }
+}
+
+impl Scalar for f64 {
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn ulps_between(self, other: Self) -> u64 {
+        if self.is_nan() || other.is_nan() {
+            return u64::MAX;
+        }
+
+        let ordered = |value: f64| {
+            let bits = value.to_bits();
+            if bits & 0x8000_0000_0000_0000 != 0 {
+                -((bits & 0x7fff_ffff_ffff_ffff) as i128)
+            } else {
+                bits as i128
+            }
+        };
+
+        ordered(self).abs_diff(ordered(other)).min(u64::MAX as u128) as u64
+    }
+}
+
+macro_rules! impl_scalar_for_integers {
+    ($($type:ty),*) => {
+        $(
+            impl Scalar for $type {
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn ulps_between(self, other: Self) -> u64 {
+                    (self as i128).abs_diff(other as i128).min(u64::MAX as u128) as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar_for_integers!(i8, i16, i32, i64, u8, u16, u32, u64, usize, isize);
+
+fn compare<T: Scalar>(name: &str, a: T, b: T, tolerance: Tolerance) -> Option<String> {
+    let difference = (a.to_f64() - b.to_f64()).abs();
+
+    let within = match tolerance {
+        Tolerance::Absolute(epsilon) => difference <= epsilon,
+        Tolerance::Relative(epsilon) => {
+            difference <= epsilon * a.to_f64().abs().max(b.to_f64().abs())
+        }
+        Tolerance::Ulps(ulps) => a.ulps_between(b) <= ulps,
+    };
+
+    if within {
+        return None;
+    }
+
+    match tolerance {
+        Tolerance::Ulps(_) => Some(format!(
+            "{}: {} vs {} ({} ulps apart)",
+            name,
+            a,
+            b,
+            a.ulps_between(b)
+        )),
+        _ => Some(format!("{}: {} vs {} (off by {})", name, a, b, difference)),
+    }
+}
+
+fn prefixed(prefix: &str, component: Option<String>) -> Option<String> {
+    component.map(|component| format!("{}.{}", prefix, component))
+}
+
+macro_rules! impl_approx_eq_for_numbers {
+    ($($type:ty),*) => {
+        $(
+            impl ApproxEq for $type {
+                fn diverging_component(&self, other: &Self, tolerance: Tolerance) -> Option<String> {
+                    compare("value", *self, *other, tolerance)
+                }
+            }
+        )*
+    };
+}
+
+impl_approx_eq_for_numbers!(f32, f64, i8, i16, i32, i64, u8, u16, u32, u64, usize, isize);
+
+macro_rules! impl_approx_eq_for_fields {
+    ($type:ty, $($field:ident),*) => {
+        impl ApproxEq for $type {
+            fn diverging_component(&self, other: &Self, tolerance: Tolerance) -> Option<String> {
+                None
+                    $(.or_else(|| compare(stringify!($field), self.$field, other.$field, tolerance)))*
+            }
+        }
+    };
+}
+
+impl_approx_eq_for_fields!(Vector2, x, y);
+impl_approx_eq_for_fields!(Vector3, x, y, z);
+impl_approx_eq_for_fields!(Vector4, x, y, z, w);
+impl_approx_eq_for_fields!(Quaternion, x, y, z, w);
+impl_approx_eq_for_fields!(Color, r, g, b, a);
+
+impl ApproxEq for Basis {
+    fn diverging_component(&self, other: &Self, tolerance: Tolerance) -> Option<String> {
+        (0..3).find_map(|row| {
+            prefixed(
+                &format!("rows[{}]", row),
+                self.rows[row].diverging_component(&other.rows[row], tolerance),
+            )
+        })
+    }
+}
+
+impl ApproxEq for Transform2D {
+    fn diverging_component(&self, other: &Self, tolerance: Tolerance) -> Option<String> {
+        prefixed("a", self.a.diverging_component(&other.a, tolerance))
+            .or_else(|| prefixed("b", self.b.diverging_component(&other.b, tolerance)))
+            .or_else(|| prefixed("origin", self.origin.diverging_component(&other.origin, tolerance)))
+    }
+}
+
+impl ApproxEq for Transform3D {
+    fn diverging_component(&self, other: &Self, tolerance: Tolerance) -> Option<String> {
+        prefixed("basis", self.basis.diverging_component(&other.basis, tolerance))
+            .or_else(|| prefixed("origin", self.origin.diverging_component(&other.origin, tolerance)))
+    }
+}
+
+#[track_caller]
+pub fn assert_approx_eq<T: ApproxEq + std::fmt::Debug>(
+    a: &T,
+    b: &T,
+    tolerance: Tolerance,
+    a_expression: &str,
+    b_expression: &str,
+) {
+    if let Some(component) = a.diverging_component(b, tolerance) {
+        panic!(
+            "assertion failed: {} ≈ {} ({}). Values: {:?} and {:?}, diverged at {}",
+            a_expression, b_expression, tolerance, a, b, component
+        );
+    }
+}
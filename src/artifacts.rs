@@ -0,0 +1,80 @@
+use crate::screenshot::{globalize_path, sanitize_file_name};
+use crate::TestContext;
+use std::path::Path;
+use std::sync::Mutex;
+
+// Left in the artifacts directory, so the next run knows it may delete it.
+// Without it, a misconfigured artifacts_dir (say "res://") is left alone.
+const MARKER: &str = ".godot_rust_specs_artifacts";
+
+lazy_static::lazy_static! {
+    // The test that's running, for artifacts written by assertions.
+    static ref CURRENT_TEST: Mutex<Option<String>> = Mutex::new(None);
+}
+
+// The artifacts directory as a filesystem path, created if needed.
+pub fn dir() -> String {
+    let dir = globalize_path(&crate::config::with(|config| config.artifacts_dir.clone()));
+
+    if std::fs::create_dir_all(&dir).is_ok() {
+        let _ = std::fs::write(format!("{}/{}", dir, MARKER), "");
+        // Keeps the editor from importing screenshots and scenes saved here.
+        let _ = std::fs::write(format!("{}/.gdignore", dir), "");
+    }
+
+    dir
+}
+
+// `<artifacts_dir>/<test>/`, named after the test with anything that isn't
+// safe in a file name replaced, created if needed.
+pub fn test_dir(test_name: &str) -> String {
+    let dir = format!("{}/{}", dir(), sanitize_file_name(test_name));
+    let _ = std::fs::create_dir_all(&dir);
+    dir
+}
+
+// Where a test should write an artifact called `file` (which may include
+// subdirectories, e.g. "frames/0001.png"). Parent directories are created.
+pub fn path_for(test_name: &str, file: &str) -> String {
+    let path = format!("{}/{}", test_dir(test_name), file.trim_start_matches('/'));
+
+    if let Some(parent) = Path::new(&path).parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+
+    path
+}
+
+// Like path_for, for the test that's running.
+pub fn current_path(file: &str) -> String {
+    let test = CURRENT_TEST.lock().unwrap().clone().unwrap_or_else(|| "no_test".to_string());
+    path_for(&test, file)
+}
+
+impl TestContext {
+    // Where to save something worth looking at when the test fails in CI:
+    //
+    //     let path = ctx.artifact_path("level.json");
+    //     std::fs::write(&path, dump).unwrap();
+    pub fn artifact_path(&self, file: &str) -> String {
+        path_for(self.name(), file)
+    }
+}
+
+pub(crate) fn set_current_test(test_name: &str) {
+    *CURRENT_TEST.lock().unwrap() = Some(test_name.to_string());
+}
+
+// Called by the runner when the suite starts, for TestConfig::clean_artifacts.
+// Only removes a directory this module created.
+pub(crate) fn clean_previous_run() {
+    let dir = globalize_path(&crate::config::with(|config| config.artifacts_dir.clone()));
+
+    if !Path::new(&format!("{}/{}", dir, MARKER)).exists() {
+        return;
+    }
+
+    if let Err(error) = std::fs::remove_dir_all(&dir) {
+        crate::output::print_plain(&format!("Could not clean up {}: {}", dir, error), true);
+    }
+}
@@ -0,0 +1,130 @@
+use godot::engine::{AudioServer, AudioStream};
+use godot::prelude::*;
+
+fn player_class(player: &Gd<Node>) -> String {
+    let class = player.get_class().to_string();
+
+    if !["AudioStreamPlayer", "AudioStreamPlayer2D", "AudioStreamPlayer3D"]
+        .iter()
+        .any(|base| player.is_class((*base).into()))
+    {
+        panic!("{} is a {}, not an AudioStreamPlayer(2D/3D)", player.get_path(), class);
+    }
+
+    class
+}
+
+fn stream_path(player: &Gd<Node>) -> Option<String> {
+    player
+        .get("stream".into())
+        .try_to::<Gd<AudioStream>>()
+        .ok()
+        .map(|stream| stream.get_path().to_string())
+}
+
+// What a player is doing, for failure messages.
+fn describe(player: &Gd<Node>) -> String {
+    format!(
+        "{} ({}): playing={}, stream={}, bus={}, volume_db={}",
+        player.get_path(),
+        player_class(player),
+        player.get("playing".into()),
+        stream_path(player).unwrap_or_else(|| "(none)".to_string()),
+        player.get("bus".into()),
+        player.get("volume_db".into())
+    )
+}
+
+// Works for AudioStreamPlayer, AudioStreamPlayer2D and AudioStreamPlayer3D.
+// `stream` is the res:// path of the stream it should be playing, if given.
+#[track_caller]
+pub fn assert_playing(player: &Gd<Node>, stream: Option<&str>, expected: bool) {
+    player_class(player);
+    let playing = player.get("playing".into()).to::<bool>();
+
+    let matches = playing && stream.map_or(true, |stream| stream_path(player).as_deref() == Some(stream));
+
+    if matches != expected {
+        panic!(
+            "expected {} {}to be playing{}, but it's {}",
+            player.get_path(),
+            if expected { "" } else { "not " },
+            stream.map(|stream| format!(" {}", stream)).unwrap_or_default(),
+            describe(player)
+        );
+    }
+}
+
+#[track_caller]
+pub fn assert_audio_bus(player: &Gd<Node>, bus: &str) {
+    player_class(player);
+    let actual = player.get("bus".into()).to::<StringName>().to_string();
+
+    if actual != bus {
+        panic!("expected {} to play on bus {:?}, but it's {}", player.get_path(), bus, describe(player));
+    }
+
+    if AudioServer::singleton().get_bus_index(bus.into()) < 0 {
+        panic!("{} plays on bus {:?}, but the project has no such bus", player.get_path(), bus);
+    }
+}
+
+#[track_caller]
+pub fn assert_volume_db(player: &Gd<Node>, volume_db: f64, epsilon: f64) {
+    player_class(player);
+    let actual = player.get("volume_db".into()).to::<f64>();
+
+    if (actual - volume_db).abs() > epsilon {
+        panic!(
+            "expected {} to play at {} dB (epsilon {}), but it's {}",
+            player.get_path(),
+            volume_db,
+            epsilon,
+            describe(player)
+        );
+    }
+}
+
+// Called by the runner when the suite starts, for TestConfig::mute_audio.
+// Players keep playing (so the assertions above still work), the master bus
+// just doesn't output anything. Tests that touch the buses get them back as
+// they were, i.e. muted, by globals::restore.
+pub(crate) fn mute_master() {
+    AudioServer::singleton().set_bus_mute(0, true);
+}
+
+// assert_playing!(player) or assert_playing!(player, "res://sfx/jump.wav")
+#[macro_export]
+macro_rules! assert_playing {
+    ($player:expr) => {
+        $crate::audio::assert_playing(&$player.clone().upcast(), None, true)
+    };
+    ($player:expr, $stream:expr) => {
+        $crate::audio::assert_playing(&$player.clone().upcast(), Some($stream), true)
+    };
+}
+
+#[macro_export]
+macro_rules! assert_not_playing {
+    ($player:expr) => {
+        $crate::audio::assert_playing(&$player.clone().upcast(), None, false)
+    };
+}
+
+#[macro_export]
+macro_rules! assert_audio_bus {
+    ($player:expr, $bus:expr) => {
+        $crate::audio::assert_audio_bus(&$player.clone().upcast(), $bus)
+    };
+}
+
+// assert_volume_db!(player, -6.0) or assert_volume_db!(player, -6.0, 0.5)
+#[macro_export]
+macro_rules! assert_volume_db {
+    ($player:expr, $volume_db:expr) => {
+        $crate::audio::assert_volume_db(&$player.clone().upcast(), $volume_db, 0.01)
+    };
+    ($player:expr, $volume_db:expr, $epsilon:expr) => {
+        $crate::audio::assert_volume_db(&$player.clone().upcast(), $volume_db, $epsilon)
+    };
+}
@@ -0,0 +1,192 @@
+use crate::TestContext;
+use godot::engine::{Engine, PackedScene, ProjectSettings};
+use godot::prelude::*;
+use std::cell::RefCell;
+
+thread_local! {
+    static STUBS: RefCell<Vec<Stub>> = RefCell::new(Vec::new());
+    // Stand-ins for missing autoloads, see require_autoload.
+    static SUBSTITUTES: RefCell<Vec<Gd<Node>>> = RefCell::new(Vec::new());
+}
+
+struct Stub {
+    original: Gd<Node>,
+    index: i32,
+    double: Gd<Node>,
+}
+
+fn tree_root() -> Gd<Node> {
+    let tree = Engine::singleton()
+        .get_main_loop()
+        .and_then(|main_loop| main_loop.try_cast::<SceneTree>().ok())
+        .expect("stub_autoload: no SceneTree is running");
+
+    tree.get_root()
+        .expect("stub_autoload: the SceneTree has no root window")
+        .upcast()
+}
+
+// Puts `double` at /root/<name> in place of the autoload for the rest of the
+// test; the original goes back when the test finishes.
+//
+// Code that finds the autoload through the tree (get_node("/root/GameState"),
+// `%`/unique names, groups) gets the double. GDScript's global `GameState`
+// identifier is bound once at startup and keeps pointing at the original.
+pub fn stub_autoload<T>(name: &str, double: Gd<T>)
+where
+    T: GodotClass + Inherits<Node>,
+{
+    let mut root = tree_root();
+    let original = root
+        .get_node_or_null(name.into())
+        .unwrap_or_else(|| panic!("stub_autoload: there is no autoload at /root/{}", name));
+
+    let index = original.get_index();
+    root.remove_child(original.clone());
+
+    let mut double = double.upcast::<Node>();
+    double.set_name(name.into());
+    root.add_child(double.clone());
+    root.move_child(double.clone(), index);
+
+    STUBS.with(|stubs| stubs.borrow_mut().push(Stub { original, index, double }));
+}
+
+impl TestContext {
+    pub fn stub_autoload<T>(&self, name: &str, double: Gd<T>)
+    where
+        T: GodotClass + Inherits<Node>,
+    {
+        stub_autoload(name, double);
+    }
+}
+
+// Called by the runner once a test has finished. Restores the originals in
+// reverse order, so stubbing the same autoload twice unwinds correctly.
+pub(crate) fn reset() {
+    let stubs = STUBS.with(|stubs| std::mem::take(&mut *stubs.borrow_mut()));
+    let substitutes = SUBSTITUTES.with(|substitutes| std::mem::take(&mut *substitutes.borrow_mut()));
+
+    for mut substitute in substitutes {
+        if substitute.is_instance_valid() {
+            substitute.free();
+        }
+    }
+
+    if stubs.is_empty() {
+        return;
+    }
+
+    let mut root = tree_root();
+
+    for mut stub in stubs.into_iter().rev() {
+        if stub.double.is_instance_valid() {
+            stub.double.free();
+        }
+
+        root.add_child(stub.original.clone());
+        root.move_child(stub.original, stub.index);
+    }
+}
+
+// The autoload (or its stub) at /root/<name> as `T`.
+#[track_caller]
+pub fn autoload<T>(name: &str) -> Gd<T>
+where
+    T: GodotClass + Inherits<Node>,
+{
+    let node = tree_root()
+        .get_node_or_null(name.into())
+        .unwrap_or_else(|| panic!("autoload: there is no autoload at /root/{}", name));
+
+    node.try_cast::<T>().unwrap_or_else(|node| {
+        panic!(
+            "autoload: /root/{} is a {}, not a {}",
+            name,
+            node.get_class(),
+            T::class_name()
+        )
+    })
+}
+
+impl TestContext {
+    #[track_caller]
+    pub fn autoload<T>(&self, name: &str) -> Gd<T>
+    where
+        T: GodotClass + Inherits<Node>,
+    {
+        autoload(name)
+    }
+}
+
+// An autoload (or engine singleton) a test can't run without, see
+// TestCase::requires_autoload.
+#[derive(Clone, Debug)]
+pub struct RequiredAutoload {
+    pub name: String,
+    // A scene to put at /root/<name> for the test when the project doesn't
+    // have the autoload, instead of failing.
+    pub substitute: Option<String>,
+}
+
+// Fails the test, with a hint on how to set the autoload up, unless there's
+// a node at /root/<name> or an engine singleton called `name`. With a
+// substitute scene, that's instanced there for the rest of the test instead.
+#[track_caller]
+pub fn require_autoload(name: &str, substitute: Option<&str>) {
+    let mut root = tree_root();
+
+    if root.has_node(name.into()) || Engine::singleton().has_singleton(name.into()) {
+        return;
+    }
+
+    if let Some(path) = substitute {
+        let scene = godot::engine::try_load::<PackedScene>(path)
+            .unwrap_or_else(|| panic!("requires_autoload: couldn't load the substitute for {} from {}", name, path));
+        let mut node = scene
+            .instantiate()
+            .unwrap_or_else(|| panic!("requires_autoload: couldn't instantiate {}", path));
+
+        node.set_name(name.into());
+        root.add_child(node.clone());
+        SUBSTITUTES.with(|substitutes| substitutes.borrow_mut().push(node));
+        return;
+    }
+
+    let setting = format!("autoload/{}", name);
+    let settings = ProjectSettings::singleton();
+
+    if settings.has_setting(setting.clone().into()) {
+        panic!(
+            "requires the {} autoload, which is set up ({} = {}) but isn't at /root/{}. \
+             Is it disabled, or does the test scene run without the project's autoloads?",
+            name,
+            setting,
+            settings.get_setting(setting.into()),
+            name
+        );
+    }
+
+    panic!(
+        "requires the {} autoload, which this project doesn't have. Add it in \
+         Project Settings > Autoload, or give the test a substitute scene: \
+         requires_autoload!(\"{}\", \"res://tests/fake_{}.tscn\")",
+        name,
+        name,
+        name.to_lowercase()
+    );
+}
+
+// At the start of a test body:
+//
+//     requires_autoload!("GameState");
+//     requires_autoload!("SteamApi", "res://tests/doubles/steam_api.tscn");
+#[macro_export]
+macro_rules! requires_autoload {
+    ($name:expr) => {
+        $crate::autoload::require_autoload($name, None)
+    };
+    ($name:expr, $substitute:expr) => {
+        $crate::autoload::require_autoload($name, Some($substitute))
+    };
+}
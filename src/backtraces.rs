@@ -0,0 +1,153 @@
+#[cfg(feature = "backtraces")]
+use backtrace::Backtrace;
+#[cfg(feature = "backtraces")]
+use regex::Regex;
+
+// How much of a failing test's backtrace the panic hook prints.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BacktraceMode {
+    Off,
+    // Only the frames between the panic machinery and the runner, i.e. the
+    // test's own code.
+    Short,
+    Full,
+}
+
+impl BacktraceMode {
+    // RUST_BACKTRACE=0 turns backtraces off and =full shows them unfiltered.
+    // Unset (or anything else) gives the short form.
+    pub fn from_env() -> Self {
+        match std::env::var("RUST_BACKTRACE").as_deref() {
+            Ok("0") => BacktraceMode::Off,
+            Ok("full") => BacktraceMode::Full,
+            _ => BacktraceMode::Short,
+        }
+    }
+}
+
+// The backtrace of where this is called from, as the panic hook prints it.
+#[cfg(feature = "backtraces")]
+pub fn current(mode: BacktraceMode, skip: &[String], max_frames: Option<usize>) -> String {
+    format_backtrace(&Backtrace::new(), mode, skip, max_frames)
+}
+
+// Without the backtraces feature there's no way to pick out the test's
+// frames, so only full backtraces are printed, as std formats them.
+#[cfg(not(feature = "backtraces"))]
+pub fn current(mode: BacktraceMode, _skip: &[String], _max_frames: Option<usize>) -> String {
+    match mode {
+        BacktraceMode::Full => std::backtrace::Backtrace::force_capture().to_string(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(feature = "backtraces")]
+struct Frame {
+    name: String,
+    location: Option<String>,
+}
+
+#[cfg(feature = "backtraces")]
+fn frames(backtrace: &Backtrace) -> Vec<Frame> {
+    backtrace
+        .frames()
+        .iter()
+        .flat_map(|frame| frame.symbols())
+        .map(|symbol| Frame {
+            name: symbol
+                .name()
+                .map_or("<unknown>".to_string(), |name| format!("{:#}", name)),
+            location: symbol.filename().map(|file| {
+                // Unify separators so the patterns below work on Windows too.
+                let file = file.display().to_string().replace('\\', "/");
+                match symbol.lineno() {
+                    Some(line) => format!("{}:{}", file, line),
+                    None => file,
+                }
+            }),
+        })
+        .collect()
+}
+
+// Capturing the backtrace itself, the panic hook and std's panic machinery.
+#[cfg(feature = "backtraces")]
+fn is_panic_machinery(frame: &Frame) -> bool {
+    let name = frame.name.as_str();
+
+    name.starts_with("backtrace::")
+        || name.starts_with("std::panicking")
+        || name.starts_with("core::panicking")
+        || name.starts_with("std::panic::")
+        || name.starts_with("std::sys")
+        || name.starts_with("rust_begin_unwind")
+        || name.starts_with("godot_rust_specs::panics::")
+        || name.contains("TestRunner as godot::")
+        || frame
+            .location
+            .as_deref()
+            .map_or(false, |location| location.contains("/library/core/src/panicking.rs"))
+}
+
+// Where the runner called into the test; everything below is the runner and
+// the engine.
+#[cfg(feature = "backtraces")]
+fn is_runner_entry(frame: &Frame) -> bool {
+    frame.name.starts_with("godot_rust_specs::") && frame.name.contains("run_test")
+}
+
+#[cfg(feature = "backtraces")]
+pub fn format_backtrace(backtrace: &Backtrace, mode: BacktraceMode, skip: &[String], max_frames: Option<usize>) -> String {
+    if mode == BacktraceMode::Off {
+        return String::new();
+    }
+
+    let mut frames = frames(backtrace);
+
+    if mode == BacktraceMode::Short {
+        // Only the machinery above the test: catch_unwind and the runner's
+        // process below it are machinery too.
+        let first = frames.iter().position(|frame| !is_panic_machinery(frame)).unwrap_or(frames.len());
+        frames.drain(..first);
+
+        if let Some(entry) = frames.iter().position(is_runner_entry) {
+            frames.truncate(entry);
+        }
+
+        let patterns: Vec<Regex> = skip
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(error) => {
+                    crate::output::print_plain(&format!("Ignoring backtrace skip pattern {:?}: {}", pattern, error), true);
+                    None
+                }
+            })
+            .collect();
+
+        frames.retain(|frame| {
+            !patterns.iter().any(|pattern| {
+                pattern.is_match(&frame.name)
+                    || frame.location.as_deref().map_or(false, |location| pattern.is_match(location))
+            })
+        });
+    }
+
+    let total = frames.len();
+    if let Some(max) = max_frames {
+        frames.truncate(max);
+    }
+
+    let mut output = String::new();
+    for (index, frame) in frames.iter().enumerate() {
+        output.push_str(&format!("{:4}: {}\n", index, frame.name));
+        if let Some(location) = &frame.location {
+            output.push_str(&format!("             at {}\n", location));
+        }
+    }
+
+    if total > frames.len() {
+        output.push_str(&format!("      ({} more frames)\n", total - frames.len()));
+    }
+
+    output
+}
@@ -0,0 +1,235 @@
+use crate::screenshot::globalize_path;
+use crate::state::test_state;
+use crate::{output, TestCase, TestContext};
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+lazy_static::lazy_static! {
+    static ref RESULTS: Mutex<Vec<BenchResult>> = Mutex::new(Vec::new());
+}
+
+// How long a benchmark may keep iterating within one frame before letting
+// the engine render the next one.
+const FRAME_BUDGET: Duration = Duration::from_millis(8);
+
+#[derive(Clone, Debug)]
+pub struct BenchResult {
+    pub name: String,
+    pub iterations: usize,
+    pub mean: Duration,
+    pub median: Duration,
+    pub p95: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl BenchResult {
+    pub fn from_samples(name: &str, samples: &[Duration]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+
+        let total: Duration = sorted.iter().sum();
+        let percentile = |p: f64| {
+            let index = ((sorted.len() as f64 - 1.0) * p).round() as usize;
+            sorted.get(index).copied().unwrap_or_default()
+        };
+
+        Self {
+            name: name.to_string(),
+            iterations: sorted.len(),
+            mean: total / sorted.len().max(1) as u32,
+            median: percentile(0.5),
+            p95: percentile(0.95),
+            min: sorted.first().copied().unwrap_or_default(),
+            max: sorted.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Samples(Vec<Duration>);
+
+// A benchmark: runs `body` `iterations` times, spread over as many frames as
+// needed, and records its timings for the report printed after the summary.
+// It shows up as a (passing) test in the run.
+#[track_caller]
+pub fn bench_case(
+    name: impl Into<String>,
+    iterations: usize,
+    body: impl Fn(&TestContext) + Send + Sync + 'static,
+) -> TestCase {
+    TestCase::new(name, move |context| run(context, iterations, &body)).tags(&["bench"])
+}
+
+fn run(context: &TestContext, iterations: usize, body: &impl Fn(&TestContext)) {
+    let frame_started_at = Instant::now();
+
+    let done = test_state(|samples: &mut Samples| {
+        while samples.0.len() < iterations && frame_started_at.elapsed() < FRAME_BUDGET {
+            let started_at = Instant::now();
+            body(context);
+            samples.0.push(started_at.elapsed());
+        }
+
+        if samples.0.len() < iterations {
+            return false;
+        }
+
+        let result = BenchResult::from_samples(context.name(), &samples.0);
+        RESULTS.lock().unwrap().push(result.clone());
+        check_baseline(&result);
+        true
+    });
+
+    if !done {
+        *crate::WANTS_REPLAY.lock().unwrap() = true;
+        *crate::DELAY_BEFORE_NEXT_TEST_RUN.lock().unwrap() = 0.0;
+    }
+}
+
+fn read_baselines() -> Value {
+    let Some(path) = crate::config::with(|config| config.bench_baseline_file.clone()) else {
+        return json!({});
+    };
+
+    std::fs::read_to_string(globalize_path(&path))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_else(|| json!({}))
+}
+
+// The committed median of `name`, if it has one.
+pub fn baseline(name: &str) -> Option<Duration> {
+    read_baselines()[name]["median_ns"]
+        .as_u64()
+        .map(Duration::from_nanos)
+}
+
+// Compares the median against the baseline. A regression beyond the
+// configured threshold panics (failing the benchmark) when
+// bench_regressions_fail is set, otherwise it's only reported.
+fn check_baseline(result: &BenchResult) {
+    let (threshold, fail, updating) = crate::config::with(|config| {
+        (
+            config.bench_regression_threshold,
+            config.bench_regressions_fail,
+            config.update_bench_baselines,
+        )
+    });
+
+    if updating {
+        return;
+    }
+
+    let Some(baseline) = baseline(&result.name) else {
+        return;
+    };
+
+    let ratio = result.median.as_secs_f64() / baseline.as_secs_f64().max(f64::EPSILON);
+    if ratio <= 1.0 + threshold {
+        return;
+    }
+
+    let message = format!(
+        "{} regressed: median {} vs baseline {} (+{:.0}%, allowed +{:.0}%)",
+        result.name,
+        format_duration(result.median),
+        format_duration(baseline),
+        (ratio - 1.0) * 100.0,
+        threshold * 100.0
+    );
+
+    if fail {
+        panic!("{}", message);
+    } else {
+        crate::println_yellow!("\n{}", message);
+    }
+}
+
+// With update_bench_baselines set, records this run's medians as the new
+// baselines (keeping those of benchmarks that didn't run).
+fn update_baselines(results: &[BenchResult]) {
+    let Some(path) = crate::config::with(|config| config.bench_baseline_file.clone()) else {
+        return;
+    };
+
+    let mut baselines = read_baselines();
+    if !baselines.is_object() {
+        baselines = json!({});
+    }
+
+    for result in results {
+        baselines[result.name.as_str()] = json!({
+            "median_ns": result.median.as_nanos() as u64,
+            "mean_ns": result.mean.as_nanos() as u64,
+            "p95_ns": result.p95.as_nanos() as u64,
+            "iterations": result.iterations,
+        });
+    }
+
+    let file = globalize_path(&path);
+    let json = serde_json::to_string_pretty(&baselines).unwrap_or_default();
+    match std::fs::write(&file, json) {
+        Ok(_) => output::print_plain(&format!("Updated benchmark baselines in {}", path), true),
+        Err(error) => output::print_plain(&format!("Could not write {}: {}", file, error), true),
+    }
+}
+
+pub fn results() -> Vec<BenchResult> {
+    RESULTS.lock().unwrap().clone()
+}
+
+fn format_duration(duration: Duration) -> String {
+    let nanos = duration.as_nanos();
+
+    if nanos < 1_000 {
+        format!("{}ns", nanos)
+    } else if nanos < 1_000_000 {
+        format!("{:.2}µs", nanos as f64 / 1_000.0)
+    } else if nanos < 1_000_000_000 {
+        format!("{:.2}ms", nanos as f64 / 1_000_000.0)
+    } else {
+        format!("{:.2}s", duration.as_secs_f64())
+    }
+}
+
+// Called by the runner after the summary.
+pub(crate) fn print_report() {
+    let results = results();
+    if results.is_empty() {
+        return;
+    }
+
+    let width = results.iter().map(|result| result.name.len()).max().unwrap_or(0);
+
+    output::print_plain("\nBenchmarks:", true);
+    for result in &results {
+        output::print_plain(
+            &format!(
+                "  {:width$}  {:>8} iterations  mean {:>10}  median {:>10}  p95 {:>10}  min {:>10}  max {:>10}",
+                result.name,
+                result.iterations,
+                format_duration(result.mean),
+                format_duration(result.median),
+                format_duration(result.p95),
+                format_duration(result.min),
+                format_duration(result.max),
+                width = width
+            ),
+            true,
+        );
+    }
+
+    if crate::config::with(|config| config.update_bench_baselines) {
+        update_baselines(&results);
+    }
+}
+
+// bench!("astar_across_the_map", 500, |ctx| { ... })
+#[macro_export]
+macro_rules! bench {
+    ($name:expr, $iterations:expr, $body:expr) => {{
+        $crate::register($crate::bench::bench_case($name, $iterations, $body).module_path(module_path!()));
+    }};
+}
@@ -0,0 +1,141 @@
+// `cargo godot-test`: builds the GDExtension, launches the test scene in
+// headless Godot and exits with Godot's exit code.
+//
+//     cargo godot-test [--project godot] [--scene res://tests.tscn]
+//                      [--godot path/to/godot] [--release] [--no-build]
+//                      [--no-headless] [--fixed-fps 60] [--shards 4]
+//                      [runner args...]
+//
+// --shards N runs the suite as N Godot processes at once and merges their
+// results into target/godot_rust_specs/results.json.
+//
+// Any argument not listed above (e.g. `--filter player --seed 42`) is passed
+// through to the runner as a Godot user argument.
+//
+//     cargo godot-test init [--project godot] [--scene res://tests.tscn]
+//                           [--lib-name my_game] [--force]
+//
+// generates the Godot project (project.godot, .gdextension and a scene with
+// a TestRunner) needed to run the suite.
+use godot_rust_specs::tooling::{self, LaunchOptions, ScaffoldOptions};
+use std::path::{Path, PathBuf};
+
+fn init(mut args: impl Iterator<Item = String>) {
+    let mut options = ScaffoldOptions {
+        project: PathBuf::from("godot"),
+        lib_name: tooling::lib_name_from_manifest(Path::new("Cargo.toml")).unwrap_or_default(),
+        scene: "res://tests.tscn".to_string(),
+        force: false,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--project" => options.project = PathBuf::from(args.next().expect("--project needs a path")),
+            "--scene" => options.scene = args.next().expect("--scene needs a res:// path"),
+            "--lib-name" => options.lib_name = args.next().expect("--lib-name needs a name"),
+            "--force" => options.force = true,
+            other => {
+                eprintln!("cargo godot-test init: unknown argument: {}", other);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if options.lib_name.is_empty() {
+        eprintln!("cargo godot-test init: could not read the crate name from Cargo.toml, pass --lib-name");
+        std::process::exit(2);
+    }
+
+    match tooling::scaffold(&options) {
+        Ok(written) if written.is_empty() => {
+            println!("Nothing to do, the project already exists (use --force to overwrite)")
+        }
+        Ok(written) => {
+            for path in written {
+                println!("created {}", path.display());
+            }
+        }
+        Err(error) => {
+            eprintln!("cargo godot-test init: {}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let mut options = LaunchOptions::default();
+    let mut godot = None;
+    let mut build_args = Vec::new();
+    let mut build = true;
+    let mut shards = None;
+
+    let mut args = std::env::args().skip(1).peekable();
+
+    // Invoked by cargo as `cargo-godot-test godot-test ...`
+    if args.peek().map(String::as_str) == Some("godot-test") {
+        args.next();
+    }
+
+    if args.peek().map(String::as_str) == Some("init") {
+        args.next();
+        init(args);
+        return;
+    }
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--project" => options.project = PathBuf::from(args.next().expect("--project needs a path")),
+            "--scene" => options.scene = args.next().expect("--scene needs a res:// path"),
+            "--godot" => godot = Some(PathBuf::from(args.next().expect("--godot needs a path"))),
+            "--release" => build_args.push("--release".to_string()),
+            "--no-build" => build = false,
+            "--no-headless" => options.headless = false,
+            "--shards" => {
+                let count = args.next().expect("--shards needs a number");
+                shards = Some(count.parse::<u32>().expect("--shards needs a number").max(1));
+            }
+            "--fixed-fps" => {
+                let fps = args.next().expect("--fixed-fps needs a number");
+                options.fixed_fps = Some(fps.parse().expect("--fixed-fps needs a number"));
+            }
+            "--" => options.user_args.extend(args.by_ref()),
+            _ => options.user_args.push(arg),
+        }
+    }
+
+    options.godot = tooling::resolve_godot_or_exit(godot.as_deref());
+
+    if build && !tooling::cargo_build(&build_args) {
+        eprintln!("cargo godot-test: build failed");
+        std::process::exit(101);
+    }
+
+    if let Some(shards) = shards {
+        let results_file = std::env::current_dir()
+            .unwrap_or_default()
+            .join("target/godot_rust_specs/results.json");
+
+        match tooling::run_sharded(&options, shards, &results_file) {
+            Ok(code) => {
+                println!("Merged results of {} shards into {}", shards, results_file.display());
+                std::process::exit(code);
+            }
+            Err(error) => {
+                eprintln!("cargo godot-test: {}", error);
+                std::process::exit(127);
+            }
+        }
+    }
+
+    match tooling::run_godot(&options) {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(error) => {
+            eprintln!(
+                "cargo godot-test: could not launch {}: {}",
+                options.godot.display(),
+                error
+            );
+            std::process::exit(127);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+// Rebuilds the GDExtension and reruns the headless test scene whenever a
+// Rust source file changes.
+//
+//     godot-specs-watch [--project godot] [--scene res://tests.tscn]
+//                       [--godot path/to/godot] [--watch src]... [-- user args]
+use godot_rust_specs::tooling::{self, LaunchOptions};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+fn main() {
+    let mut options = LaunchOptions::default();
+    let mut godot = None;
+    let mut watch_dirs = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--project" => options.project = PathBuf::from(args.next().expect("--project needs a path")),
+            "--scene" => options.scene = args.next().expect("--scene needs a res:// path"),
+            "--godot" => godot = Some(PathBuf::from(args.next().expect("--godot needs a path"))),
+            "--watch" => watch_dirs.push(PathBuf::from(args.next().expect("--watch needs a path"))),
+            "--" => {
+                options.user_args = args.by_ref().collect();
+            }
+            other => {
+                eprintln!("unknown argument: {}", other);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    options.godot = tooling::resolve_godot_or_exit(godot.as_deref());
+
+    if watch_dirs.is_empty() {
+        watch_dirs.push(PathBuf::from("src"));
+    }
+
+    let mut sources = tooling::rust_sources(&watch_dirs);
+
+    loop {
+        println!("\n=== building ===");
+
+        let mut child = if tooling::cargo_build(&[]) {
+            println!("=== running {} ===", options.scene);
+            match tooling::spawn_godot(&options) {
+                Ok(child) => Some(child),
+                Err(error) => {
+                    eprintln!("could not launch {}: {}", options.godot.display(), error);
+                    None
+                }
+            }
+        } else {
+            println!("=== build failed, waiting for changes ===");
+            None
+        };
+
+        loop {
+            thread::sleep(POLL_INTERVAL);
+
+            if let Some(running) = child.as_mut() {
+                if let Ok(Some(status)) = running.try_wait() {
+                    println!("=== finished ({}), waiting for changes ===", status);
+                    child = None;
+                }
+            }
+
+            let current = tooling::rust_sources(&watch_dirs);
+            if current != sources {
+                sources = current;
+
+                if let Some(mut running) = child.take() {
+                    let _ = running.kill();
+                    let _ = running.wait();
+                }
+
+                break;
+            }
+        }
+    }
+}
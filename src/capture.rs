@@ -0,0 +1,159 @@
+use std::sync::Mutex;
+
+// Swaps the process's stdout file descriptor for a temporary file while a
+// test body runs. Working at the descriptor level is what allows catching
+// Godot's own prints, which never go through Rust's stdout.
+#[cfg(unix)]
+pub(crate) struct OutputCapture {
+    fd: i32,
+    saved_fd: i32,
+    file: std::fs::File,
+    path: std::path::PathBuf,
+}
+
+#[cfg(unix)]
+impl OutputCapture {
+    pub(crate) fn start() -> Option<Self> {
+        Self::start_fd(libc::STDOUT_FILENO)
+    }
+
+    pub(crate) fn start_stderr() -> Option<Self> {
+        Self::start_fd(libc::STDERR_FILENO)
+    }
+
+    fn start_fd(fd: i32) -> Option<Self> {
+        use std::os::unix::io::AsRawFd;
+
+        flush_all();
+
+        let path = std::env::temp_dir().join(format!(
+            "godot_rust_specs_output_{}_{}.txt",
+            std::process::id(),
+            fd
+        ));
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .ok()?;
+
+        unsafe {
+            let saved_fd = libc::dup(fd);
+            if saved_fd < 0 {
+                return None;
+            }
+
+            if libc::dup2(file.as_raw_fd(), fd) < 0 {
+                libc::close(saved_fd);
+                return None;
+            }
+
+            Some(Self {
+                fd,
+                saved_fd,
+                file,
+                path,
+            })
+        }
+    }
+
+    // Everything written so far, without ending the capture. Reads through a
+    // separate handle, as `file` shares its write offset with the redirected
+    // descriptor.
+    pub(crate) fn contents(&self) -> String {
+        flush_all();
+        std::fs::read_to_string(&self.path).unwrap_or_default()
+    }
+
+    pub(crate) fn finish(mut self) -> String {
+        use std::io::{Read, Seek, SeekFrom};
+
+        flush_all();
+
+        unsafe {
+            libc::dup2(self.saved_fd, self.fd);
+            libc::close(self.saved_fd);
+        }
+
+        let mut output = String::new();
+        let _ = self.file.seek(SeekFrom::Start(0));
+        let _ = self.file.read_to_string(&mut output);
+        let _ = std::fs::remove_file(&self.path);
+
+        output
+    }
+}
+
+#[cfg(unix)]
+fn flush_all() {
+    use std::io::Write;
+
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+    unsafe {
+        libc::fflush(std::ptr::null_mut());
+    }
+}
+
+// Not supported on this platform yet: output goes straight to the console.
+#[cfg(not(unix))]
+pub(crate) struct OutputCapture;
+
+#[cfg(not(unix))]
+impl OutputCapture {
+    pub(crate) fn start() -> Option<Self> {
+        None
+    }
+
+    pub(crate) fn start_stderr() -> Option<Self> {
+        None
+    }
+
+    pub(crate) fn contents(&self) -> String {
+        String::new()
+    }
+
+    pub(crate) fn finish(self) -> String {
+        String::new()
+    }
+}
+
+lazy_static::lazy_static! {
+    // The current test's stdout: what its earlier runs (see wait!) printed,
+    // and the capture of the run in progress.
+    static ref STDOUT: Mutex<(String, Option<OutputCapture>)> = Mutex::new((String::new(), None));
+}
+
+// Starts capturing this run of the current test. False where stdout can't
+// be captured.
+pub(crate) fn start_stdout() -> bool {
+    let capture = OutputCapture::start();
+    let started = capture.is_some();
+    STDOUT.lock().unwrap().1 = capture;
+    started
+}
+
+pub(crate) fn stop_stdout() {
+    let mut stdout = STDOUT.lock().unwrap();
+    if let Some(capture) = stdout.1.take() {
+        let output = capture.finish();
+        stdout.0.push_str(&output);
+    }
+}
+
+// Everything the current test printed so far, across its runs.
+pub fn captured_stdout() -> String {
+    let stdout = STDOUT.lock().unwrap();
+    let mut output = stdout.0.clone();
+    if let Some(capture) = &stdout.1 {
+        output.push_str(&capture.contents());
+    }
+    output
+}
+
+// Called by the runner when a test finishes.
+pub(crate) fn take_stdout() -> String {
+    std::mem::take(&mut STDOUT.lock().unwrap().0)
+}
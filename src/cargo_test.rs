@@ -0,0 +1,139 @@
+// Runs the Godot suite from `cargo test`, reporting every spec as its own
+// libtest test, so `cargo test -- player` and IDE test explorers work. Add a
+// test target without the default harness:
+//
+//     # Cargo.toml
+//     [[test]]
+//     name = "godot"
+//     harness = false
+//
+//     // tests/godot.rs
+//     fn main() {
+//         godot_rust_specs::cargo_test::run(LaunchOptions {
+//             godot: tooling::resolve_godot_or_exit(None),
+//             ..LaunchOptions::default()
+//         });
+//     }
+//
+// Godot is launched once for the whole run and the results come back through
+// the results file. Its output is only shown when the run itself goes wrong
+// (or with --nocapture); failed specs report their failure message.
+use crate::tooling::{godot_command, LaunchOptions};
+use libtest_mimic::{Arguments, Failed, Trial};
+use serde_json::Value;
+use std::process::Stdio;
+
+pub fn run(options: LaunchOptions) -> ! {
+    let args = Arguments::from_args();
+
+    let trials = if args.list { listed_trials(&options) } else { run_trials(&options, &args) };
+
+    libtest_mimic::run(&args, trials).exit()
+}
+
+// `name [tag, tag] (file:line)` lines of --list, by name.
+fn parse_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, location) = line.trim().strip_suffix(')')?.rsplit_once(" (")?;
+            let (_, line_number) = location.rsplit_once(':')?;
+            line_number.parse::<u32>().ok()?;
+
+            let name = match name.rsplit_once(" [") {
+                Some((name, tags)) if tags.ends_with(']') => name,
+                _ => name,
+            };
+            Some(name.to_string())
+        })
+        .collect()
+}
+
+fn listed_trials(options: &LaunchOptions) -> Vec<Trial> {
+    let mut list = options.clone();
+    list.user_args.push("--list".to_string());
+
+    let output = match godot_command(&list).output() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).to_string(),
+        Err(error) => return vec![launch_failure(format!("could not launch Godot: {}", error))],
+    };
+
+    parse_list(&output).into_iter().map(|name| Trial::test(name, || Ok(()))).collect()
+}
+
+fn run_trials(options: &LaunchOptions, args: &Arguments) -> Vec<Trial> {
+    let results_file =
+        std::env::temp_dir().join(format!("godot_rust_specs_cargo_test_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&results_file);
+
+    let mut run = options.clone();
+    run.user_args.push("--results-file".to_string());
+    run.user_args.push(results_file.display().to_string());
+    if let Some(filter) = &args.filter {
+        run.user_args.push("--filter".to_string());
+        run.user_args.push(filter.clone());
+    }
+    if args.exact {
+        run.user_args.push("--exact".to_string());
+    }
+
+    let mut command = godot_command(&run);
+    if !args.nocapture {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+
+    let (code, log) = match command.output() {
+        Ok(output) => (
+            output.status.code(),
+            format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        ),
+        Err(error) => return vec![launch_failure(format!("could not launch Godot: {}", error))],
+    };
+
+    let results = std::fs::read_to_string(&results_file)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<Value>(&contents).ok());
+    let _ = std::fs::remove_file(&results_file);
+
+    let Some(results) = results else {
+        return vec![launch_failure(format!("Godot exited ({:?}) without writing results:\n{}", code, log))];
+    };
+
+    let mut trials: Vec<Trial> = results["tests"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|test| {
+            let name = test["name"].as_str()?.to_string();
+            let status = test["status"].as_str().unwrap_or("").to_string();
+            let failure = test["failure"].as_str().unwrap_or("failed").to_string();
+            let ignored = status == "skipped" || status == "pending";
+
+            Some(
+                Trial::test(name, move || match status.as_str() {
+                    "failed" => Err(Failed::from(failure)),
+                    _ => Ok(()),
+                })
+                .with_ignored_flag(ignored),
+            )
+        })
+        .collect();
+
+    // A crash part way through leaves the remaining tests out of the results
+    // (and a non-zero exit code), which shouldn't pass silently. A filter
+    // that matches nothing isn't a crash, libtest reports that itself.
+    let failures = results["suite"]["failures"].as_u64().unwrap_or(0);
+    if !matches!(code, Some(0) | Some(crate::EXIT_NO_TESTS)) && failures == 0 {
+        trials.push(launch_failure(format!("Godot exited with {:?}:\n{}", code, log)));
+    }
+
+    trials
+}
+
+fn launch_failure(message: String) -> Trial {
+    Trial::test("godot", move || Err(Failed::from(message)))
+}
@@ -0,0 +1,90 @@
+use godot::engine::ProjectSettings;
+use godot::prelude::*;
+
+fn layers_of(bits: u32) -> Vec<u32> {
+    (1..=32).filter(|layer| bits & (1 << (layer - 1)) != 0).collect()
+}
+
+fn bits_of(layers: &[u32]) -> u32 {
+    layers.iter().fold(0, |bits, layer| {
+        assert!((1..=32).contains(layer), "collision layers are numbered 1 to 32, got {}", layer);
+        bits | (1 << (layer - 1))
+    })
+}
+
+// `1 (player), 3 (enemies)`, with the names given in the project settings.
+fn describe_layers(node: &Gd<Node>, layers: &[u32]) -> String {
+    if layers.is_empty() {
+        return "none".to_string();
+    }
+
+    let kind = if node.is_class("CollisionObject2D".into())
+        || node.is_class("RayCast2D".into())
+        || node.is_class("ShapeCast2D".into())
+    {
+        "2d_physics"
+    } else {
+        "3d_physics"
+    };
+    let settings = ProjectSettings::singleton();
+
+    layers
+        .iter()
+        .map(|layer| {
+            let setting = format!("layer_names/{}/layer_{}", kind, layer);
+            let name = settings.get_setting(setting.into()).to_string();
+            if name.is_empty() {
+                layer.to_string()
+            } else {
+                format!("{} ({})", layer, name)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Used by assert_collision_layer! and assert_collision_mask!. `property` is
+// "collision_layer" or "collision_mask", so this works for anything that
+// has them: bodies, areas, ray and shape casts, in 2D and 3D.
+#[track_caller]
+pub fn assert_collision_bits(node: &Gd<Node>, property: &str, expected: &[u32]) {
+    let value = node.get(property.into());
+    let Ok(actual) = value.try_to::<i64>() else {
+        panic!("{} ({}) has no {}", node.get_path(), node.get_class(), property);
+    };
+    let actual = actual as u32;
+    let expected_bits = bits_of(expected);
+
+    if actual != expected_bits {
+        let actual_layers = layers_of(actual);
+        let missing: Vec<u32> = expected.iter().copied().filter(|layer| !actual_layers.contains(layer)).collect();
+        let extra: Vec<u32> = actual_layers.iter().copied().filter(|layer| !expected.contains(layer)).collect();
+
+        panic!(
+            "expected the {} of {} to be {}, but it's {} ({:#b}). Missing: {}. Extra: {}",
+            property,
+            node.get_path(),
+            describe_layers(node, &layers_of(expected_bits)),
+            describe_layers(node, &actual_layers),
+            actual,
+            describe_layers(node, &missing),
+            describe_layers(node, &extra)
+        );
+    }
+}
+
+// assert_collision_layer!(body, &[1, 3]): the body is on exactly layers 1
+// and 3.
+#[macro_export]
+macro_rules! assert_collision_layer {
+    ($node:expr, $layers:expr) => {
+        $crate::collision::assert_collision_bits(&$node.clone().upcast(), "collision_layer", $layers)
+    };
+}
+
+#[macro_export]
+macro_rules! assert_collision_mask {
+    ($node:expr, $layers:expr) => {
+        $crate::collision::assert_collision_bits(&$node.clone().upcast(), "collision_mask", $layers)
+    };
+}
@@ -0,0 +1,913 @@
+use crate::backtraces::BacktraceMode;
+use crate::logging::Level;
+use crate::output::ColorMode;
+use crate::report::Format;
+use crate::screenshot::ScreenshotTolerance;
+use crate::CleanupStrategy;
+use godot::engine::control::LayoutPreset;
+use godot::engine::{Control, Node, Node2D, Node3D, Os};
+use godot::prelude::{Gd, Vector2i};
+use std::sync::Mutex;
+use std::time::Duration;
+
+lazy_static::lazy_static! {
+    static ref CONFIG: Mutex<TestConfig> = Mutex::new(TestConfig::default());
+}
+
+// Everything that controls how the suite runs. Build one in your test
+// registration function and install it:
+//
+//     TestConfig::new()
+//         .cleanup(CleanupStrategy::Deferred)
+//         .test_timeout(Duration::from_secs(10))
+//         .artifacts_dir("res://ci_artifacts")
+//         .install();
+//
+// Command line arguments are applied on top of the installed config when the
+// runner becomes ready.
+#[derive(Clone, Debug)]
+pub struct TestConfig {
+    // Seconds to wait before each test's first run.
+    pub default_delay: f64,
+    // Frames to let pass before the first test, so one-time engine work
+    // (autoloads getting ready, shader compilation, see warmup::on_warmup)
+    // doesn't happen during it. They don't count towards the suite's time.
+    pub warmup_frames: u32,
+    pub format: Format,
+    pub color: ColorMode,
+    // A test still running (e.g. still calling wait!) after this long fails.
+    pub test_timeout: Option<Duration>,
+    // A test that asks to run again (wait! and friends) this many times
+    // fails, as it's most likely waiting for something that never happens.
+    pub max_iterations: Option<usize>,
+    pub cleanup: CleanupStrategy,
+    // Where screenshots, diffs and other failure artifacts go, in a folder
+    // per test, see artifacts.rs.
+    pub artifacts_dir: String,
+    // Delete the previous run's artifacts when the suite starts.
+    pub clean_artifacts: bool,
+    pub verbosity: Verbosity,
+    // Buffer what each test prints and only show it when it fails.
+    pub capture_output: bool,
+    // spec_log! records below this level are dropped.
+    pub log_level: Level,
+    pub screenshot_on_failure: bool,
+    pub screenshot_baseline_dir: String,
+    pub screenshot_tolerance: ScreenshotTolerance,
+    pub snapshot_dir: String,
+    // Record snapshot baselines instead of comparing against them.
+    pub update_snapshots: bool,
+    // Leaked orphan nodes are always listed in the summary; with this set
+    // they also fail the test that leaked them.
+    pub fail_on_orphan_nodes: bool,
+    // A test whose object (or resource) count grows by more than this fails.
+    pub object_leak_threshold: Option<i64>,
+    pub fail_on_engine_errors: bool,
+    pub fail_on_engine_warnings: bool,
+    // Also write everything the runner prints, without colors, to this file.
+    pub log_file: Option<String>,
+    // Emit `::error` workflow commands so failures show up inline on PRs.
+    pub github_annotations: bool,
+    // Where the JSON results of the run are written; None disables it.
+    pub results_file: Option<String>,
+    // Only run the tests that failed according to the previous results file.
+    pub rerun_failed: bool,
+    // Leave the game running when the suite is done instead of quitting,
+    // e.g. when it's run from a debug menu; connect to the runner's
+    // tests_finished signal for the results. Suite wide settings
+    // (fixed_fps, viewport_size, mute_audio) aren't undone.
+    pub embedded: bool,
+    // How many runs' results to keep next to the results file, to point out
+    // tests that pass and fail from run to run. Zero disables it.
+    pub history_runs: usize,
+    // Only run tests whose name contains one of these.
+    pub filters: Vec<String>,
+    // Filters have to match the whole name instead.
+    pub exact: bool,
+    // Only run tests registered in one of these modules (or below them),
+    // e.g. "specs::player".
+    pub modules: Vec<String>,
+    pub seed: Option<u64>,
+    pub fail_fast: bool,
+    // Print the registered tests and quit without running them.
+    pub list_only: bool,
+    // Fail the run if anything is focused, so a suite that only runs one
+    // spec can't be merged by accident.
+    pub forbid_focus: bool,
+    pub duplicates: DuplicatePolicy,
+    // Go through the whole run (scheduling, cleanup, reporting) without
+    // calling any test bodies; every test is reported as skipped.
+    pub dry_run: bool,
+    // Run every test twice in a row, with the same seed, and report the ones
+    // whose outcome changes: they depend on state an earlier test left
+    // behind, or leave some behind themselves.
+    pub double_run: bool,
+    // Keep a failed test's scene around, with the tree paused, until told to
+    // continue (see debug.rs), e.g. to look at it in the remote scene tree.
+    pub pause_on_failure: bool,
+    // Treat every frame as 1/fixed_fps seconds long, for wait! and for the
+    // engine's physics, so frame-counting specs behave the same on a fast
+    // machine and on a slow CI runner. For process delta to be fixed too,
+    // launch Godot with `--fixed-fps <fps>` (LaunchOptions::fixed_fps does).
+    pub fixed_fps: Option<u32>,
+    // Size of the root window for every test, unless a test sets its own
+    // with set_viewport_size.
+    pub viewport_size: Option<Vector2i>,
+    // Give every test a SubViewport of its own as its root.
+    pub isolate_viewports: bool,
+    // Like isolate_viewports, with fresh 2D and 3D worlds, so bodies, areas
+    // and gravity overrides can't reach the next test. The physics spaces
+    // are stopped before the test's nodes are freed.
+    pub isolate_physics: bool,
+    // The class of each test's root node, unless the test asks for its own
+    // with TestCase::root_type. Ignored with isolate_viewports (and
+    // isolate_physics), where the root is the SubViewport.
+    pub root_type: RootType,
+    // How long tests that don't wait may keep running back to back within
+    // one frame. Zero runs a single test per frame.
+    pub frame_budget: Duration,
+    // Only run this slice of the suite, see Shard.
+    pub shard: Option<Shard>,
+    // Committed medians of the benchmarks, to catch regressions.
+    pub bench_baseline_file: Option<String>,
+    // How much slower (0.2 = 20%) than its baseline a benchmark may get.
+    pub bench_regression_threshold: f64,
+    // Fail regressed benchmarks instead of only warning.
+    pub bench_regressions_fail: bool,
+    // Record this run's results as the new baselines.
+    pub update_bench_baselines: bool,
+    // Sample the engine's performance monitors (and ProfileScopes) while each
+    // test runs, and save them to the artifacts directory for failed tests
+    // and tests slower than profile_slow_threshold.
+    pub profile_tests: bool,
+    pub profile_slow_threshold: Option<Duration>,
+    // Record the input events tests send, and save them to the artifacts
+    // directory when a test fails (see replay_input!).
+    pub record_input: bool,
+    // Record input coming from the OS (a person clicking around in a
+    // windowed run) too.
+    pub record_real_input: bool,
+    // Keep the last this many rendered frames of each test and save them to
+    // the artifacts directory when it fails.
+    pub record_frames: Option<usize>,
+    // Save a failed test's tree as a .tscn in the artifacts directory.
+    pub save_scene_on_failure: bool,
+    // Defaults to what RUST_BACKTRACE asks for.
+    pub backtrace: BacktraceMode,
+    // Regexes of frames (function names or file paths) to leave out of short
+    // backtraces, e.g. "^my_game::test_helpers::".
+    pub backtrace_skip: Vec<String>,
+    pub backtrace_max_frames: Option<usize>,
+    // Tests taking longer than this are flagged, and listed at the end of the
+    // run even when they pass.
+    pub slow_test_threshold: Option<Duration>,
+    // Show `[done/total]` and an ETA after the dots when printing to a
+    // terminal.
+    pub progress: bool,
+    // Where to look for GDScript tests (e.g. "res://tests"), see gdscript.rs.
+    pub gdscript_tests_dir: Option<String>,
+    // The scene integration tests start from. Defaults to the project's
+    // run/main_scene.
+    pub main_scene: Option<String>,
+    // Mute the master bus for the whole suite, see audio.rs.
+    pub mute_audio: bool,
+}
+
+// `--shard 2/8`: the second of eight processes that split the suite between
+// them. Every `total`th test (in registration order, after filtering) goes
+// to the same shard, so the slices are deterministic and disjoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shard {
+    // 1-based.
+    pub index: u32,
+    pub total: u32,
+}
+
+impl Shard {
+    pub fn parse(text: &str) -> Option<Shard> {
+        let (index, total) = text.split_once('/')?;
+        let index: u32 = index.trim().parse().ok()?;
+        let total: u32 = total.trim().parse().ok()?;
+
+        if index == 0 || index > total {
+            return None;
+        }
+
+        Some(Shard { index, total })
+    }
+
+    pub fn includes(&self, position: usize) -> bool {
+        position % self.total as usize == (self.index - 1) as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    // Only the summary at the end (including what failed).
+    Quiet,
+    // Dots (or names), plus details of failures.
+    Normal,
+    // Every test's name, duration and captured output, plus extra
+    // diagnostics like memory usage.
+    Verbose,
+}
+
+impl Verbosity {
+    pub fn parse(name: &str) -> Option<Verbosity> {
+        match name {
+            "quiet" => Some(Verbosity::Quiet),
+            "normal" => Some(Verbosity::Normal),
+            "verbose" => Some(Verbosity::Verbose),
+            _ => None,
+        }
+    }
+}
+
+// Under a Node2D or Control root, children's transforms, canvas items and
+// anchors behave like they would in a real 2D or UI scene; a plain Node
+// breaks the CanvasItem (and Node3D) transform chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RootType {
+    Node,
+    Node2D,
+    Node3D,
+    // Full rect anchors, so it's the size of the viewport.
+    Control,
+}
+
+impl RootType {
+    pub(crate) fn instantiate(&self) -> Gd<Node> {
+        match self {
+            RootType::Node => Node::new_alloc(),
+            RootType::Node2D => Node2D::new_alloc().upcast(),
+            RootType::Node3D => Node3D::new_alloc().upcast(),
+            RootType::Control => {
+                let mut control = Control::new_alloc();
+                control.set_anchors_preset(LayoutPreset::FULL_RECT);
+                control.upcast()
+            }
+        }
+    }
+}
+
+// What to do when the same test (by name or function) is registered twice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    // Keep the first registration and warn about the rest.
+    Warn,
+    // Refuse to run the suite.
+    Fail,
+}
+
+impl Default for TestConfig {
+    fn default() -> Self {
+        Self {
+            default_delay: 0.0,
+            warmup_frames: 2,
+            format: Format::Progress,
+            color: ColorMode::Auto,
+            test_timeout: None,
+            max_iterations: Some(10_000),
+            cleanup: CleanupStrategy::Immediate,
+            artifacts_dir: "res://test_artifacts".to_string(),
+            clean_artifacts: true,
+            verbosity: Verbosity::Normal,
+            capture_output: true,
+            log_level: Level::Info,
+            screenshot_on_failure: false,
+            screenshot_baseline_dir: "res://test_screenshots".to_string(),
+            screenshot_tolerance: ScreenshotTolerance::default(),
+            snapshot_dir: "res://test_snapshots".to_string(),
+            update_snapshots: false,
+            fail_on_orphan_nodes: false,
+            object_leak_threshold: None,
+            fail_on_engine_errors: false,
+            fail_on_engine_warnings: false,
+            log_file: None,
+            github_annotations: std::env::var("GITHUB_ACTIONS").map_or(false, |value| value == "true"),
+            results_file: Some("user://godot_rust_specs/results.json".to_string()),
+            rerun_failed: false,
+            embedded: false,
+            history_runs: 20,
+            filters: Vec::new(),
+            exact: false,
+            modules: Vec::new(),
+            seed: None,
+            fail_fast: false,
+            list_only: false,
+            forbid_focus: false,
+            duplicates: DuplicatePolicy::Warn,
+            dry_run: false,
+            double_run: false,
+            pause_on_failure: false,
+            fixed_fps: None,
+            viewport_size: None,
+            isolate_viewports: false,
+            isolate_physics: false,
+            root_type: RootType::Node,
+            frame_budget: Duration::from_millis(10),
+            shard: None,
+            bench_baseline_file: Some("res://test_benchmarks.json".to_string()),
+            bench_regression_threshold: 0.2,
+            bench_regressions_fail: false,
+            update_bench_baselines: false,
+            profile_tests: false,
+            profile_slow_threshold: None,
+            record_input: true,
+            record_real_input: false,
+            record_frames: None,
+            save_scene_on_failure: false,
+            backtrace: BacktraceMode::from_env(),
+            backtrace_skip: Vec::new(),
+            backtrace_max_frames: None,
+            slow_test_threshold: None,
+            progress: true,
+            gdscript_tests_dir: None,
+            main_scene: None,
+            mute_audio: false,
+        }
+    }
+}
+
+macro_rules! builder_methods {
+    ($($field:ident: $type:ty),* $(,)?) => {
+        $(
+            pub fn $field(mut self, $field: impl Into<$type>) -> Self {
+                self.$field = $field.into();
+                self
+            }
+        )*
+    };
+}
+
+impl TestConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    builder_methods! {
+        default_delay: f64,
+        warmup_frames: u32,
+        format: Format,
+        color: ColorMode,
+        test_timeout: Option<Duration>,
+        max_iterations: Option<usize>,
+        cleanup: CleanupStrategy,
+        artifacts_dir: String,
+        clean_artifacts: bool,
+        verbosity: Verbosity,
+        capture_output: bool,
+        log_level: Level,
+        screenshot_on_failure: bool,
+        screenshot_baseline_dir: String,
+        screenshot_tolerance: ScreenshotTolerance,
+        snapshot_dir: String,
+        update_snapshots: bool,
+        fail_on_orphan_nodes: bool,
+        object_leak_threshold: Option<i64>,
+        fail_on_engine_errors: bool,
+        fail_on_engine_warnings: bool,
+        log_file: Option<String>,
+        github_annotations: bool,
+        results_file: Option<String>,
+        rerun_failed: bool,
+        embedded: bool,
+        history_runs: usize,
+        exact: bool,
+        seed: Option<u64>,
+        fail_fast: bool,
+        list_only: bool,
+        forbid_focus: bool,
+        duplicates: DuplicatePolicy,
+        dry_run: bool,
+        double_run: bool,
+        pause_on_failure: bool,
+        fixed_fps: Option<u32>,
+        viewport_size: Option<Vector2i>,
+        isolate_viewports: bool,
+        isolate_physics: bool,
+        root_type: RootType,
+        frame_budget: Duration,
+        shard: Option<Shard>,
+        bench_baseline_file: Option<String>,
+        bench_regression_threshold: f64,
+        bench_regressions_fail: bool,
+        update_bench_baselines: bool,
+        profile_tests: bool,
+        profile_slow_threshold: Option<Duration>,
+        record_input: bool,
+        record_real_input: bool,
+        record_frames: Option<usize>,
+        save_scene_on_failure: bool,
+        backtrace: BacktraceMode,
+        backtrace_max_frames: Option<usize>,
+        slow_test_threshold: Option<Duration>,
+        progress: bool,
+        gdscript_tests_dir: Option<String>,
+        main_scene: Option<String>,
+        mute_audio: bool,
+    }
+
+    pub fn filter(mut self, filter: impl Into<String>) -> Self {
+        self.filters.push(filter.into());
+        self
+    }
+
+    pub fn module(mut self, module: impl Into<String>) -> Self {
+        self.modules.push(module.into());
+        self
+    }
+
+    // Shorthand for verbosity(Verbosity::Verbose).
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbosity = if verbose { Verbosity::Verbose } else { Verbosity::Normal };
+        self
+    }
+
+    pub fn backtrace_skip(mut self, pattern: impl Into<String>) -> Self {
+        self.backtrace_skip.push(pattern.into());
+        self
+    }
+
+    // Makes this the configuration of the suite.
+    pub fn install(self) {
+        *CONFIG.lock().unwrap() = self;
+    }
+}
+
+// A copy of the installed configuration.
+pub fn current() -> TestConfig {
+    CONFIG.lock().unwrap().clone()
+}
+
+// Reads from the installed configuration without copying it. `f` must not
+// call back into this module.
+pub fn with<R>(f: impl FnOnce(&TestConfig) -> R) -> R {
+    f(&CONFIG.lock().unwrap())
+}
+
+pub fn update(f: impl FnOnce(&mut TestConfig)) {
+    f(&mut CONFIG.lock().unwrap());
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UserArgs {
+    pub filters: Vec<String>,
+    pub exact: bool,
+    pub modules: Vec<String>,
+    pub seed: Option<u64>,
+    pub format: Option<Format>,
+    pub fail_fast: bool,
+    pub list: bool,
+    pub forbid_focus: bool,
+    pub dry_run: bool,
+    pub double_run: bool,
+    pub pause_on_failure: bool,
+    pub fixed_fps: Option<u32>,
+    pub shard: Option<Shard>,
+    pub results_file: Option<String>,
+    pub update_bench_baselines: bool,
+    pub verbosity: Option<Verbosity>,
+    pub log_level: Option<Level>,
+    // Anything we don't understand, left for the game's own code.
+    pub unknown: Vec<String>,
+}
+
+// Parses the arguments after `--` on the Godot command line, e.g.
+//
+//     godot --headless res://tests.tscn -- --filter player --seed 42
+//
+// Both `--flag value` and `--flag=value` are accepted.
+pub fn parse_user_args(args: &[String]) -> Result<UserArgs, String> {
+    let mut parsed = UserArgs::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+
+        let mut value = |name: &str| {
+            inline_value
+                .clone()
+                .or_else(|| args.next().cloned())
+                .ok_or_else(|| format!("{} needs a value", name))
+        };
+
+        // So `--fail-fast=false` isn't quietly read as `--fail-fast`.
+        let switch = |name: &str| match inline_value {
+            Some(_) => Err(format!("{} doesn't take a value", name)),
+            None => Ok(true),
+        };
+
+        match flag {
+            "--filter" => parsed.filters.push(value("--filter")?),
+            "--exact" => parsed.exact = switch("--exact")?,
+            "--module" => parsed.modules.push(value("--module")?),
+            "--seed" => parsed.seed = Some(parse_seed("--seed", &value("--seed")?)?),
+            "--format" => parsed.format = Some(parse_format("--format", &value("--format")?)?),
+            "--fail-fast" => parsed.fail_fast = switch("--fail-fast")?,
+            "--list" => parsed.list = switch("--list")?,
+            "--forbid-focus" => parsed.forbid_focus = switch("--forbid-focus")?,
+            "--dry-run" => parsed.dry_run = switch("--dry-run")?,
+            "--double-run" => parsed.double_run = switch("--double-run")?,
+            "--pause-on-failure" => parsed.pause_on_failure = switch("--pause-on-failure")?,
+            "--update-bench-baselines" => parsed.update_bench_baselines = switch("--update-bench-baselines")?,
+            "--shard" => parsed.shard = Some(parse_shard("--shard", &value("--shard")?)?),
+            "--results-file" => parsed.results_file = Some(value("--results-file")?),
+            "--fixed-fps" => parsed.fixed_fps = Some(parse_fps("--fixed-fps", &value("--fixed-fps")?)?),
+            "--quiet" | "-q" => {
+                switch("--quiet")?;
+                parsed.verbosity = Some(Verbosity::Quiet);
+            }
+            "--verbose" | "-v" => {
+                switch("--verbose")?;
+                parsed.verbosity = Some(Verbosity::Verbose);
+            }
+            "--log-level" => parsed.log_level = Some(parse_log_level("--log-level", &value("--log-level")?)?),
+            _ => parsed.unknown.push(arg.clone()),
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn parse_seed(name: &str, seed: &str) -> Result<u64, String> {
+    seed.parse()
+        .map_err(|_| format!("{} expects a number, got {:?}", name, seed))
+}
+
+fn parse_fps(name: &str, fps: &str) -> Result<u32, String> {
+    match fps.parse::<u32>() {
+        Ok(fps) if fps > 0 => Ok(fps),
+        _ => Err(format!("{} expects a positive number of frames per second, got {:?}", name, fps)),
+    }
+}
+
+fn parse_shard(name: &str, shard: &str) -> Result<Shard, String> {
+    Shard::parse(shard)
+        .ok_or_else(|| format!("{} expects <index>/<total> with 1 <= index <= total, got {:?}", name, shard))
+}
+
+fn parse_format(name: &str, format: &str) -> Result<Format, String> {
+    Format::parse(format).ok_or_else(|| {
+        format!(
+            "unknown {} {:?} (expected progress, documentation or github)",
+            name, format
+        )
+    })
+}
+
+fn parse_verbosity(name: &str, verbosity: &str) -> Result<Verbosity, String> {
+    Verbosity::parse(verbosity).ok_or_else(|| {
+        format!("unknown {} {:?} (expected quiet, normal or verbose)", name, verbosity)
+    })
+}
+
+fn parse_log_level(name: &str, level: &str) -> Result<Level, String> {
+    Level::parse(level).ok_or_else(|| {
+        format!("unknown {} {:?} (expected trace, debug, info, warn or error)", name, level)
+    })
+}
+
+pub(crate) fn parse_env_flag(name: &str, value: &str) -> Result<bool, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "" | "0" | "false" | "no" | "off" => Ok(false),
+        "1" | "true" | "yes" | "on" => Ok(true),
+        _ => Err(format!("{} expects 1 or 0, got {:?}", name, value)),
+    }
+}
+
+// The same settings as the command line, read from the environment so CI
+// can change them without touching the launch command:
+//
+//     GODOT_SPECS_FILTER=player,inventory  (comma separated)
+//     GODOT_SPECS_EXACT=1
+//     GODOT_SPECS_MODULE=specs::player     (comma separated)
+//     GODOT_SPECS_SEED=42
+//     GODOT_SPECS_FORMAT=documentation
+//     GODOT_SPECS_FAIL_FAST=1
+//     GODOT_SPECS_FORBID_FOCUS=1
+//     GODOT_SPECS_DOUBLE_RUN=1
+//     GODOT_SPECS_PAUSE_ON_FAILURE=1
+//     GODOT_SPECS_FIXED_FPS=60
+//     GODOT_SPECS_SHARD=2/8
+//     GODOT_SPECS_VERBOSITY=quiet
+//     GODOT_SPECS_LOG_LEVEL=debug
+//
+// Command line arguments are applied afterwards: their seed and format win,
+// filters and modules from both are combined.
+pub fn env_user_args() -> Result<UserArgs, String> {
+    env_user_args_from(|name| std::env::var(name).ok())
+}
+
+pub fn env_user_args_from(var: impl Fn(&str) -> Option<String>) -> Result<UserArgs, String> {
+    let mut parsed = UserArgs::default();
+
+    if let Some(filters) = var("GODOT_SPECS_FILTER") {
+        parsed.filters = filters
+            .split(',')
+            .map(|filter| filter.trim())
+            .filter(|filter| !filter.is_empty())
+            .map(|filter| filter.to_string())
+            .collect();
+    }
+
+    if let Some(exact) = var("GODOT_SPECS_EXACT") {
+        parsed.exact = parse_env_flag("GODOT_SPECS_EXACT", &exact)?;
+    }
+
+    if let Some(modules) = var("GODOT_SPECS_MODULE") {
+        parsed.modules = modules
+            .split(',')
+            .map(|module| module.trim())
+            .filter(|module| !module.is_empty())
+            .map(|module| module.to_string())
+            .collect();
+    }
+
+    if let Some(seed) = var("GODOT_SPECS_SEED") {
+        parsed.seed = Some(parse_seed("GODOT_SPECS_SEED", seed.trim())?);
+    }
+
+    if let Some(format) = var("GODOT_SPECS_FORMAT") {
+        parsed.format = Some(parse_format("GODOT_SPECS_FORMAT", format.trim())?);
+    }
+
+    if let Some(fail_fast) = var("GODOT_SPECS_FAIL_FAST") {
+        parsed.fail_fast = parse_env_flag("GODOT_SPECS_FAIL_FAST", &fail_fast)?;
+    }
+
+    if let Some(forbid_focus) = var("GODOT_SPECS_FORBID_FOCUS") {
+        parsed.forbid_focus = parse_env_flag("GODOT_SPECS_FORBID_FOCUS", &forbid_focus)?;
+    }
+
+    if let Some(double_run) = var("GODOT_SPECS_DOUBLE_RUN") {
+        parsed.double_run = parse_env_flag("GODOT_SPECS_DOUBLE_RUN", &double_run)?;
+    }
+
+    if let Some(pause_on_failure) = var("GODOT_SPECS_PAUSE_ON_FAILURE") {
+        parsed.pause_on_failure = parse_env_flag("GODOT_SPECS_PAUSE_ON_FAILURE", &pause_on_failure)?;
+    }
+
+    if let Some(fps) = var("GODOT_SPECS_FIXED_FPS") {
+        parsed.fixed_fps = Some(parse_fps("GODOT_SPECS_FIXED_FPS", fps.trim())?);
+    }
+
+    if let Some(shard) = var("GODOT_SPECS_SHARD") {
+        parsed.shard = Some(parse_shard("GODOT_SPECS_SHARD", &shard)?);
+    }
+
+    if let Some(verbosity) = var("GODOT_SPECS_VERBOSITY") {
+        parsed.verbosity = Some(parse_verbosity("GODOT_SPECS_VERBOSITY", verbosity.trim())?);
+    }
+
+    if let Some(level) = var("GODOT_SPECS_LOG_LEVEL") {
+        parsed.log_level = Some(parse_log_level("GODOT_SPECS_LOG_LEVEL", level.trim())?);
+    }
+
+    Ok(parsed)
+}
+
+pub fn apply_user_args(args: &UserArgs) {
+    update(|config| {
+        config.filters.extend(args.filters.iter().cloned());
+        config.modules.extend(args.modules.iter().cloned());
+
+        if let Some(seed) = args.seed {
+            config.seed = Some(seed);
+        }
+
+        if let Some(format) = args.format {
+            config.format = format;
+            if format == Format::Github {
+                config.github_annotations = true;
+            }
+        }
+
+        config.exact |= args.exact;
+        config.fail_fast |= args.fail_fast;
+        config.list_only |= args.list;
+        config.forbid_focus |= args.forbid_focus;
+        config.dry_run |= args.dry_run;
+        config.double_run |= args.double_run;
+        config.pause_on_failure |= args.pause_on_failure;
+        config.update_bench_baselines |= args.update_bench_baselines;
+
+        if let Some(fps) = args.fixed_fps {
+            config.fixed_fps = Some(fps);
+        }
+
+        if let Some(shard) = args.shard {
+            config.shard = Some(shard);
+        }
+
+        if let Some(path) = &args.results_file {
+            config.results_file = Some(path.clone());
+        }
+
+        if let Some(verbosity) = args.verbosity {
+            config.verbosity = verbosity;
+        }
+
+        if let Some(level) = args.log_level {
+            config.log_level = level;
+        }
+    });
+}
+
+pub(crate) fn cmdline_user_args() -> Vec<String> {
+    Os::singleton()
+        .get_cmdline_user_args()
+        .to_vec()
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect()
+}
+
+pub(crate) fn matches_filters(name: &str) -> bool {
+    with(|config| {
+        config.filters.is_empty()
+            || config
+                .filters
+                .iter()
+                .any(|filter| if config.exact { name == filter } else { name.contains(filter.as_str()) })
+    })
+}
+
+// `specs::player` (or `specs::player::`) matches tests registered in
+// `specs::player` and `specs::player::inventory`, but not in
+// `specs::player_stats`.
+pub(crate) fn matches_modules(module_path: &str) -> bool {
+    with(|config| {
+        config.modules.is_empty()
+            || config.modules.iter().any(|module| {
+                let module = module.trim_end_matches("::");
+                module_path == module || module_path.starts_with(&format!("{}::", module))
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn args(args: &[&str]) -> Result<UserArgs, String> {
+        parse_user_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Result<UserArgs, String> {
+        let vars: HashMap<String, String> =
+            vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        env_user_args_from(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn parses_flags_and_values() {
+        let parsed = args(&[
+            "--filter",
+            "player",
+            "--filter=inventory",
+            "--seed",
+            "42",
+            "--exact",
+            "--shard=2/8",
+        ])
+        .unwrap();
+
+        assert_eq!(parsed.filters, vec!["player", "inventory"]);
+        assert_eq!(parsed.seed, Some(42));
+        assert!(parsed.exact);
+        assert_eq!(parsed.shard, Some(Shard { index: 2, total: 8 }));
+        assert!(parsed.unknown.is_empty());
+    }
+
+    #[test]
+    fn keeps_unknown_arguments() {
+        let parsed = args(&["--level", "3", "--fail-fast"]).unwrap();
+
+        assert_eq!(parsed.unknown, vec!["--level", "3"]);
+        assert!(parsed.fail_fast);
+    }
+
+    #[test]
+    fn rejects_missing_values() {
+        for flag in [
+            "--filter",
+            "--module",
+            "--seed",
+            "--format",
+            "--shard",
+            "--results-file",
+            "--fixed-fps",
+            "--log-level",
+        ] {
+            assert_eq!(args(&[flag]), Err(format!("{} needs a value", flag)));
+        }
+    }
+
+    #[test]
+    fn rejects_values_for_switches() {
+        for switch in [
+            "--exact",
+            "--fail-fast",
+            "--list",
+            "--forbid-focus",
+            "--dry-run",
+            "--double-run",
+            "--pause-on-failure",
+            "--update-bench-baselines",
+            "--quiet",
+            "--verbose",
+        ] {
+            let with_value = format!("{}=false", switch);
+
+            assert!(args(&[switch]).is_ok());
+            assert_eq!(args(&[with_value.as_str()]), Err(format!("{} doesn't take a value", switch)));
+        }
+    }
+
+    #[test]
+    fn rejects_bad_numbers() {
+        assert!(args(&["--seed", "abc"]).is_err());
+        assert!(args(&["--seed", "-1"]).is_err());
+        assert!(args(&["--fixed-fps", "0"]).is_err());
+        assert!(args(&["--fixed-fps=sixty"]).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert!(args(&["--format", "xml"]).is_err());
+        assert!(args(&["--log-level", "loud"]).is_err());
+    }
+
+    #[test]
+    fn rejects_shards_out_of_range() {
+        assert!(args(&["--shard", "0/3"]).is_err());
+        assert!(args(&["--shard", "4/3"]).is_err());
+        assert!(args(&["--shard", "0/0"]).is_err());
+        assert!(args(&["--shard", "3"]).is_err());
+        assert!(args(&["--shard", "a/3"]).is_err());
+        assert_eq!(args(&["--shard", "3/3"]).unwrap().shard, Some(Shard { index: 3, total: 3 }));
+    }
+
+    #[test]
+    fn shard_parse() {
+        assert_eq!(Shard::parse(" 1 / 2 "), Some(Shard { index: 1, total: 2 }));
+        assert_eq!(Shard::parse("0/3"), None);
+        assert_eq!(Shard::parse("4/3"), None);
+        assert_eq!(Shard::parse("1/"), None);
+        assert_eq!(Shard::parse(""), None);
+    }
+
+    #[test]
+    fn shards_split_the_suite() {
+        let shards: Vec<Shard> = (1..=3).map(|index| Shard { index, total: 3 }).collect();
+
+        for position in 0..10 {
+            assert_eq!(shards.iter().filter(|shard| shard.includes(position)).count(), 1);
+        }
+    }
+
+    #[test]
+    fn reads_the_environment() {
+        let parsed = env(&[
+            ("GODOT_SPECS_FILTER", "player, ,inventory,"),
+            ("GODOT_SPECS_SEED", " 7 "),
+            ("GODOT_SPECS_FAIL_FAST", "yes"),
+            ("GODOT_SPECS_DOUBLE_RUN", "0"),
+        ])
+        .unwrap();
+
+        assert_eq!(parsed.filters, vec!["player", "inventory"]);
+        assert_eq!(parsed.seed, Some(7));
+        assert!(parsed.fail_fast);
+        assert!(!parsed.double_run);
+        assert_eq!(env(&[]), Ok(UserArgs::default()));
+    }
+
+    #[test]
+    fn rejects_malformed_environment() {
+        assert!(env(&[("GODOT_SPECS_FAIL_FAST", "maybe")]).is_err());
+        assert!(env(&[("GODOT_SPECS_SEED", "")]).is_err());
+        assert!(env(&[("GODOT_SPECS_FORMAT", "xml")]).is_err());
+        assert!(env(&[("GODOT_SPECS_FIXED_FPS", "0")]).is_err());
+        assert!(env(&[("GODOT_SPECS_SHARD", "0/3")]).is_err());
+        assert!(env(&[("GODOT_SPECS_SHARD", "4/3")]).is_err());
+        assert!(env(&[("GODOT_SPECS_VERBOSITY", "chatty")]).is_err());
+        assert!(env(&[("GODOT_SPECS_LOG_LEVEL", "loud")]).is_err());
+    }
+
+    #[test]
+    fn parses_env_flags() {
+        for value in ["1", "true", " Yes ", "ON"] {
+            assert_eq!(parse_env_flag("FLAG", value), Ok(true));
+        }
+        for value in ["", "0", "false", "no", "Off"] {
+            assert_eq!(parse_env_flag("FLAG", value), Ok(false));
+        }
+        assert_eq!(parse_env_flag("FLAG", "2"), Err("FLAG expects 1 or 0, got \"2\"".to_string()));
+    }
+}
@@ -0,0 +1,49 @@
+use godot::prelude::*;
+use std::ops::Deref;
+
+// What a test is given when it runs. It derefs to the test's root node, so
+// tests written against `&Gd<Node>` (and helpers like `load_scene`) keep
+// working with it.
+#[derive(Clone)]
+pub struct TestContext {
+    root: Gd<Node>,
+    name: String,
+}
+
+impl TestContext {
+    pub(crate) fn new(root: Gd<Node>, name: String) -> Self {
+        Self { root, name }
+    }
+
+    // The node everything the test creates should be added under. It is freed
+    // when the test finishes.
+    pub fn root(&self) -> &Gd<Node> {
+        &self.root
+    }
+
+    // The root as the class asked for with `root_type`, e.g.
+    // `ctx.root_as::<Node2D>()`.
+    #[track_caller]
+    pub fn root_as<T: GodotClass + Inherits<Node>>(&self) -> Gd<T> {
+        self.root.clone().try_cast::<T>().unwrap_or_else(|root| {
+            panic!(
+                "the test root is a {}, not a {} (set the test's root_type)",
+                root.get_class(),
+                T::class_name()
+            )
+        })
+    }
+
+    // The name the test was registered with.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Deref for TestContext {
+    type Target = Gd<Node>;
+
+    fn deref(&self) -> &Gd<Node> {
+        &self.root
+    }
+}
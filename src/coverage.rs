@@ -0,0 +1,27 @@
+// Godot ends the process without running Rust's exit path, so with
+// `-C instrument-coverage` the LLVM profile runtime never gets to write its
+// .profraw file. Build with the `coverage` feature to have the runner write
+// it explicitly right before quitting:
+//
+//     export RUSTFLAGS="-C instrument-coverage" LLVM_PROFILE_FILE="coverage-%p.profraw"
+//     cargo build --features godot_rust_specs/coverage
+//     cargo godot-test --no-build
+//
+// The feature must only be enabled for instrumented builds, otherwise the
+// profile runtime symbol is missing at link time.
+#[cfg(feature = "coverage")]
+extern "C" {
+    fn __llvm_profile_write_file() -> std::os::raw::c_int;
+}
+
+// Called by the runner before it quits.
+pub(crate) fn flush() {
+    #[cfg(feature = "coverage")]
+    {
+        // Safe to call at any point; it writes the counters collected so far.
+        let result = unsafe { __llvm_profile_write_file() };
+        if result != 0 {
+            crate::output::print_plain("Failed to write the coverage profile", true);
+        }
+    }
+}
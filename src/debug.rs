@@ -0,0 +1,124 @@
+use crate::output;
+use godot::engine::global::Key;
+use godot::engine::{EngineDebugger, InputEvent, InputEventKey};
+use godot::prelude::*;
+use std::io::BufRead;
+use std::sync::{Mutex, Once};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Command {
+    // Clean up the failed test and carry on with the next one.
+    Continue,
+    // Clean up and stop the run, as with fail_fast.
+    Stop,
+}
+
+lazy_static::lazy_static! {
+    static ref COMMAND: Mutex<Option<Command>> = Mutex::new(None);
+    // Whether we paused the tree, so only we unpause it.
+    static ref PAUSED_TREE: Mutex<bool> = Mutex::new(false);
+    static ref STOP_REQUESTED: Mutex<bool> = Mutex::new(false);
+}
+
+static WATCH_STDIN: Once = Once::new();
+
+fn tree() -> Option<Gd<SceneTree>> {
+    godot::engine::Engine::singleton()
+        .get_main_loop()
+        .and_then(|main_loop| main_loop.try_cast::<SceneTree>().ok())
+}
+
+// Called by the runner with TestConfig::pause_on_failure, when a test has
+// failed and before anything of it is cleaned up. The tree is paused, so
+// the scene stays as it was when the test failed, until a command comes in
+// from the game window or the terminal.
+pub(crate) fn pause_on_failure(test: &str, failure: Option<&str>) {
+    *COMMAND.lock().unwrap() = None;
+
+    if let Some(mut tree) = tree() {
+        if !tree.is_paused() {
+            tree.set_pause(true);
+            *PAUSED_TREE.lock().unwrap() = true;
+        }
+    }
+
+    // Lines typed into the terminal Godot was started from, which works
+    // headless too.
+    WATCH_STDIN.call_once(|| {
+        std::thread::spawn(|| {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                let command = match line.trim() {
+                    "q" | "quit" | "stop" => Command::Stop,
+                    _ => Command::Continue,
+                };
+                *COMMAND.lock().unwrap() = Some(command);
+            }
+        });
+    });
+
+    let inspect = if EngineDebugger::singleton().is_active() {
+        "Inspect it in the editor's Remote scene tree."
+    } else {
+        "To inspect it in the editor's Remote scene tree, run with the debugger attached \
+         (from the editor, or with --remote-debug tcp://127.0.0.1:6007)."
+    };
+
+    output::unmuted(|| {
+        output::print_colored(output::RED, &format!("\nPaused on the failure of {}:", test), true);
+        if let Some(failure) = failure {
+            output::print_colored(output::RED, failure, true);
+        }
+        output::print_colored(
+            output::YELLOW,
+            &format!(
+                "The test's scene is left as it was and the tree is paused. {}\n\
+                 Press Enter (in the game window or here) to continue, or Escape in the game window \
+                 (q and Enter here) to stop the run.",
+                inspect
+            ),
+            true,
+        );
+    });
+}
+
+// Enter continues, Escape stops, from the game window.
+pub(crate) fn handle_input(event: &Gd<InputEvent>) {
+    let Ok(key) = event.clone().try_cast::<InputEventKey>() else {
+        return;
+    };
+    if !key.is_pressed() || key.is_echo() {
+        return;
+    }
+
+    let command = match key.get_keycode() {
+        Key::ENTER | Key::KP_ENTER => Command::Continue,
+        Key::ESCAPE => Command::Stop,
+        _ => return,
+    };
+    *COMMAND.lock().unwrap() = Some(command);
+}
+
+// The command to resume with, once there is one. Unpauses the tree if
+// pause_on_failure paused it.
+pub(crate) fn take_command() -> Option<Command> {
+    let command = COMMAND.lock().unwrap().take()?;
+
+    if std::mem::replace(&mut *PAUSED_TREE.lock().unwrap(), false) {
+        if let Some(mut tree) = tree() {
+            tree.set_pause(false);
+        }
+    }
+
+    if command == Command::Stop {
+        *STOP_REQUESTED.lock().unwrap() = true;
+    }
+
+    Some(command)
+}
+
+pub(crate) fn stop_requested() -> bool {
+    *STOP_REQUESTED.lock().unwrap()
+}
@@ -0,0 +1,134 @@
+use crate::{TestCase, TestContext};
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+
+type Hook = Arc<dyn Fn(&TestContext) + Send + Sync>;
+
+#[derive(Default)]
+struct Group {
+    name: String,
+    before_each: Vec<Hook>,
+    after_each: Vec<Hook>,
+}
+
+type SharedGroup = Arc<Mutex<Group>>;
+
+thread_local! {
+    // The describe! blocks being evaluated, outermost first.
+    static GROUPS: RefCell<Vec<(SharedGroup, &'static str)>> = RefCell::new(Vec::new());
+}
+
+// Groups tests RSpec style. Hooks declared in a group apply to the tests in
+// it and in the groups nested in it, wherever in the block they're declared:
+//
+//     describe!("player", {
+//         before_each(|ctx| spawn_player(ctx));
+//
+//         it("jumps", |ctx| { ... });
+//
+//         describe!("when dead", {
+//             before_each(|ctx| kill_player(ctx));
+//             it("can't jump", |ctx| { ... });
+//         });
+//     });
+//
+// registers "player jumps" and "player when dead can't jump", the latter
+// running spawn_player and then kill_player first.
+#[macro_export]
+macro_rules! describe {
+    ($name:expr, $body:block) => {
+        $crate::describe::describe($name, module_path!(), || $body)
+    };
+}
+
+pub fn describe(name: &str, module_path: &'static str, body: impl FnOnce()) {
+    let group = Arc::new(Mutex::new(Group { name: name.to_string(), ..Group::default() }));
+
+    GROUPS.with(|groups| groups.borrow_mut().push((group, module_path)));
+    // Popped even if the body panics, so later groups don't get its name.
+    let _pop = PopGroup;
+    body();
+}
+
+struct PopGroup;
+
+impl Drop for PopGroup {
+    fn drop(&mut self) {
+        GROUPS.with(|groups| groups.borrow_mut().pop());
+    }
+}
+
+fn current_group(function: &str) -> SharedGroup {
+    GROUPS.with(|groups| {
+        groups
+            .borrow()
+            .last()
+            .map(|(group, _)| group.clone())
+            .unwrap_or_else(|| panic!("{} can only be used inside describe!", function))
+    })
+}
+
+// Runs before each test of the group, on its first frame. Outer groups'
+// hooks run first.
+pub fn before_each(hook: impl Fn(&TestContext) + Send + Sync + 'static) {
+    current_group("before_each").lock().unwrap().before_each.push(Arc::new(hook));
+}
+
+// Runs once each test of the group has finished, passed or failed (the
+// runner calls them, so a timed out test gets them too). Inner groups' hooks
+// run first, so they can undo what the outer ones set up. Like before_each,
+// a hook can't wait!, it runs within one frame.
+pub fn after_each(hook: impl Fn(&TestContext) + Send + Sync + 'static) {
+    current_group("after_each").lock().unwrap().after_each.push(Arc::new(hook));
+}
+
+#[track_caller]
+pub fn it(name: &str, body: impl Fn(&TestContext) + Send + Sync + 'static) {
+    let location = std::panic::Location::caller();
+
+    let (chain, module_path) = GROUPS.with(|groups| {
+        let groups = groups.borrow();
+        let chain: Vec<SharedGroup> = groups.iter().map(|(group, _)| group.clone()).collect();
+        (chain, groups.last().map_or("", |(_, module_path)| *module_path))
+    });
+
+    if chain.is_empty() {
+        panic!("it can only be used inside describe!");
+    }
+
+    let mut names: Vec<String> = chain.iter().map(|group| group.lock().unwrap().name.clone()).collect();
+    names.push(name.to_string());
+
+    let teardown_chain = chain.clone();
+    let mut test =
+        TestCase::new(names.join(" "), move |ctx| run(&chain, ctx, &body)).module_path(module_path);
+    test.file = location.file();
+    test.line = location.line();
+    test.teardown = Some(Arc::new(move |ctx| teardown(&teardown_chain, ctx)));
+
+    crate::register(test);
+}
+
+fn run(chain: &[SharedGroup], ctx: &TestContext, body: &dyn Fn(&TestContext)) {
+    // Collected when the test runs, so hooks declared after `it` in the
+    // block count too.
+    let before: Vec<Hook> =
+        chain.iter().flat_map(|group| group.lock().unwrap().before_each.clone()).collect();
+
+    if *crate::CURRENT_TEST_ITERATION.lock().unwrap() == 0 {
+        for hook in &before {
+            hook(ctx);
+        }
+    }
+
+    body(ctx);
+}
+
+fn teardown(chain: &[SharedGroup], ctx: &TestContext) {
+    let after: Vec<Hook> =
+        chain.iter().rev().flat_map(|group| group.lock().unwrap().after_each.clone()).collect();
+
+    for hook in &after {
+        hook(ctx);
+    }
+}
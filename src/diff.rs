@@ -0,0 +1,386 @@
+use crate::output::{colorize, BLUE, GREEN, RED};
+use std::fmt::Debug;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+// Diff of `expected` -> `actual` based on the longest common subsequence.
+// Quadratic, which is fine for assertion-sized inputs.
+fn diff_slices<'a>(expected: &[&'a str], actual: &[&'a str]) -> Vec<DiffLine<'a>> {
+    let mut lengths = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lengths[i][j] = if expected[i] == actual[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < expected.len() && j < actual.len() {
+        if expected[i] == actual[j] {
+            lines.push(DiffLine::Same(expected[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            lines.push(DiffLine::Removed(expected[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(actual[j]));
+            j += 1;
+        }
+    }
+
+    lines.extend(expected[i..].iter().map(|line| DiffLine::Removed(line)));
+    lines.extend(actual[j..].iter().map(|line| DiffLine::Added(line)));
+
+    lines
+}
+
+pub fn diff_lines<'a>(expected: &'a str, actual: &'a str) -> Vec<DiffLine<'a>> {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    diff_slices(&expected, &actual)
+}
+
+// Splits into runs of word characters and runs of everything else, so that
+// `Vector2 { x: 1.0, y: 2.0 }` diffs per number rather than per line.
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut previous_is_word = None;
+
+    for (index, character) in text.char_indices() {
+        let is_word = character.is_alphanumeric() || character == '_' || character == '.';
+        if previous_is_word.is_some() && previous_is_word != Some(is_word) {
+            tokens.push(&text[start..index]);
+            start = index;
+        }
+        previous_is_word = Some(is_word);
+    }
+
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+
+    tokens
+}
+
+pub fn diff_words<'a>(expected: &'a str, actual: &'a str) -> Vec<DiffLine<'a>> {
+    diff_slices(&tokenize(expected), &tokenize(actual))
+}
+
+// "- expected" / "+ actual" / "  unchanged" lines.
+pub fn format_diff(expected: &str, actual: &str) -> String {
+    let mut output = String::new();
+
+    for line in diff_lines(expected, actual) {
+        match line {
+            DiffLine::Same(line) => output.push_str(&format!("  {}\n", line)),
+            DiffLine::Removed(line) => output.push_str(&format!("- {}\n", line)),
+            DiffLine::Added(line) => output.push_str(&format!("+ {}\n", line)),
+        }
+    }
+
+    output
+}
+
+pub fn format_colored_diff(expected: &str, actual: &str) -> String {
+    let mut output = String::new();
+
+    for line in diff_lines(expected, actual) {
+        match line {
+            DiffLine::Same(line) => output.push_str(&format!("  {}\n", line)),
+            DiffLine::Removed(line) => {
+                output.push_str(&colorize(RED, &format!("- {}", line)));
+                output.push('\n');
+            }
+            DiffLine::Added(line) => {
+                output.push_str(&colorize(GREEN, &format!("+ {}", line)));
+                output.push('\n');
+            }
+        }
+    }
+
+    output
+}
+
+// Only the changed lines plus `context` lines around them, in `@@` hunks
+// like `diff -u`, so a one-line change in a long text stays readable.
+pub fn format_unified_diff(expected: &str, actual: &str, context: usize) -> String {
+    let lines = diff_lines(expected, actual);
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, DiffLine::Same(_)))
+        .map(|(index, _)| index)
+        .collect();
+
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    // Ranges of diff lines to show, merged when their context overlaps.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for index in changed {
+        let start = index.saturating_sub(context);
+        let end = (index + context + 1).min(lines.len());
+
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut output = String::new();
+
+    for (start, end) in hunks {
+        // Line numbers in expected and actual at the start of the hunk.
+        let expected_line = 1 + lines[..start].iter().filter(|line| !matches!(line, DiffLine::Added(_))).count();
+        let actual_line = 1 + lines[..start].iter().filter(|line| !matches!(line, DiffLine::Removed(_))).count();
+        let hunk = &lines[start..end];
+        let expected_count = hunk.iter().filter(|line| !matches!(line, DiffLine::Added(_))).count();
+        let actual_count = hunk.iter().filter(|line| !matches!(line, DiffLine::Removed(_))).count();
+        // Like diff -u, a side without lines in the hunk gives the line
+        // before it, e.g. `-0,0` for an empty text.
+        let expected_line = if expected_count == 0 { expected_line - 1 } else { expected_line };
+        let actual_line = if actual_count == 0 { actual_line - 1 } else { actual_line };
+
+        output.push_str(&colorize(
+            BLUE,
+            &format!("@@ -{},{} +{},{} @@", expected_line, expected_count, actual_line, actual_count),
+        ));
+        output.push('\n');
+
+        for line in hunk {
+            match line {
+                DiffLine::Same(line) => output.push_str(&format!("  {}\n", line)),
+                DiffLine::Removed(line) => {
+                    output.push_str(&colorize(RED, &format!("- {}", line)));
+                    output.push('\n');
+                }
+                DiffLine::Added(line) => {
+                    output.push_str(&colorize(GREEN, &format!("+ {}", line)));
+                    output.push('\n');
+                }
+            }
+        }
+    }
+
+    output
+}
+
+// A single line with removed words as [-red-] and added words as {+green+}.
+pub fn format_colored_word_diff(expected: &str, actual: &str) -> String {
+    let mut output = String::new();
+
+    for token in diff_words(expected, actual) {
+        match token {
+            DiffLine::Same(token) => output.push_str(token),
+            DiffLine::Removed(token) => output.push_str(&colorize(RED, &format!("[-{}-]", token))),
+            DiffLine::Added(token) => output.push_str(&colorize(GREEN, &format!("{{+{}+}}", token))),
+        }
+    }
+
+    output
+}
+
+// Diff of the pretty Debug output of two values: per word when both fit on
+// one line, per line otherwise.
+pub fn debug_diff<L: Debug, R: Debug>(left: &L, right: &R) -> String {
+    multiline_diff(left, right)
+        .unwrap_or_else(|| text_diff(&format!("{:#?}", left), &format!("{:#?}", right)))
+}
+
+// Per word when both fit on one line, per line otherwise.
+pub fn text_diff(left: &str, right: &str) -> String {
+    if !left.contains('\n') && !right.contains('\n') {
+        format!("  {}\n", format_colored_word_diff(left, right))
+    } else {
+        format_colored_diff(left, right)
+    }
+}
+
+#[macro_export]
+macro_rules! assert_eq_diff {
+    ($left:expr, $right:expr $(,)?) => {{
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left == *right) {
+                    #[allow(unused_imports)]
+                    use $crate::pretty::{ViaDebug, ViaPretty};
+                    let left_formatter = (&$crate::pretty::Formatter::of(left)).formatter();
+                    let right_formatter = (&$crate::pretty::Formatter::of(right)).formatter();
+
+                    let diff = match (left_formatter, right_formatter) {
+                        (Some(format_left), Some(format_right)) => {
+                            $crate::diff::text_diff(&format_left(left), &format_right(right))
+                        }
+                        _ => $crate::diff::debug_diff(left, right),
+                    };
+
+                    panic!(
+                        "assertion failed: `{} == {}` (- left, + right)\n{}",
+                        stringify!($left),
+                        stringify!($right),
+                        diff
+                    );
+                }
+            }
+        }
+    }};
+}
+
+// A diff of two values for showing under an equality failure, when their
+// output spans lines: multi-line strings diff per line rather than as one
+// escaped line, other values by their pretty Debug output.
+pub fn multiline_diff<E: Debug + ?Sized, A: Debug + ?Sized>(expected: &E, actual: &A) -> Option<String> {
+    let expected = format!("{:#?}", expected);
+    let actual = format!("{:#?}", actual);
+
+    let (expected, actual) = match (unescape_string(&expected), unescape_string(&actual)) {
+        (Some(expected), Some(actual)) => (expected, actual),
+        _ => (expected, actual),
+    };
+
+    if !expected.contains('\n') && !actual.contains('\n') {
+        return None;
+    }
+
+    Some(format_unified_diff(&expected, &actual, 3))
+}
+
+// The contents of a string's Debug output, which escapes like JSON for the
+// characters that matter here (quotes, backslashes, newlines, tabs).
+fn unescape_string(debug: &str) -> Option<String> {
+    if debug.len() < 2 || !debug.starts_with('"') || !debug.ends_with('"') {
+        return None;
+    }
+
+    serde_json::from_str::<String>(debug).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Without the color codes, which depend on the terminal.
+    fn plain(text: &str) -> String {
+        let mut plain = String::new();
+        let mut characters = text.chars();
+
+        while let Some(character) = characters.next() {
+            if character == '\x1B' {
+                for character in characters.by_ref() {
+                    if character == 'm' {
+                        break;
+                    }
+                }
+            } else {
+                plain.push(character);
+            }
+        }
+
+        plain
+    }
+
+    fn unified(expected: &str, actual: &str, context: usize) -> String {
+        plain(&format_unified_diff(expected, actual, context))
+    }
+
+    #[test]
+    fn diffs_lines() {
+        assert_eq!(
+            diff_lines("a\nb\nc", "a\nx\nc"),
+            vec![DiffLine::Same("a"), DiffLine::Removed("b"), DiffLine::Added("x"), DiffLine::Same("c")]
+        );
+    }
+
+    #[test]
+    fn diffs_empty_inputs() {
+        assert_eq!(diff_lines("", ""), Vec::<DiffLine>::new());
+        assert_eq!(diff_lines("", "a"), vec![DiffLine::Added("a")]);
+        assert_eq!(diff_lines("a", ""), vec![DiffLine::Removed("a")]);
+        assert_eq!(format_diff("", ""), "");
+    }
+
+    #[test]
+    fn formats_diffs() {
+        assert_eq!(format_diff("a\nb", "a\nc"), "  a\n- b\n+ c\n");
+    }
+
+    #[test]
+    fn diffs_words() {
+        assert_eq!(
+            diff_words("x: 1.0, y: 2.0", "x: 1.0, y: 3.5"),
+            vec![
+                DiffLine::Same("x"),
+                DiffLine::Same(": "),
+                DiffLine::Same("1.0"),
+                DiffLine::Same(", "),
+                DiffLine::Same("y"),
+                DiffLine::Same(": "),
+                DiffLine::Removed("2.0"),
+                DiffLine::Added("3.5"),
+            ]
+        );
+        assert_eq!(diff_words("", ""), Vec::<DiffLine>::new());
+    }
+
+    #[test]
+    fn merges_hunks_whose_context_touches() {
+        let expected = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10";
+        let actual = "1\nx\n3\n4\n5\n6\n7\n8\ny\n10";
+
+        assert_eq!(
+            unified(expected, actual, 3),
+            "@@ -1,10 +1,10 @@\n  1\n- 2\n+ x\n  3\n  4\n  5\n  6\n  7\n  8\n- 9\n+ y\n  10\n"
+        );
+        assert_eq!(
+            unified(expected, actual, 2),
+            "@@ -1,4 +1,4 @@\n  1\n- 2\n+ x\n  3\n  4\n@@ -7,4 +7,4 @@\n  7\n  8\n- 9\n+ y\n  10\n"
+        );
+    }
+
+    #[test]
+    fn diffs_changes_at_the_start_and_end() {
+        assert_eq!(
+            unified("a\nb\nc\nd\ne\nf\ng\nh", "A\nb\nc\nd\ne\nf\ng\nH", 1),
+            "@@ -1,2 +1,2 @@\n- a\n+ A\n  b\n@@ -7,2 +7,2 @@\n  g\n- h\n+ H\n"
+        );
+    }
+
+    #[test]
+    fn diffs_empty_sides() {
+        assert_eq!(unified("", "a\nb", 3), "@@ -0,0 +1,2 @@\n+ a\n+ b\n");
+        assert_eq!(unified("a\nb", "", 3), "@@ -1,2 +0,0 @@\n- a\n- b\n");
+        assert_eq!(unified("", "", 3), "");
+        assert_eq!(unified("a\nb", "a\nb", 3), "");
+    }
+
+    #[test]
+    fn unescapes_only_strings() {
+        assert_eq!(unescape_string("\"a\\nb\""), Some("a\nb".to_string()));
+        assert_eq!(unescape_string("\""), None);
+        assert_eq!(unescape_string("42"), None);
+        assert_eq!(unescape_string("\"unterminated\\\""), None);
+    }
+
+    #[test]
+    fn diffs_multiline_values_only() {
+        assert_eq!(multiline_diff("a", "b"), None);
+        assert_eq!(multiline_diff(&1, &2), None);
+        assert_eq!(
+            multiline_diff("a\nb", "a\nc").map(|diff| plain(&diff)),
+            Some("@@ -1,2 +1,2 @@\n  a\n- b\n+ c\n".to_string())
+        );
+    }
+}
@@ -0,0 +1,413 @@
+// A "Tests" dock for the Godot editor. It lists the registered tests and
+// runs all of them, the selected ones or the ones that failed last time in a
+// headless child Godot, then shows each test's result and failure message.
+//
+// The child runs the scene in the `godot_rust_specs/test_scene` project
+// setting (res://tests.tscn by default), so build the extension before
+// pressing Run, the same as for a run from the command line.
+//
+// Only compiled with the `editor` feature.
+use crate::output::strip_ansi;
+use crate::screenshot::globalize_path;
+use crate::tooling::{self, LaunchOptions};
+use godot::engine::editor_plugin::DockSlot;
+use godot::engine::tree::SelectMode;
+use godot::engine::{
+    Button, EditorPlugin, HBoxContainer, IEditorPlugin, Label, Os, ProjectSettings, TextEdit, Tree,
+    TreeItem, VBoxContainer,
+};
+use godot::prelude::*;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::{Child, Stdio};
+
+const SCENE_SETTING: &str = "godot_rust_specs/test_scene";
+
+struct ListedTest {
+    name: String,
+    defined_at: String,
+}
+
+struct TestOutcome {
+    status: String,
+    failure: Option<String>,
+}
+
+enum RunKind {
+    // `--list`, to fill the dock.
+    List,
+    // The tests asked for, empty for all of them.
+    Tests(Vec<String>),
+}
+
+struct ChildRun {
+    child: Child,
+    kind: RunKind,
+    log_file: PathBuf,
+}
+
+#[derive(GodotClass)]
+#[class(tool, init, editor_plugin, base=EditorPlugin)]
+pub struct TestsPlugin {
+    #[base]
+    base: Base<EditorPlugin>,
+    dock: Option<Gd<VBoxContainer>>,
+    tree: Option<Gd<Tree>>,
+    status: Option<Gd<Label>>,
+    details: Option<Gd<TextEdit>>,
+    run: Option<ChildRun>,
+    tests: Vec<ListedTest>,
+    outcomes: HashMap<String, TestOutcome>,
+}
+
+#[godot_api]
+impl IEditorPlugin for TestsPlugin {
+    fn enter_tree(&mut self) {
+        let plugin = self.base().clone();
+        let mut dock = VBoxContainer::new_alloc();
+        dock.set_name("Tests".into());
+
+        let mut buttons = HBoxContainer::new_alloc();
+        for (text, method) in [
+            ("Refresh", "refresh"),
+            ("Run all", "run_all"),
+            ("Run selected", "run_selected"),
+            ("Run failed", "run_failed"),
+        ] {
+            let mut button = Button::new_alloc();
+            button.set_text(text.into());
+            button.connect("pressed".into(), Callable::from_object_method(&plugin, method));
+            buttons.add_child(button.upcast());
+        }
+        dock.add_child(buttons.upcast());
+
+        let mut status = Label::new_alloc();
+        dock.add_child(status.clone().upcast());
+        status.set_text("".into());
+
+        let mut tree = Tree::new_alloc();
+        tree.set_hide_root(true);
+        tree.set_select_mode(SelectMode::MULTI);
+        tree.set_v_size_flags(godot::engine::control::SizeFlags::EXPAND_FILL);
+        tree.connect("item_selected".into(), Callable::from_object_method(&plugin, "show_details"));
+        tree.connect(
+            "multi_selected".into(),
+            Callable::from_object_method(&plugin, "show_details_multi"),
+        );
+        dock.add_child(tree.clone().upcast());
+
+        let mut details = TextEdit::new_alloc();
+        details.set_editable(false);
+        details.set_custom_minimum_size(Vector2::new(0.0, 120.0));
+        dock.add_child(details.clone().upcast());
+
+        self.base_mut().add_control_to_dock(DockSlot::RIGHT_UL, dock.clone().upcast());
+
+        self.dock = Some(dock);
+        self.tree = Some(tree);
+        self.status = Some(status);
+        self.details = Some(details);
+
+        self.refresh();
+    }
+
+    fn exit_tree(&mut self) {
+        if let Some(mut run) = self.run.take() {
+            let _ = run.child.kill();
+        }
+
+        if let Some(mut dock) = self.dock.take() {
+            self.base_mut().remove_control_from_docks(dock.clone().upcast());
+            dock.queue_free();
+        }
+
+        self.tree = None;
+        self.status = None;
+        self.details = None;
+    }
+
+    fn process(&mut self, _delta: f64) {
+        let Some(run) = &mut self.run else {
+            return;
+        };
+
+        let code = match run.child.try_wait() {
+            Ok(Some(status)) => status.code(),
+            Ok(None) => return,
+            Err(_) => None,
+        };
+
+        let run = self.run.take().unwrap();
+        let log = std::fs::read_to_string(&run.log_file).map(|log| strip_ansi(&log)).unwrap_or_default();
+
+        match run.kind {
+            RunKind::List => self.finish_list(&log, code),
+            RunKind::Tests(names) => self.finish_tests(&log, code, &names),
+        }
+    }
+}
+
+#[godot_api]
+impl TestsPlugin {
+    #[func]
+    fn refresh(&mut self) {
+        self.start(RunKind::List, vec!["--list".to_string()]);
+    }
+
+    #[func]
+    fn run_all(&mut self) {
+        self.run_tests(Vec::new());
+    }
+
+    #[func]
+    fn run_selected(&mut self) {
+        let selected = self.selected_tests();
+
+        if selected.is_empty() {
+            self.set_status("Select the tests to run first");
+            return;
+        }
+
+        self.run_tests(selected);
+    }
+
+    #[func]
+    fn run_failed(&mut self) {
+        let failed: Vec<String> = self
+            .tests
+            .iter()
+            .filter(|test| self.outcomes.get(&test.name).map_or(false, |outcome| outcome.status == "failed"))
+            .map(|test| test.name.clone())
+            .collect();
+
+        if failed.is_empty() {
+            self.set_status("No failures to rerun");
+            return;
+        }
+
+        self.run_tests(failed);
+    }
+
+    #[func]
+    fn show_details(&mut self) {
+        let Some(item) = self.tree.as_ref().and_then(|tree| tree.get_selected()) else {
+            return;
+        };
+
+        let name = item.get_text(0).to_string();
+        let text = match self.outcomes.get(&name) {
+            Some(TestOutcome { failure: Some(failure), .. }) => failure.clone(),
+            Some(outcome) => format!("{} {}", name, outcome.status),
+            None => self
+                .tests
+                .iter()
+                .find(|test| test.name == name)
+                .map(|test| test.defined_at.clone())
+                .unwrap_or_default(),
+        };
+
+        if let Some(details) = &mut self.details {
+            details.set_text(text.into());
+        }
+    }
+
+    #[func]
+    fn show_details_multi(&mut self, _item: Gd<TreeItem>, _column: i64, _selected: bool) {
+        self.show_details();
+    }
+}
+
+impl TestsPlugin {
+    fn set_status(&mut self, text: &str) {
+        if let Some(status) = &mut self.status {
+            status.set_text(text.into());
+        }
+    }
+
+    fn run_tests(&mut self, names: Vec<String>) {
+        let mut args = vec!["--results-file".to_string(), results_file().display().to_string()];
+
+        // Whole names, so picking "player jumps" doesn't run "player jumps
+        // twice" too.
+        if !names.is_empty() {
+            args.push("--exact".to_string());
+        }
+
+        for name in &names {
+            args.push("--filter".to_string());
+            args.push(name.clone());
+        }
+
+        self.start(RunKind::Tests(names), args);
+    }
+
+    fn start(&mut self, kind: RunKind, user_args: Vec<String>) {
+        if self.run.is_some() {
+            self.set_status("Tests are already running");
+            return;
+        }
+
+        let options = LaunchOptions {
+            godot: PathBuf::from(Os::singleton().get_executable_path().to_string()),
+            project: PathBuf::from(globalize_path("res://")),
+            scene: test_scene(),
+            headless: true,
+            fixed_fps: None,
+            user_args,
+        };
+
+        let log_file = std::env::temp_dir().join("godot_rust_specs_editor.log");
+        let log = match std::fs::File::create(&log_file) {
+            Ok(log) => log,
+            Err(error) => {
+                self.set_status(&format!("Could not create {}: {}", log_file.display(), error));
+                return;
+            }
+        };
+
+        let _ = std::fs::remove_file(results_file());
+
+        let mut command = tooling::godot_command(&options);
+        command.stdout(log.try_clone().map(Stdio::from).unwrap_or_else(|_| Stdio::null()));
+        command.stderr(Stdio::from(log));
+
+        match command.spawn() {
+            Ok(child) => {
+                self.set_status(match kind {
+                    RunKind::List => "Listing tests...",
+                    RunKind::Tests(_) => "Running tests...",
+                });
+                self.run = Some(ChildRun { child, kind, log_file });
+            }
+            Err(error) => {
+                self.set_status(&format!("Could not launch {}: {}", options.godot.display(), error))
+            }
+        }
+    }
+
+    fn finish_list(&mut self, log: &str, code: Option<i32>) {
+        self.tests = parse_list(log);
+
+        if self.tests.is_empty() && code != Some(crate::EXIT_NO_TESTS) && code != Some(0) {
+            self.set_status(&format!("Listing tests failed (exit code {:?})", code));
+            self.show_log(log);
+        } else {
+            self.set_status(&format!("{} test(s)", self.tests.len()));
+        }
+
+        self.rebuild_tree();
+    }
+
+    fn finish_tests(&mut self, log: &str, code: Option<i32>, names: &[String]) {
+        let results = std::fs::read_to_string(results_file())
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Value>(&contents).ok());
+
+        let Some(results) = results else {
+            self.set_status(&format!("The run ended without results (exit code {:?})", code));
+            self.show_log(log);
+            return;
+        };
+
+        for test in results["tests"].as_array().into_iter().flatten() {
+            let Some(name) = test["name"].as_str() else {
+                continue;
+            };
+
+            // Only the tests asked for, even if the run included others.
+            if !names.is_empty() && !names.iter().any(|wanted| wanted == name) {
+                continue;
+            }
+
+            self.outcomes.insert(
+                name.to_string(),
+                TestOutcome {
+                    status: test["status"].as_str().unwrap_or("").to_string(),
+                    failure: test["failure"].as_str().map(str::to_string),
+                },
+            );
+        }
+
+        let suite = &results["suite"];
+        self.set_status(&format!(
+            "{} examples, {} failures",
+            suite["examples"].as_u64().unwrap_or(0),
+            suite["failures"].as_u64().unwrap_or(0)
+        ));
+
+        self.rebuild_tree();
+    }
+
+    fn show_log(&mut self, log: &str) {
+        if let Some(details) = &mut self.details {
+            details.set_text(log.into());
+        }
+    }
+
+    fn selected_tests(&self) -> Vec<String> {
+        let Some(root) = self.tree.as_ref().and_then(|tree| tree.get_root()) else {
+            return Vec::new();
+        };
+
+        root.get_children()
+            .iter_shared()
+            .filter(|item| item.is_selected(0))
+            .map(|item| item.get_text(0).to_string())
+            .collect()
+    }
+
+    fn rebuild_tree(&mut self) {
+        let Some(tree) = &mut self.tree else {
+            return;
+        };
+
+        tree.clear();
+        let root = tree.create_item().unwrap();
+
+        for test in &self.tests {
+            let mut item = tree.create_item_ex().parent(root.clone()).done().unwrap();
+            item.set_text(0, test.name.clone().into());
+            item.set_tooltip_text(0, test.defined_at.clone().into());
+
+            let color = match self.outcomes.get(&test.name).map(|outcome| outcome.status.as_str()) {
+                Some("passed") | Some("expected_failure") => Some(Color::from_rgb(0.4, 0.85, 0.4)),
+                Some("failed") => Some(Color::from_rgb(0.95, 0.35, 0.35)),
+                Some("skipped") | Some("pending") => Some(Color::from_rgb(0.9, 0.8, 0.3)),
+                _ => None,
+            };
+
+            if let Some(color) = color {
+                item.set_custom_color(0, color);
+            }
+        }
+    }
+}
+
+fn test_scene() -> String {
+    let settings = ProjectSettings::singleton();
+
+    if settings.has_setting(SCENE_SETTING.into()) {
+        settings.get_setting(SCENE_SETTING.into()).to_string()
+    } else {
+        "res://tests.tscn".to_string()
+    }
+}
+
+fn results_file() -> PathBuf {
+    std::env::temp_dir().join("godot_rust_specs_editor_results.json")
+}
+
+// Picks the `name [tags] (file:line)` lines printed by --list out of the
+// rest of Godot's output.
+fn parse_list(log: &str) -> Vec<ListedTest> {
+    let pattern = regex::Regex::new(r"^(.+?)(?: \[[^\]]*\])? \(([^()]+:\d+)\)$").unwrap();
+
+    log.lines()
+        .filter_map(|line| pattern.captures(line.trim()))
+        .map(|captures| ListedTest {
+            name: captures[1].to_string(),
+            defined_at: captures[2].to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,168 @@
+use crate::capture::OutputCapture;
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+    // Godot has no error callback we can hook, but everything reported
+    // through push_error/push_warning (and godot_error!/godot_warn!) goes to
+    // stderr, so stderr is captured while a test is running.
+    static ref STDERR_CAPTURE: Mutex<Option<OutputCapture>> = Mutex::new(None);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EngineMessageKind {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EngineMessage {
+    pub kind: EngineMessageKind,
+    // The message line plus the "   at: ..." line that follows it.
+    pub text: String,
+}
+
+pub fn parse_engine_messages(stderr: &str) -> Vec<EngineMessage> {
+    let mut messages: Vec<EngineMessage> = Vec::new();
+
+    for line in stderr.lines() {
+        let kind = if line.starts_with("ERROR:")
+            || line.starts_with("SCRIPT ERROR:")
+            || line.starts_with("USER ERROR:")
+        {
+            Some(EngineMessageKind::Error)
+        } else if line.starts_with("WARNING:")
+            || line.starts_with("SCRIPT WARNING:")
+            || line.starts_with("USER WARNING:")
+        {
+            Some(EngineMessageKind::Warning)
+        } else {
+            None
+        };
+
+        match kind {
+            Some(kind) => messages.push(EngineMessage {
+                kind,
+                text: line.to_string(),
+            }),
+            None => {
+                if line.trim_start().starts_with("at:") {
+                    if let Some(message) = messages.last_mut() {
+                        message.text.push('\n');
+                        message.text.push_str(line);
+                    }
+                }
+            }
+        }
+    }
+
+    messages
+}
+
+pub(crate) fn start_capture() {
+    let mut capture = STDERR_CAPTURE.lock().unwrap();
+
+    if capture.is_none() {
+        *capture = OutputCapture::start_stderr();
+    }
+}
+
+// Whether stderr is being captured, i.e. a test is running and the capture
+// could be set up (it can't on non-unix targets).
+pub fn is_capturing() -> bool {
+    STDERR_CAPTURE.lock().unwrap().is_some()
+}
+
+// Everything the current test wrote to stderr so far.
+pub fn captured_stderr() -> String {
+    STDERR_CAPTURE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|capture| capture.contents())
+        .unwrap_or_default()
+}
+
+// Ends the capture, returning the raw stderr output of the test.
+pub(crate) fn finish_capture() -> String {
+    let capture = STDERR_CAPTURE.lock().unwrap().take();
+    capture.map(|capture| capture.finish()).unwrap_or_default()
+}
+
+// Whether these messages should fail the test they were emitted in.
+pub(crate) fn should_fail(messages: &[EngineMessage]) -> bool {
+    let (fail_on_errors, fail_on_warnings) = crate::config::with(|config| {
+        (config.fail_on_engine_errors, config.fail_on_engine_warnings)
+    });
+
+    messages.iter().any(|message| match message.kind {
+        EngineMessageKind::Error => fail_on_errors,
+        EngineMessageKind::Warning => fail_on_warnings,
+    })
+}
+
+// Fails (when checked or dropped) if any engine error or warning was emitted
+// since it was created:
+//
+//     let _guard = NoErrorsGuard::new();
+//     spawn_wave(&root);
+//
+// Creating one fails when stderr isn't being captured, as there'd be no way
+// to tell. Which is also why there's no Default.
+pub struct NoErrorsGuard {
+    start: usize,
+    checked: bool,
+}
+
+#[allow(clippy::new_without_default)]
+impl NoErrorsGuard {
+    #[track_caller]
+    pub fn new() -> Self {
+        if !is_capturing() {
+            panic!("can't check for engine errors, stderr isn't being captured (outside a test, or not supported on this platform)");
+        }
+
+        Self {
+            start: captured_stderr().len(),
+            checked: false,
+        }
+    }
+
+    #[track_caller]
+    pub fn check(mut self) {
+        self.checked = true;
+        self.verify();
+    }
+
+    #[track_caller]
+    fn verify(&self) {
+        let stderr = captured_stderr();
+        let messages = parse_engine_messages(stderr.get(self.start..).unwrap_or(""));
+
+        if !messages.is_empty() {
+            let texts: Vec<String> = messages.into_iter().map(|message| message.text).collect();
+            panic!(
+                "expected no engine errors or warnings, but got {}:\n{}",
+                texts.len(),
+                texts.join("\n")
+            );
+        }
+    }
+}
+
+impl Drop for NoErrorsGuard {
+    fn drop(&mut self) {
+        if !self.checked && !std::thread::panicking() {
+            self.verify();
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! assert_no_errors {
+    ($($body:tt)*) => {{
+        let guard = $crate::errors::NoErrorsGuard::new();
+        let result = { $($body)* };
+        guard.check();
+        result
+    }};
+}
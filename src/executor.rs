@@ -0,0 +1,143 @@
+use crate::{TestCase, TestContext};
+use godot::engine::Engine;
+use godot::prelude::*;
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use std::time::{Duration, Instant};
+
+type TestFuture = Pin<Box<dyn Future<Output = ()>>>;
+
+thread_local! {
+    // The future of the async test that is currently running. It is polled
+    // once per frame by replaying the test, like `wait!` does.
+    static CURRENT: RefCell<Option<TestFuture>> = RefCell::new(None);
+}
+
+impl TestCase {
+    // A test written as an async closure. It runs on the main thread and is
+    // polled once per frame, so awaiting `next_frame()`, `seconds(..)` or
+    // `signal(..)` lets the engine carry on in the meantime:
+    //
+    //     register(TestCase::new_async("door_opens", |ctx| async move {
+    //         let door = ctx.load_scene::<Door>("res://door.tscn");
+    //         door.clone().bind_mut().interact();
+    //         signal(&door.clone().upcast(), "opened").await;
+    //         assert!(door.bind().is_open());
+    //     }));
+    #[track_caller]
+    pub fn new_async<F, Fut>(name: impl Into<String>, func: F) -> Self
+    where
+        F: Fn(TestContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        TestCase::new(name, move |context| run_async(context, &func))
+    }
+}
+
+fn run_async<F, Fut>(context: &TestContext, func: &F)
+where
+    F: Fn(TestContext) -> Fut,
+    Fut: Future<Output = ()> + 'static,
+{
+    let mut future = CURRENT
+        .with(|current| current.borrow_mut().take())
+        .unwrap_or_else(|| Box::pin(func(context.clone())));
+
+    let waker = noop_waker();
+    let mut poll_context = Context::from_waker(&waker);
+
+    // If this panics the future is dropped with it, failing the test.
+    match future.as_mut().poll(&mut poll_context) {
+        Poll::Ready(()) => {}
+        Poll::Pending => {
+            CURRENT.with(|current| *current.borrow_mut() = Some(future));
+            *crate::WANTS_REPLAY.lock().unwrap() = true;
+            *crate::DELAY_BEFORE_NEXT_TEST_RUN.lock().unwrap() = 0.0;
+        }
+    }
+}
+
+// Called by the runner once a test has finished.
+pub(crate) fn reset() {
+    let future = CURRENT.with(|current| current.borrow_mut().take());
+    drop(future);
+}
+
+fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    // Nothing needs waking: every pending test is polled again next frame.
+    unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) }
+}
+
+// Resolves once `poll_ready` returns true. It is asked once per frame.
+pub struct Until<F: FnMut() -> bool> {
+    poll_ready: F,
+}
+
+impl<F: FnMut() -> bool + Unpin> Future for Until<F> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+        if (self.poll_ready)() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+pub fn until<F: FnMut() -> bool + Unpin>(condition: F) -> Until<F> {
+    Until { poll_ready: condition }
+}
+
+fn process_frames() -> u64 {
+    Engine::singleton().get_process_frames()
+}
+
+pub fn frames(count: u64) -> Until<impl FnMut() -> bool + Unpin> {
+    let target = process_frames() + count;
+    until(move || process_frames() >= target)
+}
+
+pub fn next_frame() -> Until<impl FnMut() -> bool + Unpin> {
+    frames(1)
+}
+
+pub fn seconds(seconds: f64) -> Until<impl FnMut() -> bool + Unpin> {
+    let deadline = Instant::now() + Duration::from_secs_f64(seconds);
+    until(move || Instant::now() >= deadline)
+}
+
+// Resolves the next time `object` emits `signal_name`.
+pub fn signal(object: &Gd<Object>, signal_name: &str) -> Until<impl FnMut() -> bool + Unpin> {
+    if !object.has_signal(signal_name.into()) {
+        panic!("{} has no signal named {}", object.get_class(), signal_name);
+    }
+
+    let emitted = Arc::new(AtomicBool::new(false));
+    let flag = emitted.clone();
+
+    let callable = Callable::from_fn("godot_rust_specs::signal", move |_| {
+        flag.store(true, Ordering::SeqCst);
+        Ok(Variant::nil())
+    });
+
+    object
+        .clone()
+        .connect_ex(signal_name.into(), callable.clone())
+        .flags(godot::engine::object::ConnectFlags::ONE_SHOT.ord() as u32)
+        .done();
+    crate::signals::track(object, signal_name, callable);
+
+    until(move || emitted.load(Ordering::SeqCst))
+}
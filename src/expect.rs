@@ -0,0 +1,337 @@
+use crate::approx::{ApproxEq, Tolerance};
+use crate::pretty::format_with;
+use godot::prelude::*;
+use std::fmt::Debug;
+
+// Entry point of the fluent assertion API. Prefer the `expect!` macro, which
+// also records the expression text for failure messages:
+//
+//     expect!(player.get_health()).to_eq(100);
+//     expect!(items).not().to_contain(&sword);
+pub struct Expectation<T> {
+    value: T,
+    expression: String,
+    negated: bool,
+    // Set by expect! for types with a Pretty impl.
+    formatter: Option<fn(&T) -> String>,
+}
+
+pub fn expect<T>(value: T) -> Expectation<T> {
+    Expectation::new(value, "value")
+}
+
+impl<T> Expectation<T> {
+    pub fn new(value: T, expression: &str) -> Self {
+        Self {
+            value,
+            expression: expression.to_string(),
+            negated: false,
+            formatter: None,
+        }
+    }
+
+    pub fn with_formatter(mut self, formatter: Option<fn(&T) -> String>) -> Self {
+        self.formatter = formatter;
+        self
+    }
+
+    pub fn not(mut self) -> Self {
+        self.negated = !self.negated;
+        self
+    }
+
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    // Fails unless `passed` (inverted by `not()`). The negation only applies
+    // to the matcher it precedes.
+    #[track_caller]
+    fn check(self, passed: bool, description: String, actual: String) -> Self {
+        self.check_with_diff(passed, description, actual, None)
+    }
+
+    #[track_caller]
+    fn check_with_diff(mut self, passed: bool, description: String, actual: String, diff: Option<String>) -> Self {
+        if passed == self.negated {
+            let not = if self.negated { "not " } else { "" };
+            match diff {
+                Some(diff) => panic!(
+                    "expected `{}` to {}\n  actual: {}\n(- expected, + actual)\n{}",
+                    self.expression, description, actual, diff
+                ),
+                None => panic!(
+                    "expected `{}` {}to {}\n  actual: {}",
+                    self.expression, not, description, actual
+                ),
+            }
+        }
+
+        self.negated = false;
+        self
+    }
+}
+
+impl<T: Debug> Expectation<T> {
+    // Checks the value against any matcher, built in or user defined:
+    //
+    //     expect!(enemy).to(have_health(100));
+    #[track_caller]
+    pub fn to<M: Matcher<T>>(self, matcher: M) -> Self {
+        let passed = matcher.matches(&self.value);
+        let actual = matcher
+            .describe_actual(&self.value)
+            .unwrap_or_else(|| format_with(&self.value, self.formatter));
+        let diff = if passed { None } else { matcher.diff(&self.value) };
+        self.check_with_diff(passed, matcher.description(), actual, diff)
+    }
+
+    #[track_caller]
+    pub fn to_eq<U: Debug>(self, expected: U) -> Self
+    where
+        T: PartialEq<U>,
+    {
+        self.to(Equal(expected))
+    }
+
+    #[track_caller]
+    pub fn to_be_near(self, expected: T, epsilon: f64) -> Self
+    where
+        T: ApproxEq,
+    {
+        self.to(BeNear { expected, epsilon })
+    }
+
+    #[track_caller]
+    pub fn to_contain<I: Debug + ?Sized>(self, item: &I) -> Self
+    where
+        T: Contains<I>,
+    {
+        self.to(Contain(item))
+    }
+
+    #[track_caller]
+    pub fn to_be_true(self) -> Self
+    where
+        T: PartialEq<bool>,
+    {
+        self.to_eq(true)
+    }
+
+    #[track_caller]
+    pub fn to_be_false(self) -> Self
+    where
+        T: PartialEq<bool>,
+    {
+        self.to_eq(false)
+    }
+}
+
+// A reusable assertion that plugs into `expect!(...).to(...)`, including
+// `not()` and the standard failure message:
+//
+//     expected `<expression>` [not ]to <description>
+//       actual: <describe_actual, or the value's Pretty/Debug output>
+pub trait Matcher<T: ?Sized> {
+    fn matches(&self, actual: &T) -> bool;
+
+    // Completes the sentence "expected `x` to ...", e.g. "have 100 health".
+    fn description(&self) -> String;
+
+    fn describe_actual(&self, _actual: &T) -> Option<String> {
+        None
+    }
+
+    // Shown below the failure, e.g. a line diff of expected and actual text.
+    fn diff(&self, _actual: &T) -> Option<String> {
+        None
+    }
+}
+
+// Builds a matcher from a closure, for one-off domain matchers:
+//
+//     fn have_health(health: i32) -> impl Matcher<Gd<Enemy>> {
+//         satisfy(format!("have {} health", health), move |enemy| enemy.bind().health == health)
+//     }
+pub fn satisfy<T: ?Sized, F: Fn(&T) -> bool>(
+    description: impl Into<String>,
+    predicate: F,
+) -> Satisfy<F> {
+    Satisfy {
+        description: description.into(),
+        predicate,
+    }
+}
+
+pub struct Satisfy<F> {
+    description: String,
+    predicate: F,
+}
+
+impl<T: ?Sized, F: Fn(&T) -> bool> Matcher<T> for Satisfy<F> {
+    fn matches(&self, actual: &T) -> bool {
+        (self.predicate)(actual)
+    }
+
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+}
+
+pub struct Equal<U>(pub U);
+
+impl<T: PartialEq<U> + Debug, U: Debug> Matcher<T> for Equal<U> {
+    fn matches(&self, actual: &T) -> bool {
+        *actual == self.0
+    }
+
+    fn description(&self) -> String {
+        format!("eq {:?}", self.0)
+    }
+
+    fn diff(&self, actual: &T) -> Option<String> {
+        crate::diff::multiline_diff(&self.0, actual)
+    }
+}
+
+pub struct BeNear<T> {
+    pub expected: T,
+    pub epsilon: f64,
+}
+
+impl<T: ApproxEq + Debug> Matcher<T> for BeNear<T> {
+    fn matches(&self, actual: &T) -> bool {
+        actual
+            .diverging_component(&self.expected, Tolerance::Absolute(self.epsilon))
+            .is_none()
+    }
+
+    fn description(&self) -> String {
+        format!("be near {:?} (epsilon {})", self.expected, self.epsilon)
+    }
+
+    fn describe_actual(&self, actual: &T) -> Option<String> {
+        let component =
+            actual.diverging_component(&self.expected, Tolerance::Absolute(self.epsilon));
+
+        Some(match component {
+            Some(component) => format!("{:?} (diverged at {})", actual, component),
+            None => format!("{:?}", actual),
+        })
+    }
+}
+
+pub struct Contain<'a, I: ?Sized>(pub &'a I);
+
+impl<'a, T: Contains<I>, I: Debug + ?Sized> Matcher<T> for Contain<'a, I> {
+    fn matches(&self, actual: &T) -> bool {
+        actual.contains_item(self.0)
+    }
+
+    fn description(&self) -> String {
+        format!("contain {:?}", self.0)
+    }
+}
+
+impl<C: GodotClass + Inherits<Object>> Expectation<Gd<C>> {
+    #[track_caller]
+    pub fn to_be_instance_of<U: GodotClass>(self) -> Self {
+        let object = self.value.clone().upcast::<Object>();
+        let passed = object.is_instance_valid() && object.is_class(U::class_name().to_string().into());
+        let actual = crate::pretty::object(&object);
+        self.check(
+            passed,
+            format!("be an instance of {} ({})", U::class_name(), std::any::type_name::<U>()),
+            actual,
+        )
+    }
+}
+
+// Casts to `T`, or fails naming the expression, the expected Rust type and
+// what the object actually is. Used by assert_is!.
+#[track_caller]
+pub fn assert_is<T, C>(value: Gd<C>, expression: &str) -> Gd<T>
+where
+    T: GodotClass + Inherits<Object>,
+    C: GodotClass + Inherits<Object>,
+{
+    let object = value.upcast::<Object>();
+
+    if !object.is_instance_valid() {
+        panic!(
+            "expected `{}` to be a {} ({}), but it was freed",
+            expression,
+            T::class_name(),
+            std::any::type_name::<T>()
+        );
+    }
+
+    object.try_cast::<T>().unwrap_or_else(|object| {
+        panic!(
+            "expected `{}` to be a {} ({}), but it is a {}",
+            expression,
+            T::class_name(),
+            std::any::type_name::<T>(),
+            crate::pretty::object(&object)
+        )
+    })
+}
+
+pub trait Contains<Item: ?Sized> {
+    fn contains_item(&self, item: &Item) -> bool;
+}
+
+impl Contains<str> for String {
+    fn contains_item(&self, item: &str) -> bool {
+        self.contains(item)
+    }
+}
+
+impl Contains<str> for &str {
+    fn contains_item(&self, item: &str) -> bool {
+        self.contains(item)
+    }
+}
+
+impl Contains<str> for GString {
+    fn contains_item(&self, item: &str) -> bool {
+        self.to_string().contains(item)
+    }
+}
+
+impl<T: PartialEq> Contains<T> for Vec<T> {
+    fn contains_item(&self, item: &T) -> bool {
+        self.contains(item)
+    }
+}
+
+impl<T: PartialEq> Contains<T> for &[T] {
+    fn contains_item(&self, item: &T) -> bool {
+        self.contains(item)
+    }
+}
+
+impl<T: GodotType + PartialEq> Contains<T> for Array<T> {
+    fn contains_item(&self, item: &T) -> bool {
+        self.iter_shared().any(|element| element == *item)
+    }
+}
+
+// let player = assert_is!(node, Player);
+#[macro_export]
+macro_rules! assert_is {
+    ($value:expr, $class:ty) => {
+        $crate::expect::assert_is::<$class, _>($value.clone(), stringify!($value))
+    };
+}
+
+#[macro_export]
+macro_rules! expect {
+    ($value:expr) => {{
+        #[allow(unused_imports)]
+        use $crate::pretty::{ViaDebug, ViaPretty};
+        let value = $value;
+        let formatter = (&$crate::pretty::Formatter::of(&value)).formatter();
+        $crate::expect::Expectation::new(value, stringify!($value)).with_formatter(formatter)
+    }};
+}
@@ -0,0 +1,161 @@
+use crate::leaks::ObjectCounts;
+use crate::TestContext;
+use godot::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+thread_local! {
+    // Built by shared_fixture, outside the tree, until the suite ends.
+    static SHARED: RefCell<HashMap<String, Gd<Node>>> = RefCell::new(HashMap::new());
+    // Objects the current test's shared_fixture calls created, which stay
+    // around on purpose and so aren't leaks.
+    static RETAINED: Cell<ObjectCounts> = Cell::new(ObjectCounts::default());
+}
+
+// Builds the fixture called `name` the first time any test asks for it and
+// returns that same instance after that, like a before_all for the whole
+// suite. It isn't in the tree and shouldn't be changed; give each test its
+// own copy with duplicate_fixture:
+//
+//     let level = shared_fixture("big_level", || build_big_level());
+//     let level = ctx.duplicate_fixture(&level);
+pub fn shared_fixture<T>(name: &str, build: impl FnOnce() -> Gd<T>) -> Gd<T>
+where
+    T: GodotClass + Inherits<Node>,
+{
+    let existing = SHARED.with(|shared| shared.borrow().get(name).cloned());
+
+    let fixture = match existing {
+        Some(fixture) => fixture,
+        None => {
+            let before = ObjectCounts::current();
+            let fixture = build().upcast::<Node>();
+            let built = ObjectCounts::current() - before;
+
+            RETAINED.with(|retained| {
+                let total = retained.get();
+                retained.set(ObjectCounts {
+                    objects: total.objects + built.objects,
+                    resources: total.resources + built.resources,
+                    orphan_nodes: total.orphan_nodes + built.orphan_nodes,
+                })
+            });
+            SHARED.with(|shared| shared.borrow_mut().insert(name.to_string(), fixture.clone()));
+            fixture
+        }
+    };
+
+    fixture.try_cast::<T>().unwrap_or_else(|fixture| {
+        panic!("shared_fixture: {:?} is a {}, not a {}", name, fixture.get_class(), T::class_name())
+    })
+}
+
+// A deep copy of `fixture` (which needn't be in the tree), with its
+// scripts, groups, exported and stored properties. On top of what
+// Node::duplicate does:
+//
+// - nodes owned by something inside the fixture are owned by the matching
+//   copy, so the duplicate can be saved as a scene or snapshotted like the
+//   original;
+// - signal connections are copied, not only the persistent ones the editor
+//   makes, with those between nodes of the fixture pointing at the copies.
+pub fn duplicate_fixture<T>(fixture: &Gd<T>) -> Gd<T>
+where
+    T: GodotClass + Inherits<Node>,
+{
+    let original = fixture.clone().upcast::<Node>();
+    let copy = original
+        .duplicate()
+        .unwrap_or_else(|| panic!("duplicate_fixture: couldn't duplicate {}", original.get_name()));
+
+    let mut nodes = vec![original.clone()];
+    descendants(&original, &mut nodes);
+
+    let counterpart = |node: &Gd<Node>| -> Option<Gd<Node>> {
+        if *node == original {
+            return Some(copy.clone());
+        }
+        if !original.is_ancestor_of(node.clone()) {
+            return None;
+        }
+        copy.get_node_or_null(original.get_path_to(node.clone()))
+    };
+
+    for node in &nodes {
+        let Some(mut node_copy) = counterpart(node) else {
+            continue;
+        };
+
+        if let Some(owner) = node.get_owner().and_then(|owner| counterpart(&owner)) {
+            if node_copy.get_owner().as_ref() != Some(&owner) {
+                node_copy.set_owner(owner);
+            }
+        }
+
+        for signal in node.get_signal_list().iter_shared() {
+            let Some(signal) = signal.get("name").map(|name| name.to_string()) else {
+                continue;
+            };
+
+            for connection in node.get_signal_connection_list(signal.as_str().into()).iter_shared() {
+                let callable = connection.get("callable").and_then(|callable| callable.try_to::<Callable>().ok());
+                let Some(callable) = callable else {
+                    continue;
+                };
+                let flags = connection.get("flags").and_then(|flags| flags.try_to::<u32>().ok()).unwrap_or(0);
+
+                let target = callable
+                    .object()
+                    .and_then(|object| object.try_cast::<Node>().ok())
+                    .and_then(|target| counterpart(&target));
+                let callable = match (target, callable.method_name()) {
+                    (Some(target), Some(method)) => Callable::from_object_method(&target, method),
+                    _ => callable,
+                };
+
+                if !node_copy.is_connected(signal.as_str().into(), callable.clone()) {
+                    node_copy.connect_ex(signal.as_str().into(), callable).flags(flags).done();
+                }
+            }
+        }
+    }
+
+    copy.try_cast::<T>().unwrap_or_else(|copy| {
+        panic!("duplicate_fixture: the copy is a {}, not a {}", copy.get_class(), T::class_name())
+    })
+}
+
+impl TestContext {
+    // duplicate_fixture, added under the test's root.
+    pub fn duplicate_fixture<T>(&self, fixture: &Gd<T>) -> Gd<T>
+    where
+        T: GodotClass + Inherits<Node>,
+    {
+        let copy = duplicate_fixture(fixture);
+        self.root().clone().add_child(copy.clone().upcast());
+        copy
+    }
+}
+
+fn descendants(node: &Gd<Node>, found: &mut Vec<Gd<Node>>) {
+    for child in node.get_children().iter_shared() {
+        found.push(child.clone());
+        descendants(&child, found);
+    }
+}
+
+// Called by the runner when a test finishes, for the leak check.
+pub(crate) fn take_retained() -> ObjectCounts {
+    RETAINED.with(|retained| retained.replace(ObjectCounts::default()))
+}
+
+// Called by the runner when the suite ends.
+pub(crate) fn free_all() {
+    let shared = SHARED.with(|shared| std::mem::take(&mut *shared.borrow_mut()));
+
+    for (_, mut fixture) in shared {
+        if fixture.is_instance_valid() {
+            fixture.free();
+        }
+    }
+}
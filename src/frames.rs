@@ -0,0 +1,65 @@
+use godot::engine::global::Error;
+use godot::engine::{Image, Viewport};
+use godot::prelude::*;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+thread_local! {
+    // The current test's last `record_frames` frames. Thread local as Gd
+    // isn't Send.
+    static FRAMES: RefCell<VecDeque<Gd<Image>>> = RefCell::new(VecDeque::new());
+}
+
+// Called by the runner every frame a test is running. Reading the frame back
+// from the GPU isn't free, so this only happens with record_frames set (and
+// does nothing headless, where nothing is rendered).
+pub(crate) fn capture(viewport: Gd<Viewport>) {
+    let Some(limit) = crate::config::with(|config| config.record_frames) else {
+        return;
+    };
+
+    let Some(image) = viewport.get_texture().and_then(|texture| texture.get_image()) else {
+        return;
+    };
+
+    if image.is_empty() {
+        return;
+    }
+
+    FRAMES.with(|frames| {
+        let mut frames = frames.borrow_mut();
+        frames.push_back(image);
+        while frames.len() > limit {
+            frames.pop_front();
+        }
+    });
+}
+
+// The recorded frames, taken by the runner when the test finishes.
+pub(crate) fn take() -> Vec<Gd<Image>> {
+    FRAMES.with(|frames| frames.borrow_mut().drain(..).collect())
+}
+
+// Writes the frames as `<artifacts_dir>/<test>/frames/0001.png` and so on,
+// returning the directory. `ffmpeg -framerate 60 -i %04d.png out.mp4` turns
+// them into a video.
+pub(crate) fn save(test_name: &str, frames: &[Gd<Image>]) -> Option<String> {
+    if frames.is_empty() {
+        return None;
+    }
+
+    let dir = crate::artifacts::path_for(test_name, "frames");
+
+    // Frames of an earlier failure shouldn't mix with these.
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).ok()?;
+
+    for (index, image) in frames.iter().enumerate() {
+        let path = format!("{}/{:04}.png", dir, index + 1);
+        if image.save_png(path.into()) != Error::OK {
+            return None;
+        }
+    }
+
+    Some(dir)
+}
@@ -0,0 +1,347 @@
+use crate::errors::{captured_stderr, parse_engine_messages, EngineMessageKind};
+use crate::input;
+use crate::panics::catch_expected_panic;
+use crate::random::{current_seed, TestRng};
+use crate::scene::SceneFixtures;
+use crate::screenshot::globalize_path;
+use crate::state::test_state;
+use crate::{TestCase, TestContext};
+use godot::engine::global::MouseButton;
+use godot::engine::{Input, InputEventAction};
+use godot::prelude::*;
+use serde_json::{json, Value};
+
+// One generated input. Stored as data (rather than Gd<InputEvent>) so a
+// failing sequence can be written out and replayed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FuzzEvent {
+    Action { name: String, pressed: bool },
+    MouseMotion { position: Vector2 },
+    MouseButton { position: Vector2, right: bool, pressed: bool },
+}
+
+// Drives a scene with random input for a number of frames:
+//
+//     fuzz_test!(
+//         "player_survives_random_input",
+//         Fuzz::new("res://player.tscn")
+//             .frames(600)
+//             .actions(&["move_left", "move_right", "jump"]),
+//         |ctx, player| assert!(player.get("position").to::<Vector2>().y < 1000.0)
+//     );
+//
+// The test fails on a panic in the invariant, or on an engine error (which
+// includes panics in Rust callbacks, as gdext reports those as errors). The
+// sequence up to the failing frame is written to
+// `<artifacts_dir>/<test>/fuzz.json`, and `.replay(path)` runs exactly that
+// sequence again instead of a random one.
+#[derive(Clone, Debug)]
+pub struct Fuzz {
+    scene: String,
+    frames: usize,
+    actions: Vec<String>,
+    mouse_area: Option<Rect2>,
+    event_chance: f64,
+    replay: Option<String>,
+}
+
+impl Fuzz {
+    pub fn new(scene: &str) -> Self {
+        Self {
+            scene: scene.to_string(),
+            frames: 300,
+            actions: Vec::new(),
+            mouse_area: None,
+            event_chance: 0.3,
+            replay: None,
+        }
+    }
+
+    pub fn frames(mut self, frames: usize) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    // Input map actions to press and release.
+    pub fn actions(mut self, actions: &[&str]) -> Self {
+        self.actions = actions.iter().map(|action| action.to_string()).collect();
+        self
+    }
+
+    // Also move and click the mouse within `area` (viewport coordinates).
+    pub fn mouse(mut self, area: Rect2) -> Self {
+        self.mouse_area = Some(area);
+        self
+    }
+
+    // How likely a frame is to get an event, 0 to 1.
+    pub fn event_chance(mut self, chance: f64) -> Self {
+        self.event_chance = chance.clamp(0.0, 1.0);
+        self
+    }
+
+    // Replays a sequence written by a failed run.
+    pub fn replay(mut self, path: &str) -> Self {
+        self.replay = Some(path.to_string());
+        self
+    }
+
+    fn generate(&self, rng: &mut TestRng) -> Vec<Vec<FuzzEvent>> {
+        let mut held: Vec<String> = Vec::new();
+        let mut mouse_held = None;
+        let mut sequence = Vec::with_capacity(self.frames);
+
+        for _ in 0..self.frames {
+            let mut events = Vec::new();
+
+            if rng.next_f64() < self.event_chance {
+                let use_mouse = match (self.mouse_area, self.actions.is_empty()) {
+                    (None, _) => false,
+                    (Some(_), true) => true,
+                    (Some(_), false) => rng.next_bool(),
+                };
+
+                if use_mouse {
+                    let area = self.mouse_area.unwrap();
+                    let position = Vector2::new(
+                        rng.range_f64(area.position.x as f64, (area.position.x + area.size.x) as f64) as f32,
+                        rng.range_f64(area.position.y as f64, (area.position.y + area.size.y) as f64) as f32,
+                    );
+
+                    events.push(FuzzEvent::MouseMotion { position });
+
+                    if rng.next_f64() < 0.5 {
+                        let event = match mouse_held.take() {
+                            Some(right) => FuzzEvent::MouseButton { position, right, pressed: false },
+                            None => {
+                                let right = rng.next_f64() < 0.2;
+                                mouse_held = Some(right);
+                                FuzzEvent::MouseButton { position, right, pressed: true }
+                            }
+                        };
+                        events.push(event);
+                    }
+                } else if let Some(action) = rng.choose(&self.actions) {
+                    let pressed = !held.contains(action);
+                    if pressed {
+                        held.push(action.clone());
+                    } else {
+                        held.retain(|held| held != action);
+                    }
+                    events.push(FuzzEvent::Action { name: action.clone(), pressed });
+                }
+            }
+
+            sequence.push(events);
+        }
+
+        sequence
+    }
+}
+
+#[derive(Default)]
+struct FuzzState {
+    scene: Option<Gd<Node>>,
+    sequence: Vec<Vec<FuzzEvent>>,
+    frame: usize,
+    stderr_checked: usize,
+    held_actions: Vec<String>,
+    mouse_position: Option<Vector2>,
+}
+
+#[track_caller]
+pub fn fuzz_case<F>(name: impl Into<String>, fuzz: Fuzz, invariant: F) -> TestCase
+where
+    F: Fn(&TestContext, &Gd<Node>) + Send + Sync + 'static,
+{
+    TestCase::new(name, move |context| {
+        let failure = test_state(|state: &mut FuzzState| run_frame(context, &fuzz, &invariant, state));
+
+        match failure {
+            None => {}
+            Some(None) => {
+                *crate::WANTS_REPLAY.lock().unwrap() = true;
+                *crate::DELAY_BEFORE_NEXT_TEST_RUN.lock().unwrap() = 0.0;
+            }
+            Some(Some(message)) => panic!("{}", message),
+        }
+    })
+}
+
+// None when the sequence is done, Some(None) to keep going and
+// Some(Some(message)) on failure.
+fn run_frame<F>(context: &TestContext, fuzz: &Fuzz, invariant: &F, state: &mut FuzzState) -> Option<Option<String>>
+where
+    F: Fn(&TestContext, &Gd<Node>),
+{
+    if state.scene.is_none() {
+        state.sequence = match &fuzz.replay {
+            Some(path) => load_sequence(path),
+            None => fuzz.generate(&mut TestRng::new(current_seed())),
+        };
+        state.stderr_checked = captured_stderr().len();
+        state.scene = Some(context.load_scene::<Node>(&fuzz.scene));
+    }
+
+    let scene = state.scene.clone().unwrap();
+    let frame = state.frame;
+
+    if frame >= state.sequence.len() {
+        release_held(state);
+        return None;
+    }
+
+    for event in state.sequence[frame].clone() {
+        send(&event, state);
+    }
+    state.frame += 1;
+
+    let mut failure = catch_expected_panic(|| invariant(context, &scene))
+        .map(|message| format!("invariant failed: {}", message));
+
+    let stderr = captured_stderr();
+    let errors: Vec<String> = parse_engine_messages(stderr.get(state.stderr_checked..).unwrap_or(""))
+        .into_iter()
+        .filter(|message| message.kind == EngineMessageKind::Error)
+        .map(|message| message.text)
+        .collect();
+    state.stderr_checked = stderr.len();
+
+    if failure.is_none() && !errors.is_empty() {
+        failure = Some(format!("engine error:\n{}", errors.join("\n")));
+    }
+
+    let failure = failure?;
+
+    release_held(state);
+
+    let saved = save_sequence(context.name(), &state.sequence[..=frame]);
+    let replay = match &saved {
+        Some(path) => format!("sequence saved to {} (replay it with Fuzz::replay)", path),
+        None => "the sequence could not be saved".to_string(),
+    };
+
+    Some(Some(format!(
+        "fuzzing {} failed on frame {} of {} (seed {})\n{}\n{}",
+        fuzz.scene,
+        frame + 1,
+        state.sequence.len(),
+        current_seed(),
+        failure,
+        replay
+    )))
+}
+
+fn send(event: &FuzzEvent, state: &mut FuzzState) {
+    match event {
+        FuzzEvent::Action { name, pressed } => {
+            state.held_actions.retain(|held| held != name);
+            if *pressed {
+                state.held_actions.push(name.clone());
+            }
+
+            let mut event = InputEventAction::new_gd();
+            event.set_action(name.into());
+            event.set_pressed(*pressed);
+            event.set_strength(if *pressed { 1.0 } else { 0.0 });
+            input::send_event(event.upcast());
+        }
+        FuzzEvent::MouseMotion { position } => {
+            let relative = *position - state.mouse_position.unwrap_or(*position);
+            state.mouse_position = Some(*position);
+            input::mouse_move(*position, relative);
+        }
+        FuzzEvent::MouseButton { position, right, pressed } => {
+            let button = if *right { MouseButton::RIGHT } else { MouseButton::LEFT };
+            if *pressed {
+                input::mouse_press(*position, button);
+            } else {
+                input::mouse_release(*position, button);
+            }
+        }
+    }
+}
+
+// Mouse buttons are released by input::reset; actions aren't tracked there.
+fn release_held(state: &mut FuzzState) {
+    for name in std::mem::take(&mut state.held_actions) {
+        Input::singleton().action_release(name.into());
+    }
+}
+
+fn event_to_json(event: &FuzzEvent) -> Value {
+    match event {
+        FuzzEvent::Action { name, pressed } => json!({ "action": name, "pressed": pressed }),
+        FuzzEvent::MouseMotion { position } => json!({ "mouse_motion": [position.x, position.y] }),
+        FuzzEvent::MouseButton { position, right, pressed } => json!({
+            "mouse_button": if *right { "right" } else { "left" },
+            "position": [position.x, position.y],
+            "pressed": pressed,
+        }),
+    }
+}
+
+fn event_from_json(value: &Value) -> Option<FuzzEvent> {
+    let vector = |value: &Value| {
+        let array = value.as_array()?;
+        Some(Vector2::new(array.first()?.as_f64()? as f32, array.get(1)?.as_f64()? as f32))
+    };
+
+    if let Some(name) = value.get("action").and_then(Value::as_str) {
+        let pressed = value.get("pressed")?.as_bool()?;
+        Some(FuzzEvent::Action { name: name.to_string(), pressed })
+    } else if let Some(position) = value.get("mouse_motion") {
+        Some(FuzzEvent::MouseMotion { position: vector(position)? })
+    } else if let Some(button) = value.get("mouse_button").and_then(Value::as_str) {
+        Some(FuzzEvent::MouseButton {
+            position: vector(value.get("position")?)?,
+            right: button == "right",
+            pressed: value.get("pressed")?.as_bool()?,
+        })
+    } else {
+        None
+    }
+}
+
+fn save_sequence(test_name: &str, sequence: &[Vec<FuzzEvent>]) -> Option<String> {
+    let frames: Vec<Value> = sequence
+        .iter()
+        .map(|events| Value::Array(events.iter().map(event_to_json).collect()))
+        .collect();
+    let contents = json!({ "test": test_name, "seed": current_seed(), "frames": frames });
+
+    let path = crate::artifacts::path_for(test_name, "fuzz.json");
+
+    std::fs::write(&path, serde_json::to_string_pretty(&contents).unwrap_or_default()).ok()?;
+    Some(path)
+}
+
+fn load_sequence(path: &str) -> Vec<Vec<FuzzEvent>> {
+    let path = globalize_path(path);
+    let contents = std::fs::read_to_string(&path)
+        .unwrap_or_else(|error| panic!("Fuzz::replay: could not read {}: {}", path, error));
+    let contents: Value = serde_json::from_str(&contents)
+        .unwrap_or_else(|error| panic!("Fuzz::replay: {} is not valid JSON: {}", path, error));
+
+    let frames = contents
+        .get("frames")
+        .and_then(Value::as_array)
+        .unwrap_or_else(|| panic!("Fuzz::replay: {} has no \"frames\"", path));
+
+    frames
+        .iter()
+        .map(|events| {
+            events
+                .as_array()
+                .map(|events| events.iter().filter_map(event_from_json).collect())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+#[macro_export]
+macro_rules! fuzz_test {
+    ($name:expr, $fuzz:expr, $invariant:expr) => {{
+        $crate::register($crate::fuzz::fuzz_case($name, $fuzz, $invariant).module_path(module_path!()));
+    }};
+}
@@ -0,0 +1,206 @@
+use crate::errors::{captured_stderr, parse_engine_messages, EngineMessageKind};
+use crate::state::test_state;
+use crate::{TestCase, TestContext, DELAY_BEFORE_NEXT_TEST_RUN, WANTS_REPLAY};
+use godot::engine::{DirAccess, Script};
+use godot::prelude::*;
+use std::cell::RefCell;
+
+thread_local! {
+    // What an awaiting test method returned in the end, set by its
+    // GDScriptFunctionState's `completed` signal.
+    static COMPLETED: RefCell<Option<Variant>> = RefCell::new(None);
+}
+
+// Tests written in GDScript, run by the same runner as the Rust ones. Every
+// script under `gdscript_tests_dir` is instantiated once per test, and each
+// of its methods named `test_*` becomes a test called `<script>::<method>`:
+//
+//     # res://tests/player_test.gd
+//     extends Node
+//
+//     func test_jumps(root: Node) -> void:
+//         var player = preload("res://player.tscn").instantiate()
+//         root.add_child(player)
+//         player.jump()
+//         await get_tree().physics_frame
+//         assert(player.position.y < 0)
+//
+// The method gets the test root if it takes an argument; a Node script is
+// also added under the root. It fails when it logs an engine error (a failed
+// assert, a script error, push_error), returns false, or returns a
+// non-empty String, which is used as the failure message. Methods that await
+// keep the test running until they return.
+pub(crate) fn register_scripts() {
+    let Some(dir) = crate::config::with(|config| config.gdscript_tests_dir.clone()) else {
+        return;
+    };
+
+    let mut scripts = Vec::new();
+    find_scripts(&dir, &mut scripts);
+    scripts.sort();
+
+    for path in scripts {
+        let Some(script) = godot::engine::try_load::<Script>(path.as_str()) else {
+            crate::println_red!("Could not load GDScript tests from {}", path);
+            continue;
+        };
+
+        let file: &'static str = Box::leak(path.clone().into_boxed_str());
+
+        for method in script.get_script_method_list().iter_shared() {
+            let name = method.get("name").map(|name| name.to_string()).unwrap_or_default();
+            if !name.starts_with("test_") {
+                continue;
+            }
+
+            let takes_root = method
+                .get("args")
+                .and_then(|args| args.try_to::<VariantArray>().ok())
+                .map_or(false, |args| !args.is_empty());
+
+            let method = name.clone();
+            let mut test = TestCase::new(format!("{}::{}", path, name), move |context| {
+                run(context, file, &method, takes_root)
+            });
+            test.file = file;
+            test.line = 0;
+
+            crate::register(test.tags(&["gdscript"]));
+        }
+    }
+}
+
+fn find_scripts(dir: &str, scripts: &mut Vec<String>) {
+    let Some(access) = DirAccess::open(dir.into()) else {
+        crate::println_red!("gdscript_tests_dir {} can't be opened", dir);
+        return;
+    };
+
+    let dir = dir.trim_end_matches('/');
+
+    for file in access.get_files().to_vec() {
+        if file.to_string().ends_with(".gd") {
+            scripts.push(format!("{}/{}", dir, file));
+        }
+    }
+
+    for sub_dir in access.get_directories().to_vec() {
+        find_scripts(&format!("{}/{}", dir, sub_dir), scripts);
+    }
+}
+
+#[derive(Default)]
+struct ScriptTest {
+    instance: Option<Gd<Object>>,
+    // The GDScriptFunctionState of a method that is awaiting.
+    awaiting: Option<Gd<Object>>,
+}
+
+impl Drop for ScriptTest {
+    fn drop(&mut self) {
+        // Nodes go with the test root and RefCounted frees itself.
+        if let Some(instance) = self.instance.take() {
+            if instance.is_instance_valid()
+                && !instance.is_class("Node".into())
+                && !instance.is_class("RefCounted".into())
+            {
+                instance.free();
+            }
+        }
+    }
+}
+
+fn run(context: &TestContext, script_path: &str, method: &str, takes_root: bool) {
+    let errors_from = captured_stderr().len();
+
+    let returned = test_state(|state: &mut ScriptTest| {
+        if let Some(function_state) = &state.awaiting {
+            if function_state.is_instance_valid()
+                && function_state.clone().call("is_valid".into(), &[]).to::<bool>()
+            {
+                return None;
+            }
+
+            state.awaiting = None;
+            return Some(COMPLETED.with(|completed| completed.borrow_mut().take()).unwrap_or_default());
+        }
+
+        let mut instance = instantiate(context, script_path);
+        state.instance = Some(instance.clone());
+
+        let args = if takes_root { vec![context.root().to_variant()] } else { Vec::new() };
+        let returned = instance.call(method.into(), &args);
+
+        match returned.try_to::<Gd<Object>>() {
+            Ok(function_state) if function_state.is_class("GDScriptFunctionState".into()) => {
+                COMPLETED.with(|completed| *completed.borrow_mut() = None);
+
+                let callable = Callable::from_fn("godot_rust_specs::gdscript_completed", |args| {
+                    let value = args.first().map(|value| (*value).clone()).unwrap_or_default();
+                    COMPLETED.with(|completed| *completed.borrow_mut() = Some(value));
+                    Ok(Variant::nil())
+                });
+                function_state.clone().connect("completed".into(), callable.clone());
+                crate::signals::track(&function_state, "completed", callable);
+
+                state.awaiting = Some(function_state);
+                None
+            }
+            _ => Some(returned),
+        }
+    });
+
+    fail_on_script_errors(errors_from);
+
+    match returned {
+        // Still awaiting, check again next frame.
+        None => {
+            *WANTS_REPLAY.lock().unwrap() = true;
+            *DELAY_BEFORE_NEXT_TEST_RUN.lock().unwrap() = 0.0;
+        }
+        Some(value) => check_returned(method, &value),
+    }
+}
+
+fn instantiate(context: &TestContext, script_path: &str) -> Gd<Object> {
+    let script = godot::engine::try_load::<Script>(script_path)
+        .unwrap_or_else(|| panic!("could not load {}", script_path));
+
+    let instance = script
+        .clone()
+        .call("new".into(), &[])
+        .try_to::<Gd<Object>>()
+        .unwrap_or_else(|_| panic!("could not instantiate {}", script_path));
+
+    if let Ok(node) = instance.clone().try_cast::<Node>() {
+        context.root().clone().add_child(node);
+    }
+
+    instance
+}
+
+fn fail_on_script_errors(errors_from: usize) {
+    let stderr = captured_stderr();
+    let errors: Vec<String> = parse_engine_messages(stderr.get(errors_from..).unwrap_or(""))
+        .into_iter()
+        .filter(|message| message.kind == EngineMessageKind::Error)
+        .map(|message| message.text)
+        .collect();
+
+    if !errors.is_empty() {
+        panic!("GDScript test logged {} error(s):\n{}", errors.len(), errors.join("\n"));
+    }
+}
+
+fn check_returned(method: &str, value: &Variant) {
+    match value.get_type() {
+        VariantType::Bool if !value.to::<bool>() => panic!("{} returned false", method),
+        VariantType::String => {
+            let message = value.to::<GString>().to_string();
+            if !message.is_empty() {
+                panic!("{}", message);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,173 @@
+use godot::engine::input::MouseMode;
+use godot::engine::physics_server_2d::AreaParameter as AreaParameter2D;
+use godot::engine::physics_server_3d::AreaParameter as AreaParameter3D;
+use godot::engine::{AudioServer, Engine, Input, InputMap, PhysicsServer2D, PhysicsServer3D};
+use godot::prelude::*;
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+    static ref SNAPSHOT: Mutex<Option<GlobalState>> = Mutex::new(None);
+}
+
+// Engine-wide state that tests tend to change and forget about. It is
+// snapshotted before every test and put back after it, so e.g. a test
+// pausing the tree can't stop the rest of the suite from running.
+#[derive(Clone, Debug, PartialEq)]
+struct GlobalState {
+    time_scale: f64,
+    paused: bool,
+    mouse_mode: MouseMode,
+    pressed_actions: Vec<StringName>,
+    buses: Vec<BusState>,
+    gravity_2d: Option<(Variant, Variant)>,
+    gravity_3d: Option<(Variant, Variant)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct BusState {
+    mute: bool,
+    solo: bool,
+    bypass_effects: bool,
+    volume_db: f32,
+}
+
+fn tree() -> Option<Gd<SceneTree>> {
+    Engine::singleton()
+        .get_main_loop()
+        .and_then(|main_loop| main_loop.try_cast::<SceneTree>().ok())
+}
+
+// The default physics spaces of the root window's worlds, whose gravity is
+// what `area_set_param(space, GRAVITY, ..)` overrides.
+fn spaces() -> (Option<Rid>, Option<Rid>) {
+    let Some(root) = tree().and_then(|tree| tree.get_root()) else {
+        return (None, None);
+    };
+
+    (
+        root.find_world_2d().map(|world| world.get_space()),
+        root.find_world_3d().map(|world| world.get_space()),
+    )
+}
+
+fn capture() -> GlobalState {
+    let input = Input::singleton();
+    let audio = AudioServer::singleton();
+    let (space_2d, space_3d) = spaces();
+
+    GlobalState {
+        time_scale: Engine::singleton().get_time_scale(),
+        paused: tree().map_or(false, |tree| tree.is_paused()),
+        mouse_mode: input.get_mouse_mode(),
+        pressed_actions: InputMap::singleton()
+            .get_actions()
+            .iter_shared()
+            .filter(|action| input.is_action_pressed(action.clone()))
+            .collect(),
+        buses: (0..audio.get_bus_count())
+            .map(|bus| BusState {
+                mute: audio.is_bus_mute(bus),
+                solo: audio.is_bus_solo(bus),
+                bypass_effects: audio.is_bus_bypassing_effects(bus),
+                volume_db: audio.get_bus_volume_db(bus),
+            })
+            .collect(),
+        gravity_2d: space_2d.map(|space| {
+            let server = PhysicsServer2D::singleton();
+            (
+                server.area_get_param(space, AreaParameter2D::GRAVITY),
+                server.area_get_param(space, AreaParameter2D::GRAVITY_VECTOR),
+            )
+        }),
+        gravity_3d: space_3d.map(|space| {
+            let server = PhysicsServer3D::singleton();
+            (
+                server.area_get_param(space, AreaParameter3D::GRAVITY),
+                server.area_get_param(space, AreaParameter3D::GRAVITY_VECTOR),
+            )
+        }),
+    }
+}
+
+// Called by the runner before a test's first frame.
+pub(crate) fn snapshot() {
+    *SNAPSHOT.lock().unwrap() = Some(capture());
+}
+
+// Called by the runner once a test has finished. Returns what the test left
+// changed, for verbose output.
+pub(crate) fn restore() -> Vec<&'static str> {
+    let Some(before) = SNAPSHOT.lock().unwrap().take() else {
+        return Vec::new();
+    };
+
+    let after = capture();
+    let mut restored = Vec::new();
+
+    if after.time_scale != before.time_scale {
+        Engine::singleton().set_time_scale(before.time_scale);
+        restored.push("Engine.time_scale");
+    }
+
+    if after.paused != before.paused {
+        if let Some(mut tree) = tree() {
+            tree.set_pause(before.paused);
+        }
+        restored.push("SceneTree.paused");
+    }
+
+    let mut input = Input::singleton();
+
+    if after.mouse_mode != before.mouse_mode {
+        input.set_mouse_mode(before.mouse_mode);
+        restored.push("Input.mouse_mode");
+    }
+
+    let mut released = false;
+    for action in &after.pressed_actions {
+        if !before.pressed_actions.contains(action) {
+            input.action_release(action.clone());
+            released = true;
+        }
+    }
+    if released {
+        restored.push("pressed input actions");
+    }
+
+    let mut audio = AudioServer::singleton();
+    if after.buses != before.buses {
+        for (bus, state) in before.buses.iter().enumerate() {
+            let bus = bus as i32;
+            if bus >= audio.get_bus_count() {
+                break;
+            }
+            audio.set_bus_mute(bus, state.mute);
+            audio.set_bus_solo(bus, state.solo);
+            audio.set_bus_bypass_effects(bus, state.bypass_effects);
+            audio.set_bus_volume_db(bus, state.volume_db);
+        }
+        restored.push("audio buses");
+    }
+
+    let (space_2d, space_3d) = spaces();
+
+    if after.gravity_2d != before.gravity_2d {
+        if let (Some(space), Some((gravity, vector))) = (space_2d, before.gravity_2d) {
+            let mut server = PhysicsServer2D::singleton();
+            server.area_set_param(space, AreaParameter2D::GRAVITY, gravity);
+            server.area_set_param(space, AreaParameter2D::GRAVITY_VECTOR, vector);
+        }
+        restored.push("2D gravity");
+    }
+
+    if after.gravity_3d != before.gravity_3d {
+        if let (Some(space), Some((gravity, vector))) = (space_3d, before.gravity_3d) {
+            let mut server = PhysicsServer3D::singleton();
+            server.area_set_param(space, AreaParameter3D::GRAVITY, gravity);
+            server.area_set_param(space, AreaParameter3D::GRAVITY_VECTOR, vector);
+        }
+        restored.push("3D gravity");
+    }
+
+    restored
+}
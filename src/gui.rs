@@ -0,0 +1,81 @@
+use crate::input::{self, ScheduledEvent};
+use godot::engine::global::MouseButton;
+use godot::engine::{Button, Control, InputEventKey, LineEdit};
+use godot::prelude::*;
+
+// The control's center in viewport coordinates, i.e. where a mouse event has
+// to land to hit it.
+pub fn center_of(control: &Gd<Control>) -> Vector2 {
+    let transform = control.get_global_transform_with_canvas();
+    transform * (control.get_size() / 2.0)
+}
+
+pub fn click(control: &Gd<Control>) {
+    if !control.is_visible_in_tree() {
+        panic!("cannot click {}: it is not visible", control.get_path());
+    }
+
+    let position = center_of(control);
+
+    input::mouse_move(position, Vector2::ZERO);
+    input::mouse_press(position, MouseButton::LEFT);
+    input::mouse_release(position, MouseButton::LEFT);
+}
+
+pub fn click_button(button: &Gd<Button>) {
+    if button.is_disabled() {
+        panic!("cannot click {}: the button is disabled", button.get_path());
+    }
+
+    click(&button.clone().upcast());
+}
+
+// Focuses the LineEdit and sends a key press/release for every character.
+pub fn type_text(line_edit: &Gd<LineEdit>, text: &str) {
+    if !line_edit.is_editable() {
+        panic!("cannot type into {}: it is not editable", line_edit.get_path());
+    }
+
+    line_edit.clone().grab_focus();
+
+    for character in text.chars() {
+        for pressed in [true, false] {
+            let mut event = InputEventKey::new_gd();
+            event.set_unicode(character as u32);
+            event.set_pressed(pressed);
+            input::send_event(event.upcast());
+        }
+    }
+}
+
+// Presses the left mouse button at `from`, moves to `to` over
+// `duration_frames` frames and releases it there. The events are sent by the
+// runner on the following frames, so the test has to keep calling `wait!`
+// while `input::scheduled_input_pending()` is true.
+pub fn drag(from: Vector2, to: Vector2, duration_frames: usize) {
+    let steps = duration_frames.max(1);
+
+    input::schedule(
+        0,
+        ScheduledEvent::MouseButton { position: from, button: MouseButton::LEFT, pressed: true },
+    );
+
+    let mut previous = from;
+    for step in 1..=steps {
+        let position = from.lerp(to, step as f32 / steps as f32);
+        input::schedule(
+            step,
+            ScheduledEvent::MouseMotion { position, relative: position - previous },
+        );
+        previous = position;
+    }
+
+    input::schedule(
+        steps + 1,
+        ScheduledEvent::MouseButton { position: to, button: MouseButton::LEFT, pressed: false },
+    );
+}
+
+pub fn drag_control(from: &Gd<Control>, to: &Gd<Control>, duration_frames: usize) {
+    drag(center_of(from), center_of(to), duration_frames);
+}
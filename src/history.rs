@@ -0,0 +1,241 @@
+use crate::output;
+use crate::report::{TestResult, TestStatus};
+use crate::screenshot::globalize_path;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// A test that both passed and failed within the recorded runs.
+#[derive(Clone, Debug)]
+pub struct FlakyCandidate {
+    pub name: String,
+    // Runs it passed or failed in, and how many of those it failed.
+    pub runs: usize,
+    pub failures: usize,
+    // How often it went from passing to failing or back.
+    pub flips: usize,
+    // Oldest run first: `.` passed, `F` failed, `-` didn't run (or was
+    // skipped).
+    pub timeline: String,
+}
+
+impl FlakyCandidate {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "runs": self.runs,
+            "failures": self.failures,
+            "flips": self.flips,
+            "timeline": self.timeline,
+        })
+    }
+}
+
+// `results.json` keeps its history in `results.history.json`.
+fn history_path() -> Option<String> {
+    let path = crate::report::results_path()?;
+
+    Some(match path.rfind('.') {
+        Some(dot) if !path[dot..].contains('/') && !path[dot..].contains('\\') => {
+            format!("{}.history{}", &path[..dot], &path[dot..])
+        }
+        _ => format!("{}.history", path),
+    })
+}
+
+fn load(path: &str) -> Vec<Value> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+        .and_then(|json| json["runs"].as_array().cloned())
+        .unwrap_or_default()
+}
+
+// Adds this run to the history (keeping the last TestConfig::history_runs
+// runs) and returns the tests that flipped between passing and failing in
+// them, the most flips first.
+pub(crate) fn record(results: &[TestResult]) -> Vec<FlakyCandidate> {
+    let runs_to_keep = crate::config::with(|config| config.history_runs);
+    if runs_to_keep == 0 {
+        return Vec::new();
+    }
+
+    let Some(path) = history_path() else {
+        return Vec::new();
+    };
+    let path = globalize_path(&path);
+
+    let started_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+    let tests: serde_json::Map<String, Value> = results
+        .iter()
+        .map(|result| (result.name.clone(), Value::from(result.status.as_str())))
+        .collect();
+
+    let mut runs = load(&path);
+    runs.push(json!({
+        "started_at": started_at,
+        "seed": crate::random::suite_seed(),
+        "tests": tests,
+    }));
+    if runs.len() > runs_to_keep {
+        runs.drain(..runs.len() - runs_to_keep);
+    }
+
+    if let Some(parent) = std::path::Path::new(&path).parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let json = serde_json::to_string_pretty(&json!({ "runs": runs })).unwrap_or_default();
+    if let Err(error) = std::fs::write(&path, json) {
+        output::print_plain(&format!("Could not write the results history to {}: {}", path, error), true);
+    }
+
+    flaky_candidates(&runs)
+}
+
+fn flaky_candidates(runs: &[Value]) -> Vec<FlakyCandidate> {
+    let passed = TestStatus::Passed.as_str();
+    let failed = TestStatus::Failed.as_str();
+
+    let mut outcomes: BTreeMap<String, Vec<Option<bool>>> = BTreeMap::new();
+    for (index, run) in runs.iter().enumerate() {
+        let Some(tests) = run["tests"].as_object() else {
+            continue;
+        };
+        for (name, status) in tests {
+            let outcome = match status.as_str() {
+                Some(status) if status == passed => Some(true),
+                Some(status) if status == failed => Some(false),
+                _ => None,
+            };
+            outcomes.entry(name.clone()).or_insert_with(|| vec![None; runs.len()])[index] = outcome;
+        }
+    }
+
+    let mut candidates: Vec<FlakyCandidate> = outcomes
+        .into_iter()
+        .filter_map(|(name, outcomes)| {
+            let ran: Vec<bool> = outcomes.iter().flatten().copied().collect();
+            let failures = ran.iter().filter(|passed| !**passed).count();
+            if failures == 0 || failures == ran.len() {
+                return None;
+            }
+
+            Some(FlakyCandidate {
+                name,
+                runs: ran.len(),
+                failures,
+                flips: ran.windows(2).filter(|pair| pair[0] != pair[1]).count(),
+                timeline: outcomes
+                    .iter()
+                    .map(|outcome| match outcome {
+                        Some(true) => '.',
+                        Some(false) => 'F',
+                        None => '-',
+                    })
+                    .collect(),
+            })
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| b.flips.cmp(&a.flips).then(b.failures.cmp(&a.failures)));
+    candidates
+}
+
+pub(crate) fn print_flaky(candidates: &[FlakyCandidate]) {
+    if candidates.is_empty() {
+        return;
+    }
+
+    crate::println_yellow!("\nFlaky candidates (both passed and failed in recent runs):");
+    for candidate in candidates {
+        crate::println_yellow!(
+            "  {} [{}] failed {} of {} runs, {} flip(s)",
+            candidate.name,
+            candidate.timeline,
+            candidate.failures,
+            candidate.runs,
+            candidate.flips
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(candidates: &[FlakyCandidate]) -> Vec<&str> {
+        candidates.iter().map(|candidate| candidate.name.as_str()).collect()
+    }
+
+    #[test]
+    fn finds_tests_that_flip() {
+        let runs = [
+            json!({ "tests": { "a": "passed", "b": "failed", "c": "passed", "d": "failed" } }),
+            json!({ "tests": { "a": "failed", "b": "failed", "c": "passed", "d": "failed" } }),
+            json!({ "tests": { "a": "passed", "b": "passed", "c": "passed", "d": "failed" } }),
+        ];
+
+        let candidates = flaky_candidates(&runs);
+
+        assert_eq!(names(&candidates), vec!["a", "b"]);
+        assert_eq!((candidates[0].runs, candidates[0].failures, candidates[0].flips), (3, 1, 2));
+        assert_eq!(candidates[0].timeline, ".F.");
+        assert_eq!((candidates[1].runs, candidates[1].failures, candidates[1].flips), (3, 2, 1));
+        assert_eq!(candidates[1].timeline, "FF.");
+    }
+
+    #[test]
+    fn needs_both_a_pass_and_a_failure() {
+        let runs = [
+            json!({ "tests": { "once": "failed", "skipped": "skipped", "pending": "passed" } }),
+            json!({ "tests": { "skipped": "failed", "pending": "pending" } }),
+        ];
+
+        assert!(flaky_candidates(&runs).is_empty());
+        assert!(flaky_candidates(&[]).is_empty());
+    }
+
+    #[test]
+    fn ranks_by_flips_then_failures() {
+        let runs = [
+            json!({ "tests": { "few": "failed", "many": "failed", "most": "passed" } }),
+            json!({ "tests": { "few": "passed", "many": "failed", "most": "failed" } }),
+            json!({ "tests": { "few": "passed", "many": "passed", "most": "passed" } }),
+        ];
+
+        assert_eq!(names(&flaky_candidates(&runs)), vec!["most", "many", "few"]);
+    }
+
+    #[test]
+    fn follows_tests_present_in_some_runs() {
+        let runs = [
+            json!({ "tests": { "new": "passed" } }),
+            json!({ "tests": {} }),
+            json!({ "tests": { "new": "failed" } }),
+            json!({ "tests": { "new": "skipped" } }),
+        ];
+
+        let candidates = flaky_candidates(&runs);
+
+        assert_eq!(names(&candidates), vec!["new"]);
+        assert_eq!((candidates[0].runs, candidates[0].failures, candidates[0].flips), (2, 1, 1));
+        assert_eq!(candidates[0].timeline, ".-F-");
+    }
+
+    #[test]
+    fn skips_malformed_runs() {
+        let runs = [
+            json!({}),
+            json!({ "tests": "none" }),
+            json!({ "tests": { "a": 3 } }),
+            json!({ "tests": { "a": "passed" } }),
+            json!({ "tests": { "a": "failed" } }),
+        ];
+
+        let candidates = flaky_candidates(&runs);
+
+        assert_eq!(names(&candidates), vec!["a"]);
+        assert_eq!(candidates[0].runs, 2);
+        assert_eq!(candidates[0].timeline, "---.F");
+    }
+}
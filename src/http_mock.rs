@@ -0,0 +1,312 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+lazy_static::lazy_static! {
+    // Servers started by the current test, shut down when it finishes.
+    static ref SERVERS: Mutex<Vec<MockServer>> = Mutex::new(Vec::new());
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockResponse {
+    pub fn new(status: u16) -> Self {
+        Self { status, headers: Vec::new(), body: Vec::new() }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    pub fn json(self, body: &str) -> Self {
+        self.header("Content-Type", "application/json").body(body)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedRequest {
+    pub method: String,
+    // Including the query string, e.g. `/scores?page=2`.
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    // Whether a stub answered it (otherwise it got a 404).
+    pub matched: bool,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn body_text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+struct Stub {
+    method: String,
+    path: String,
+    response: MockResponse,
+}
+
+struct Shared {
+    stubs: Mutex<Vec<Stub>>,
+    requests: Mutex<Vec<RecordedRequest>>,
+    shutdown: AtomicBool,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+// A local HTTP server for code using HTTPRequest/HTTPClient. Requests are
+// answered from the stubs and recorded for assertions:
+//
+//     let server = http_mock::start();
+//     server.stub("GET", "/scores", MockResponse::new(200).json(r#"{"best": 10}"#));
+//     leaderboard.bind_mut().fetch(server.url("/scores"));
+//     ...
+//     server.assert_requested("GET", "/scores", 1);
+//
+// It is shut down when the test finishes.
+#[derive(Clone)]
+pub struct MockServer {
+    address: SocketAddr,
+    shared: Arc<Shared>,
+}
+
+pub fn start() -> MockServer {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("http_mock: failed to bind a local port");
+    listener
+        .set_nonblocking(true)
+        .expect("http_mock: failed to make the listener non-blocking");
+    let address = listener.local_addr().unwrap();
+
+    let shared = Arc::new(Shared {
+        stubs: Mutex::new(Vec::new()),
+        requests: Mutex::new(Vec::new()),
+        shutdown: AtomicBool::new(false),
+        thread: Mutex::new(None),
+    });
+
+    let thread_shared = shared.clone();
+    let thread = std::thread::Builder::new()
+        .name(format!("http_mock {}", address))
+        .spawn(move || serve(listener, thread_shared))
+        .expect("http_mock: failed to start the server thread");
+    *shared.thread.lock().unwrap() = Some(thread);
+
+    let server = MockServer { address, shared };
+    SERVERS.lock().unwrap().push(server.clone());
+    server
+}
+
+impl MockServer {
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    // `http://127.0.0.1:<port><path>`
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.address, path)
+    }
+
+    // Answer `method` requests to `path` (query string ignored) with
+    // `response`. Later stubs for the same route replace earlier ones.
+    pub fn stub(&self, method: &str, path: &str, response: MockResponse) {
+        let mut stubs = self.shared.stubs.lock().unwrap();
+        stubs.retain(|stub| !(stub.method.eq_ignore_ascii_case(method) && stub.path == path));
+        stubs.push(Stub { method: method.to_string(), path: path.to_string(), response });
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.shared.requests.lock().unwrap().clone()
+    }
+
+    #[track_caller]
+    pub fn assert_requested(&self, method: &str, path: &str, times: usize) {
+        let requests = self.requests();
+        let count = requests
+            .iter()
+            .filter(|request| {
+                request.method.eq_ignore_ascii_case(method) && route(&request.path) == path
+            })
+            .count();
+
+        if count != times {
+            let received: Vec<String> = requests
+                .iter()
+                .map(|request| format!("  {} {}", request.method, request.path))
+                .collect();
+            panic!(
+                "expected {} {} to be requested {} time(s), but it was requested {} time(s)\nreceived:\n{}",
+                method,
+                path,
+                times,
+                count,
+                if received.is_empty() { "  (nothing)".to_string() } else { received.join("\n") }
+            );
+        }
+    }
+
+    // Fails if any request arrived that no stub matched.
+    #[track_caller]
+    pub fn assert_no_unmatched_requests(&self) {
+        let unmatched: Vec<String> = self
+            .requests()
+            .iter()
+            .filter(|request| !request.matched)
+            .map(|request| format!("  {} {}", request.method, request.path))
+            .collect();
+
+        if !unmatched.is_empty() {
+            panic!("requests without a matching stub:\n{}", unmatched.join("\n"));
+        }
+    }
+
+    pub fn shutdown(&self) {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+
+        if let Some(thread) = self.shared.thread.lock().unwrap().take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn route(path: &str) -> &str {
+    path.split('?').next().unwrap_or(path)
+}
+
+fn serve(listener: TcpListener, shared: Arc<Shared>) {
+    while !shared.shutdown.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((stream, _)) => handle(stream, &shared),
+            Err(error) if error.kind() == ErrorKind::WouldBlock => {
+                std::thread::sleep(Duration::from_millis(1))
+            }
+            Err(_) => std::thread::sleep(Duration::from_millis(1)),
+        }
+    }
+}
+
+fn handle(mut stream: TcpStream, shared: &Shared) {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+
+    let Some(mut request) = read_request(&mut stream) else {
+        return;
+    };
+
+    let response = shared
+        .stubs
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|stub| stub.method.eq_ignore_ascii_case(&request.method) && stub.path == route(&request.path))
+        .map(|stub| stub.response.clone());
+
+    request.matched = response.is_some();
+    shared.requests.lock().unwrap().push(request);
+
+    let response = response.unwrap_or_else(|| MockResponse::new(404).body("no stub for this route"));
+    let _ = write_response(&mut stream, &response);
+}
+
+fn read_request(stream: &mut TcpStream) -> Option<RecordedRequest> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    let header_end = loop {
+        if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position;
+        }
+        let read = stream.read(&mut chunk).ok()?;
+        if read == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).into_owned();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    let content_length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let mut body = buffer[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let read = stream.read(&mut chunk).ok()?;
+        if read == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(content_length);
+
+    Some(RecordedRequest { method, path, headers, body, matched: false })
+}
+
+fn write_response(stream: &mut TcpStream, response: &MockResponse) -> std::io::Result<()> {
+    let mut head = format!("HTTP/1.1 {} {}\r\n", response.status, reason(response.status));
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", response.body.len()));
+
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+// Called by the runner once a test has finished.
+pub(crate) fn reset() {
+    let servers: Vec<MockServer> = SERVERS.lock().unwrap().drain(..).collect();
+
+    for server in servers {
+        server.shutdown();
+    }
+}
@@ -0,0 +1,259 @@
+use godot::engine::global::{JoyAxis, JoyButton, MouseButton, MouseButtonMask};
+use godot::engine::{
+    Input, InputEvent, InputEventJoypadButton, InputEventJoypadMotion, InputEventMouseButton,
+    InputEventMouseMotion,
+};
+use godot::prelude::*;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+    static ref VIRTUAL_JOYPADS: Mutex<Vec<VirtualJoypadState>> = Mutex::new(Vec::new());
+    static ref SCHEDULED_EVENTS: Mutex<VecDeque<Vec<ScheduledEvent>>> = Mutex::new(VecDeque::new());
+    static ref PRESSED_MOUSE_BUTTONS: Mutex<Vec<(MouseButton, Vector2)>> = Mutex::new(Vec::new());
+}
+
+// Godot only tracks devices 0 to 15 (JOY_MAX).
+const MAX_JOYPADS: i32 = 16;
+
+struct VirtualJoypadState {
+    device: i32,
+    pressed_buttons: Vec<JoyButton>,
+    moved_axes: Vec<JoyAxis>,
+}
+
+// Feeds an event into Godot's input pipeline and flushes it so that
+// `Input::is_action_pressed`, `Input::get_axis` etc. reflect it immediately.
+pub fn send_event(event: Gd<InputEvent>) {
+    crate::recording::record_synthetic(&event, || {
+        let mut input = Input::singleton();
+        input.parse_input_event(event.clone());
+        input.flush_buffered_events();
+    });
+}
+
+// Events queued to be sent on a later frame. Gd<InputEvent> can't be stored
+// in a static, so the event is only built once its frame comes around.
+#[derive(Clone, Copy, Debug)]
+pub enum ScheduledEvent {
+    MouseMotion { position: Vector2, relative: Vector2 },
+    MouseButton { position: Vector2, button: MouseButton, pressed: bool },
+}
+
+// Queue an event to be sent `frames_from_now` frames later (0 being the next
+// frame the runner processes).
+pub fn schedule(frames_from_now: usize, event: ScheduledEvent) {
+    let mut scheduled = SCHEDULED_EVENTS.lock().unwrap();
+
+    while scheduled.len() <= frames_from_now {
+        scheduled.push_back(Vec::new());
+    }
+
+    scheduled[frames_from_now].push(event);
+}
+
+// True while there are still scheduled events waiting to be sent. Multi-frame
+// helpers like `gui::drag` rely on the test calling `wait!` until this is false.
+pub fn scheduled_input_pending() -> bool {
+    !SCHEDULED_EVENTS.lock().unwrap().is_empty()
+}
+
+// Called by the runner once per frame, before the current test runs.
+pub(crate) fn process_scheduled() {
+    let events = SCHEDULED_EVENTS.lock().unwrap().pop_front();
+
+    for event in events.unwrap_or_default() {
+        send_scheduled(event);
+    }
+}
+
+fn send_scheduled(event: ScheduledEvent) {
+    match event {
+        ScheduledEvent::MouseMotion { position, relative } => {
+            let mut event = InputEventMouseMotion::new_gd();
+            event.set_position(position);
+            event.set_global_position(position);
+            event.set_relative(relative);
+            if let Some(mask) = pressed_mouse_button_mask() {
+                event.set_button_mask(mask);
+            }
+            send_event(event.upcast());
+        }
+        ScheduledEvent::MouseButton { position, button, pressed } => {
+            {
+                let mut buttons = PRESSED_MOUSE_BUTTONS.lock().unwrap();
+                buttons.retain(|(pressed_button, _)| *pressed_button != button);
+                if pressed {
+                    buttons.push((button, position));
+                }
+            }
+
+            let mut event = InputEventMouseButton::new_gd();
+            event.set_position(position);
+            event.set_global_position(position);
+            event.set_button_index(button);
+            event.set_pressed(pressed);
+            if let Some(mask) = pressed_mouse_button_mask() {
+                event.set_button_mask(mask);
+            }
+            send_event(event.upcast());
+        }
+    }
+}
+
+fn pressed_mouse_button_mask() -> Option<MouseButtonMask> {
+    let buttons = PRESSED_MOUSE_BUTTONS.lock().unwrap();
+
+    buttons
+        .iter()
+        .filter_map(|(button, _)| match *button {
+            MouseButton::LEFT => Some(MouseButtonMask::LEFT),
+            MouseButton::RIGHT => Some(MouseButtonMask::RIGHT),
+            MouseButton::MIDDLE => Some(MouseButtonMask::MIDDLE),
+            _ => None,
+        })
+        .reduce(|mask, button| mask | button)
+}
+
+pub fn mouse_move(position: Vector2, relative: Vector2) {
+    send_scheduled(ScheduledEvent::MouseMotion { position, relative });
+}
+
+pub fn mouse_press(position: Vector2, button: MouseButton) {
+    send_scheduled(ScheduledEvent::MouseButton { position, button, pressed: true });
+}
+
+pub fn mouse_release(position: Vector2, button: MouseButton) {
+    send_scheduled(ScheduledEvent::MouseButton { position, button, pressed: false });
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VirtualJoypad {
+    pub device: i32,
+}
+
+// Connects a fake joypad on the first free device id. It is disconnected
+// again (with all buttons released and axes centered) when the test finishes.
+pub fn connect_joypad() -> VirtualJoypad {
+    let connected = Input::singleton().get_connected_joypads();
+    let mut joypads = VIRTUAL_JOYPADS.lock().unwrap();
+
+    let device = (0..MAX_JOYPADS)
+        .find(|device| {
+            !connected.iter_shared().any(|id| id == *device as i64)
+                && !joypads.iter().any(|joypad| joypad.device == *device)
+        })
+        .expect("no free joypad device id left to connect a virtual joypad");
+
+    Input::singleton().joy_connection_changed(
+        device,
+        true,
+        format!("Virtual Joypad {}", device).into(),
+        "".into(),
+    );
+
+    joypads.push(VirtualJoypadState {
+        device,
+        pressed_buttons: Vec::new(),
+        moved_axes: Vec::new(),
+    });
+
+    VirtualJoypad { device }
+}
+
+impl VirtualJoypad {
+    pub fn set_axis(&self, axis: JoyAxis, value: f32) {
+        with_state(self.device, |state| {
+            if !state.moved_axes.contains(&axis) {
+                state.moved_axes.push(axis);
+            }
+        });
+
+        send_axis(self.device, axis, value);
+    }
+
+    pub fn press(&self, button: JoyButton) {
+        with_state(self.device, |state| {
+            if !state.pressed_buttons.contains(&button) {
+                state.pressed_buttons.push(button);
+            }
+        });
+
+        send_button(self.device, button, true);
+    }
+
+    pub fn release(&self, button: JoyButton) {
+        with_state(self.device, |state| {
+            state.pressed_buttons.retain(|pressed| *pressed != button);
+        });
+
+        send_button(self.device, button, false);
+    }
+
+    pub fn disconnect(&self) {
+        let state = {
+            let mut joypads = VIRTUAL_JOYPADS.lock().unwrap();
+            let index = joypads.iter().position(|joypad| joypad.device == self.device);
+            index.map(|index| joypads.remove(index))
+        };
+
+        if let Some(state) = state {
+            reset_joypad(state);
+        }
+    }
+}
+
+fn with_state(device: i32, f: impl FnOnce(&mut VirtualJoypadState)) {
+    let mut joypads = VIRTUAL_JOYPADS.lock().unwrap();
+    let state = joypads
+        .iter_mut()
+        .find(|joypad| joypad.device == device)
+        .expect("virtual joypad used after it was disconnected");
+    f(state);
+}
+
+fn send_axis(device: i32, axis: JoyAxis, value: f32) {
+    let mut event = InputEventJoypadMotion::new_gd();
+    event.set_device(device);
+    event.set_axis(axis);
+    event.set_axis_value(value);
+    send_event(event.upcast());
+}
+
+fn send_button(device: i32, button: JoyButton, pressed: bool) {
+    let mut event = InputEventJoypadButton::new_gd();
+    event.set_device(device);
+    event.set_button_index(button);
+    event.set_pressed(pressed);
+    event.set_pressure(if pressed { 1.0 } else { 0.0 });
+    send_event(event.upcast());
+}
+
+fn reset_joypad(state: VirtualJoypadState) {
+    for button in state.pressed_buttons {
+        send_button(state.device, button, false);
+    }
+
+    for axis in state.moved_axes {
+        send_axis(state.device, axis, 0.0);
+    }
+
+    Input::singleton().joy_connection_changed(state.device, false, "".into(), "".into());
+}
+
+// Called by the runner between tests.
+pub(crate) fn reset() {
+    SCHEDULED_EVENTS.lock().unwrap().clear();
+
+    let buttons: Vec<(MouseButton, Vector2)> =
+        PRESSED_MOUSE_BUTTONS.lock().unwrap().clone();
+    for (button, position) in buttons {
+        mouse_release(position, button);
+    }
+
+    let joypads: Vec<VirtualJoypadState> = VIRTUAL_JOYPADS.lock().unwrap().drain(..).collect();
+
+    for state in joypads {
+        reset_joypad(state);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::scene::SceneFixtures;
+use crate::TestContext;
+use godot::engine::{Engine, ProjectSettings};
+use godot::prelude::*;
+use std::cell::RefCell;
+
+thread_local! {
+    // The current integration test's instance of the main scene, and the
+    // SceneTree's current_scene from before it.
+    static MAIN_SCENE: RefCell<Option<MainScene>> = RefCell::new(None);
+}
+
+struct MainScene {
+    instance: Gd<Node>,
+    previous_current_scene: Option<Gd<Node>>,
+}
+
+fn tree() -> Gd<SceneTree> {
+    Engine::singleton()
+        .get_main_loop()
+        .and_then(|main_loop| main_loop.try_cast::<SceneTree>().ok())
+        .expect("main scene fixture: no SceneTree is running")
+}
+
+// TestConfig::main_scene, or the project's run/main_scene.
+fn main_scene_path() -> String {
+    crate::config::with(|config| config.main_scene.clone()).unwrap_or_else(|| {
+        ProjectSettings::singleton()
+            .get_setting("application/run/main_scene".into())
+            .to_string()
+    })
+}
+
+// Called by the runner on the first frame of a test registered with
+// integration_test! (or TestCase::integration): instances the main scene
+// under the test root and makes it the tree's current_scene, so game code
+// using get_tree().current_scene finds it. It's freed with the root.
+pub(crate) fn setup(context: &TestContext) {
+    let path = main_scene_path();
+    if path.is_empty() {
+        panic!("integration test: no main_scene is configured and the project has no run/main_scene");
+    }
+
+    let instance: Gd<Node> = context.root().load_scene(&path);
+
+    let mut tree = tree();
+    let previous_current_scene = tree.get_current_scene();
+    tree.set_current_scene(instance.clone());
+
+    MAIN_SCENE.with(|main_scene| {
+        *main_scene.borrow_mut() = Some(MainScene { instance, previous_current_scene })
+    });
+}
+
+// Called by the runner once a test has finished.
+pub(crate) fn reset() {
+    let Some(main_scene) = MAIN_SCENE.with(|main_scene| main_scene.borrow_mut().take()) else {
+        return;
+    };
+
+    if let Some(previous) = main_scene.previous_current_scene {
+        if previous.is_instance_valid() {
+            tree().set_current_scene(previous);
+        }
+    }
+}
+
+impl TestContext {
+    // The main scene instanced for this integration test.
+    #[track_caller]
+    pub fn main_scene(&self) -> Gd<Node> {
+        MAIN_SCENE
+            .with(|main_scene| main_scene.borrow().as_ref().map(|main_scene| main_scene.instance.clone()))
+            .expect("main_scene: only available in tests registered with integration_test!")
+    }
+
+    // A node of the main scene by path, e.g. `ctx.game_node::<Label>("Hud/Score")`.
+    #[track_caller]
+    pub fn game_node<T>(&self, path: &str) -> Gd<T>
+    where
+        T: GodotClass + Inherits<Node>,
+    {
+        crate::nodes::find(&self.main_scene(), path)
+    }
+}
@@ -0,0 +1,93 @@
+use godot::engine::performance::Monitor;
+use godot::engine::Performance;
+use std::ops::Sub;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ObjectCounts {
+    pub objects: i64,
+    pub resources: i64,
+    pub orphan_nodes: i64,
+}
+
+impl ObjectCounts {
+    pub fn current() -> Self {
+        let performance = Performance::singleton();
+
+        Self {
+            objects: performance.get_monitor(Monitor::OBJECT_COUNT) as i64,
+            resources: performance.get_monitor(Monitor::OBJECT_RESOURCE_COUNT) as i64,
+            orphan_nodes: performance.get_monitor(Monitor::OBJECT_ORPHAN_NODE_COUNT) as i64,
+        }
+    }
+}
+
+impl Sub for ObjectCounts {
+    type Output = ObjectCounts;
+
+    fn sub(self, other: ObjectCounts) -> ObjectCounts {
+        ObjectCounts {
+            objects: self.objects - other.objects,
+            resources: self.resources - other.resources,
+            orphan_nodes: self.orphan_nodes - other.orphan_nodes,
+        }
+    }
+}
+
+pub(crate) struct LeakCheck {
+    pub reports: Vec<String>,
+    pub failed: bool,
+}
+
+pub(crate) fn check(test: &str, delta: ObjectCounts) -> LeakCheck {
+    let (fail_on_orphan_nodes, object_leak_threshold) = crate::config::with(|config| {
+        (config.fail_on_orphan_nodes, config.object_leak_threshold)
+    });
+
+    let mut check = LeakCheck {
+        reports: Vec::new(),
+        failed: false,
+    };
+
+    if delta.orphan_nodes > 0 {
+        check.reports.push(format!(
+            "{} leaked {} orphan node(s)",
+            test, delta.orphan_nodes
+        ));
+
+        if fail_on_orphan_nodes {
+            check.failed = true;
+        }
+    }
+
+    if delta.objects > 0 || delta.resources > 0 {
+        check.reports.push(format!(
+            "{} grew the object count by {} ({} resources)",
+            test, delta.objects, delta.resources
+        ));
+
+        if let Some(threshold) = object_leak_threshold {
+            if delta.objects > threshold || delta.resources > threshold {
+                check.failed = true;
+            }
+        }
+    }
+
+    check
+}
+
+pub(crate) fn describe_trend(start: ObjectCounts, end: ObjectCounts) -> String {
+    let delta = end - start;
+
+    format!(
+        "objects: {} -> {} ({:+}), resources: {} -> {} ({:+}), orphan nodes: {} -> {} ({:+})",
+        start.objects,
+        end.objects,
+        delta.objects,
+        start.resources,
+        end.resources,
+        delta.resources,
+        start.orphan_nodes,
+        end.orphan_nodes,
+        delta.orphan_nodes
+    )
+}
@@ -1,32 +1,354 @@
 use godot::prelude::*;
-use std::collections::VecDeque;
-use std::io::{self, Write};
+use godot::engine::node::ProcessMode;
+use godot::engine::{Image, InputEvent, Viewport};
+use std::collections::{HashSet, VecDeque};
 use std::panic;
+use std::sync::Arc;
 use std::sync::Mutex;
-use backtrace::Backtrace;
-use regex::Regex;
+use std::time::{Duration, Instant};
+
+pub mod animation;
+pub mod approx;
+pub mod artifacts;
+pub mod audio;
+pub mod autoload;
+pub mod backtraces;
+#[cfg(feature = "bench")]
+pub mod bench;
+pub mod capture;
+#[cfg(feature = "cargo-test-bridge")]
+pub mod cargo_test;
+pub mod collision;
+pub mod config;
+pub mod context;
+pub mod coverage;
+pub mod debug;
+pub mod describe;
+pub mod diff;
+#[cfg(feature = "editor")]
+pub mod editor;
+pub mod errors;
+pub mod executor;
+pub mod expect;
+pub mod fixtures;
+pub mod frames;
+pub mod fuzz;
+pub mod gdscript;
+pub mod globals;
+pub mod gui;
+pub mod history;
+#[cfg(feature = "http-mock")]
+pub mod http_mock;
+pub mod input;
+pub mod integration;
+pub mod leaks;
+pub mod logging;
+pub mod memory;
+pub mod metadata;
+pub mod multiplayer;
+pub mod navigation;
+pub mod nodes;
+pub mod observer;
+pub mod output;
+pub mod panics;
+pub mod pause;
+pub mod physics;
+pub mod pretty;
+pub mod profiler;
+pub mod progress;
+pub mod prop;
+pub mod properties;
+pub mod quit;
+pub mod random;
+pub mod recording;
+pub mod replication;
+pub mod report;
+pub mod resources;
+#[cfg(feature = "tokio")]
+pub mod runtime;
+pub mod sandbox;
+pub mod scene;
+pub mod schedule;
+pub mod screenshot;
+pub mod shaders;
+pub mod signals;
+pub mod skip;
+pub mod snapshot;
+pub mod spatial;
+pub mod spy;
+pub mod state;
+pub mod suite;
+pub mod threads;
+pub mod time;
+pub mod tooling;
+pub mod viewport;
+pub mod visibility;
+pub mod waiting;
+pub mod warmup;
+
+pub use config::{RootType, TestConfig};
+pub use context::TestContext;
+pub use report::{TestResult, TestStatus};
+pub use scene::{instantiate_as, SceneFixtures};
+pub use state::{test_state, SpecCase};
+pub use suite::TestSuite;
 
 lazy_static::lazy_static! {
-    pub static ref REGISTERED_TESTS: Mutex<VecDeque<fn(&Gd<Node>)>> = Mutex::new(VecDeque::new());
-    pub static ref FOCUSED_TEST: Mutex<Option<fn(&Gd<Node>)>> = Mutex::new(None);
+    pub static ref REGISTERED_TESTS: Mutex<VecDeque<TestCase>> = Mutex::new(VecDeque::new());
+    // Registered with focus!. When there are any, only these (and the tests
+    // matching FOCUS_PATTERNS) run.
+    pub static ref FOCUSED_TESTS: Mutex<Vec<TestCase>> = Mutex::new(Vec::new());
+    pub static ref FOCUS_PATTERNS: Mutex<Vec<String>> = Mutex::new(Vec::new());
     pub static ref CURRENT_TEST_INDEX: Mutex<usize> = Mutex::new(0);
     pub static ref CURRENT_TEST_ITERATION: Mutex<usize> = Mutex::new(0);
     pub static ref WANTS_REPLAY: Mutex<bool> = Mutex::new(false);
     pub static ref DELAY_BEFORE_NEXT_TEST_RUN: Mutex<f64> = Mutex::new(0.0);
 }
 
+// Exit code used when the suite has nothing to run, to tell it apart from a
+// run with failures (1).
+pub const EXIT_NO_TESTS: i32 = 3;
+
+// Shared rather than boxed so a test can be handed out without holding the
+// REGISTERED_TESTS lock while it runs.
+pub type TestFn = Arc<dyn Fn(&TestContext) + Send + Sync>;
+
+#[derive(Clone)]
+pub struct TestCase {
+    pub name: String,
+    pub func: TestFn,
+    // Where the test was registered.
+    pub file: &'static str,
+    pub line: u32,
+    pub tags: &'static [&'static str],
+    // `module_path!()` where it was registered, for --module. Empty for
+    // tests registered without the macros.
+    pub module_path: &'static str,
+    // The function behind test!/focus!, used to notice the same function
+    // being registered twice under different names. None for closures.
+    pub fn_address: Option<usize>,
+    pub requires_rendering: bool,
+    // Checked (or substituted) before the test's first frame.
+    pub required_autoloads: Vec<autoload::RequiredAutoload>,
+    // Gets a fresh instance of the main scene under its root, see
+    // integration.rs.
+    pub integration: bool,
+    // Overrides TestConfig::root_type for this test.
+    pub root_type: Option<config::RootType>,
+    // Not run, and reported as pending, e.g. for a spec written ahead of the
+    // feature.
+    pub pending: Option<String>,
+    // Run, and reported as an expected failure when it fails. Passing fails
+    // it, so the marker gets removed once the bug is fixed.
+    pub expected_failure: Option<String>,
+    // Higher runs earlier, see schedule.rs.
+    pub priority: i32,
+    // Names of tests that have to run (and pass) first. If one of them
+    // fails, this one is skipped as blocked.
+    pub depends_on: Vec<String>,
+    // Runs once the test has finished, whatever its outcome (including
+    // timeouts and failures the runner notices after the body), e.g. the
+    // after_each hooks of describe!. Not run if the body never was.
+    pub teardown: Option<TestFn>,
+}
+
+impl TestCase {
+    // A test from a closure, e.g. for generated or parameterized cases:
+    //
+    //     for speed in [1.0, 10.0, 100.0] {
+    //         register(TestCase::new(format!("moves_at_{}", speed), move |ctx| {
+    //             let player = ctx.load_scene::<Player>("res://player.tscn");
+    //             ...
+    //         }));
+    //     }
+    #[track_caller]
+    pub fn new(name: impl Into<String>, func: impl Fn(&TestContext) + Send + Sync + 'static) -> Self {
+        let location = std::panic::Location::caller();
+
+        Self {
+            name: name.into(),
+            func: Arc::new(func),
+            file: location.file(),
+            line: location.line(),
+            tags: &[],
+            module_path: "",
+            fn_address: None,
+            requires_rendering: false,
+            required_autoloads: Vec::new(),
+            integration: false,
+            root_type: None,
+            pending: None,
+            expected_failure: None,
+            priority: 0,
+            depends_on: Vec::new(),
+            teardown: None,
+        }
+    }
+
+    // Used by test!, focus! and spec!.
+    pub fn from_fn(
+        name: &'static str,
+        func: fn(&Gd<Node>),
+        file: &'static str,
+        line: u32,
+        tags: &'static [&'static str],
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            func: Arc::new(move |context: &TestContext| func(context)),
+            file,
+            line,
+            tags,
+            module_path: "",
+            fn_address: Some(func as usize),
+            requires_rendering: false,
+            required_autoloads: Vec::new(),
+            integration: false,
+            root_type: None,
+            pending: None,
+            expected_failure: None,
+            priority: 0,
+            depends_on: Vec::new(),
+            teardown: None,
+        }
+    }
+
+    pub fn tags(mut self, tags: &'static [&'static str]) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn module_path(mut self, module_path: &'static str) -> Self {
+        self.module_path = module_path;
+        self
+    }
+
+    // Skip the test (instead of failing it) when running headless.
+    pub fn requires_rendering(mut self) -> Self {
+        self.requires_rendering = true;
+        self
+    }
+
+    pub fn requires_autoload(mut self, name: impl Into<String>) -> Self {
+        self.required_autoloads.push(autoload::RequiredAutoload { name: name.into(), substitute: None });
+        self
+    }
+
+    // Like requires_autoload, but instances `scene` in its place when the
+    // project doesn't have it.
+    pub fn requires_autoload_or(mut self, name: impl Into<String>, scene: impl Into<String>) -> Self {
+        self.required_autoloads.push(autoload::RequiredAutoload {
+            name: name.into(),
+            substitute: Some(scene.into()),
+        });
+        self
+    }
+
+    pub fn integration(mut self) -> Self {
+        self.integration = true;
+        self
+    }
+
+    pub fn root_type(mut self, root_type: config::RootType) -> Self {
+        self.root_type = Some(root_type);
+        self
+    }
+
+    pub fn pending(mut self, reason: impl Into<String>) -> Self {
+        self.pending = Some(reason.into());
+        self
+    }
+
+    pub fn expect_failure(mut self, reason: impl Into<String>) -> Self {
+        self.expected_failure = Some(reason.into());
+        self
+    }
+
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn depends_on(mut self, test: impl Into<String>) -> Self {
+        self.depends_on.push(test.into());
+        self
+    }
+
+    // `name [tag, tag] (file:line)`, as printed by --list.
+    pub fn describe(&self) -> String {
+        let mut description = self.name.to_string();
+
+        if !self.tags.is_empty() {
+            description.push_str(&format!(" [{}]", self.tags.join(", ")));
+        }
+
+        description.push_str(&format!(" ({}:{})", self.file, self.line));
+        description
+    }
+}
+
+// Queues `test` to run, or adds it to the suite being built by
+// TestSuite::collect.
+pub fn register(test: TestCase) {
+    if let Some(test) = suite::collect(test, false) {
+        REGISTERED_TESTS.lock().unwrap().push_back(test);
+    }
+}
+
+// Used by focus!.
+pub fn register_focused(test: TestCase) {
+    if let Some(test) = suite::collect(test, true) {
+        FOCUSED_TESTS.lock().unwrap().push(test.clone());
+        REGISTERED_TESTS.lock().unwrap().push_back(test);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CleanupStrategy {
+    // free() the test root as soon as the test finishes.
+    Immediate,
+    // queue_free() the test root and hold off the next test until the engine
+    // has actually freed it. Use this when game code still has signals or
+    // physics callbacks in flight when a test ends.
+    Deferred,
+    // Free the test root's subtree a bit at a time, deepest nodes first,
+    // within the frame_budget of each frame, before the next test starts.
+    // For tests that create thousands of nodes, where freeing them all at
+    // once makes for a long frame. The time spent freeing counts towards the
+    // test's duration, the frames in between don't.
+    Incremental,
+}
+
 #[macro_export]
 macro_rules! focus {
-    ($test_func:ident) => {{
-        let mut focused_test = godot_rust_specs::FOCUSED_TEST.lock().unwrap();
-        *focused_test = Some($test_func);
+    ($test_func:ident $(, tags: [$($tag:expr),* $(,)?])?) => {{
+        let test = $crate::TestCase::from_fn(
+            stringify!($test_func),
+            $test_func,
+            file!(),
+            line!(),
+            &[$($($tag),*)?],
+        )
+        .module_path(module_path!());
+        $crate::register_focused(test);
+    }};
+}
+
+// Focus every registered test whose name contains `pattern`.
+#[macro_export]
+macro_rules! focus_named {
+    ($pattern:expr) => {{
+        $crate::FOCUS_PATTERNS
+            .lock()
+            .unwrap()
+            .push(($pattern).to_string());
     }};
 }
 
 #[macro_export]
 macro_rules! tick {
     () => {
-        godot_rust_specs::CURRENT_TEST_ITERATION
+        $crate::CURRENT_TEST_ITERATION
             .lock()
             .unwrap()
             .clone()
@@ -36,80 +358,160 @@ macro_rules! tick {
 #[macro_export]
 macro_rules! wait {
     ($millis:expr) => {{
-        *godot_rust_specs::WANTS_REPLAY.lock().unwrap() = true;
-        *godot_rust_specs::DELAY_BEFORE_NEXT_TEST_RUN.lock().unwrap() = $millis.into();
+        *$crate::WANTS_REPLAY.lock().unwrap() = true;
+        *$crate::DELAY_BEFORE_NEXT_TEST_RUN.lock().unwrap() = $millis.into();
         return;
     }};
 }
 
 #[macro_export]
 macro_rules! test {
-    ($test_func:ident) => {{
-        let mut tests = godot_rust_specs::REGISTERED_TESTS.lock().unwrap();
-        tests.push_back($test_func);
+    ($test_func:ident $(, tags: [$($tag:expr),* $(,)?])?) => {{
+        $crate::register(
+            $crate::TestCase::from_fn(
+                stringify!($test_func),
+                $test_func,
+                file!(),
+                line!(),
+                &[$($($tag),*)?],
+            )
+            .module_path(module_path!()),
+        );
+    }};
+    // test!("name", |ctx| { ... })
+    ($name:expr, $closure:expr $(, tags: [$($tag:expr),* $(,)?])?) => {{
+        $crate::register(
+            $crate::TestCase::new($name, $closure)
+                .tags(&[$($($tag),*)?])
+                .module_path(module_path!()),
+        );
+    }};
+}
+
+// Like test!, with a fresh instance of the main scene under the test root,
+// see integration.rs.
+#[macro_export]
+macro_rules! integration_test {
+    ($test_func:ident $(, tags: [$($tag:expr),* $(,)?])?) => {{
+        $crate::register(
+            $crate::TestCase::from_fn(
+                stringify!($test_func),
+                $test_func,
+                file!(),
+                line!(),
+                &[$($($tag),*)?],
+            )
+            .module_path(module_path!())
+            .integration(),
+        );
+    }};
+}
+
+// Registers `async fn name(ctx: TestContext)` as a test, see executor.
+#[macro_export]
+macro_rules! async_test {
+    ($test_func:ident $(, tags: [$($tag:expr),* $(,)?])?) => {{
+        let test = $crate::TestCase::new_async(stringify!($test_func), $test_func);
+        $crate::register(test.tags(&[$($($tag),*)?]).module_path(module_path!()));
+    }};
+}
+
+// Registers a type implementing SpecCase as a test.
+#[macro_export]
+macro_rules! spec {
+    ($spec:ty $(, tags: [$($tag:expr),* $(,)?])?) => {{
+        let test = $crate::TestCase::new(stringify!($spec), |context| {
+            $crate::state::run_spec::<$spec>(context);
+        });
+        $crate::register(test.tags(&[$($($tag),*)?]).module_path(module_path!()));
     }};
 }
 
 #[macro_export]
 macro_rules! print_red {
     ($($arg:tt)*) => ({
-        print!("\x1B[31m");
-        print!($($arg)*);
-        print!("\x1B[0m");
-        io::stdout().flush().unwrap();
+        $crate::output::print_colored($crate::output::RED, &format!($($arg)*), false);
     });
 }
 
 #[macro_export]
 macro_rules! print_green {
     ($($arg:tt)*) => ({
-        print!("\x1B[32m");
-        print!($($arg)*);
-        print!("\x1B[0m");
-        io::stdout().flush().unwrap();
+        $crate::output::print_colored($crate::output::GREEN, &format!($($arg)*), false);
+    });
+}
+
+#[macro_export]
+macro_rules! print_yellow {
+    ($($arg:tt)*) => ({
+        $crate::output::print_colored($crate::output::YELLOW, &format!($($arg)*), false);
     });
 }
 
 #[macro_export]
 macro_rules! println_red {
     ($($arg:tt)*) => ({
-        print!("\x1B[31m");
-        print!($($arg)*);
-        print!("\x1B[0m\n");
+        $crate::output::print_colored($crate::output::RED, &format!($($arg)*), true);
     });
 }
 
 #[macro_export]
 macro_rules! println_green {
     ($($arg:tt)*) => ({
-        print!("\x1B[32m");
-        print!($($arg)*);
-        print!("\x1B[0m\n");
+        $crate::output::print_colored($crate::output::GREEN, &format!($($arg)*), true);
     });
 }
 
 #[macro_export]
 macro_rules! println_blue {
     ($($arg:tt)*) => ({
-        print!("\x1B[34m");
-        print!($($arg)*);
-        print!("\x1B[0m\n");
+        $crate::output::print_colored($crate::output::BLUE, &format!($($arg)*), true);
+    });
+}
+
+#[macro_export]
+macro_rules! println_yellow {
+    ($($arg:tt)*) => ({
+        $crate::output::print_colored($crate::output::YELLOW, &format!($($arg)*), true);
     });
 }
 
 #[macro_export]
 macro_rules! assert_approx_eq {
     ($a:expr, $b:expr, $epsilon:expr) => {
-        if ($a - $b).abs() > $epsilon {
-            panic!(
-                "assertion failed: ({} - {}).abs() <= {}. Values: {} and {}",
-                stringify!($a),
-                stringify!($b),
-                stringify!($epsilon),
-                $a,
-                $b
-            );
-        }
+        $crate::approx::assert_approx_eq(
+            &$a,
+            &$b,
+            $crate::approx::Tolerance::Absolute(($epsilon) as f64),
+            stringify!($a),
+            stringify!($b),
+        )
+    };
+}
+
+#[macro_export]
+macro_rules! assert_approx_eq_rel {
+    ($a:expr, $b:expr, $epsilon:expr) => {
+        $crate::approx::assert_approx_eq(
+            &$a,
+            &$b,
+            $crate::approx::Tolerance::Relative(($epsilon) as f64),
+            stringify!($a),
+            stringify!($b),
+        )
+    };
+}
+
+#[macro_export]
+macro_rules! assert_approx_eq_ulps {
+    ($a:expr, $b:expr, $ulps:expr) => {
+        $crate::approx::assert_approx_eq(
+            &$a,
+            &$b,
+            $crate::approx::Tolerance::Ulps(($ulps) as u64),
+            stringify!($a),
+            stringify!($b),
+        )
     };
 }
 
@@ -121,6 +523,65 @@ struct TestRunner {
     time_counter: f64,
     passes: usize,
     failures: usize,
+    skipped: usize,
+    pending: usize,
+    expected_failures: usize,
+    suite_started_at: Instant,
+    test_root: Option<Gd<Node>>,
+    pending_free: Option<Gd<Node>>,
+    finished_test: Option<FinishedTest>,
+    // Until when the finished test's threads get to end on their own.
+    threads_deadline: Option<Instant>,
+    counts_before: leaks::ObjectCounts,
+    suite_counts_start: Option<leaks::ObjectCounts>,
+    leak_reports: Vec<String>,
+    memory_before: memory::MemoryUsage,
+    test_started_at: Instant,
+    results: Vec<TestResult>,
+    // With double_run: the outcome of the current test's first run, and the
+    // tests whose second run came out differently.
+    first_run_outcome: Option<TestStatus>,
+    double_run_changes: Vec<String>,
+    // Tests that failed or were blocked, whose dependents are blocked.
+    unmet_dependencies: HashSet<String>,
+    // With pause_on_failure: the failed test, waiting to be cleaned up.
+    paused_on_failure: Option<FinishedTest>,
+    // The finished test's teardown, with its context.
+    pending_teardown: Option<(TestFn, TestContext)>,
+    started: bool,
+    warmup_frames_left: u32,
+    quitting: bool,
+}
+
+// A test that has run to completion but whose result isn't counted yet,
+// because its cleanup may still be in progress.
+struct FinishedTest {
+    name: String,
+    outcome: TestStatus,
+    duration: Duration,
+    output: String,
+    stderr: String,
+    engine_messages: Vec<errors::EngineMessage>,
+    failure: Option<String>,
+    location: Option<panics::SourceLocation>,
+    seed: u64,
+    skip_reason: Option<String>,
+    // Where the test was registered.
+    defined_at: panics::SourceLocation,
+    expected_failure: Option<String>,
+    input_recording: Vec<recording::RecordedEvent>,
+    frames: Vec<Gd<Image>>,
+    metadata: Vec<(String, serde_json::Value)>,
+    log: Vec<logging::LogRecord>,
+}
+
+#[godot_api]
+impl TestRunner {
+    // Emitted when the suite is done, with the results (as written to the
+    // results file) and the exit code the runner quits with, or would quit
+    // with when embedded or when there's a quit hook.
+    #[signal]
+    fn tests_finished(results: Dictionary, exit_code: i64);
 }
 
 #[godot_api]
@@ -131,139 +592,1094 @@ impl INode for TestRunner {
             time_counter: 0.0,
             passes: 0,
             failures: 0,
+            skipped: 0,
+            pending: 0,
+            expected_failures: 0,
+            suite_started_at: Instant::now(),
+            test_root: None,
+            pending_free: None,
+            finished_test: None,
+            threads_deadline: None,
+            counts_before: leaks::ObjectCounts::default(),
+            suite_counts_start: None,
+            leak_reports: Vec::new(),
+            memory_before: memory::MemoryUsage::default(),
+            test_started_at: Instant::now(),
+            results: Vec::new(),
+            first_run_outcome: None,
+            double_run_changes: Vec::new(),
+            unmet_dependencies: HashSet::new(),
+            paused_on_failure: None,
+            pending_teardown: None,
+            started: false,
+            warmup_frames_left: 0,
+            quitting: false,
         }
     }
 
     fn ready(&mut self) {
-        println!("");
+        self.base_mut().set_process_mode(ProcessMode::ALWAYS);
+        output::colors_enabled();
+        output::print_plain("", true);
+
+        match config::env_user_args() {
+            Ok(args) => config::apply_user_args(&args),
+            Err(error) => println_red!("Ignoring environment variables: {}", error),
+        }
+
+        match config::parse_user_args(&config::cmdline_user_args()) {
+            Ok(args) => config::apply_user_args(&args),
+            Err(error) => println_red!("Ignoring command line arguments: {}", error),
+        }
+
+        panics::install_hook(|info| {
+            if info.payload().downcast_ref::<skip::Skip>().is_some() {
+                return;
+            }
+
+            panics::record_location(info);
+
+            if panics::SUPPRESS_PANIC_OUTPUT.lock().unwrap().clone() {
+                return;
+            }
 
-        panic::set_hook(Box::new(|info| {
             println_red!("{}", info);
-            let backtrace = Backtrace::new();
-            let backtrace = format!("{:?}", backtrace);
-
-            let re = Regex::new(
-                r"(?s)\w+\d+: godot_rust_specs::impl\$0::run_test.*"
-            ).unwrap();
-            let backtrace = re.replace_all(&backtrace, "");
-
-            let re = Regex::new(
-                r"(?s)\w+\d+: godot_rust_specs::TestRunner::run_test::.*"
-            ).unwrap();
-            let backtrace = re.replace_all(&backtrace, "");
-
-            let re = Regex::new(
-                r"(?s).*/library\\core\\src\\panicking.rs:\d+"
-            ).unwrap();
-            let backtrace = re.replace_all(&backtrace, "");
-
-            let re = Regex::new(
-                r"(?s).*/library/core/src/panicking.rs:\d+:\d+"
-            ).unwrap();
-            let backtrace = re.replace_all(&backtrace, "");
-
-            println_blue!("{}", backtrace);
-        }));
+
+            let (mode, skip, max_frames) = config::with(|config| {
+                (config.backtrace, config.backtrace_skip.clone(), config.backtrace_max_frames)
+            });
+            let backtrace = backtraces::current(mode, &skip, max_frames);
+
+            if !backtrace.is_empty() {
+                println_blue!("{}", backtrace);
+            }
+        });
+    }
+
+    fn input(&mut self, event: Gd<InputEvent>) {
+        if self.paused_on_failure.is_some() {
+            debug::handle_input(&event);
+            return;
+        }
+
+        recording::record_real(&event);
     }
 
     fn process(&mut self, delta: f64) {
-        self.time_counter += delta;
+        if self.quitting {
+            return;
+        }
+
+        if self.paused_on_failure.is_some() {
+            if debug::take_command().is_some() {
+                let finished = self.paused_on_failure.take().unwrap();
+                self.finish_test(finished);
+            }
+            return;
+        }
+
+        input::process_scheduled();
+
+        if let Some(root) = self.pending_free.clone() {
+            if root.is_instance_valid() && config::with(|config| config.cleanup) == CleanupStrategy::Incremental {
+                let spent = free_incrementally(&root, config::with(|config| config.frame_budget));
+                if let Some(finished) = &mut self.finished_test {
+                    finished.duration += spent;
+                }
+            }
+            if root.is_instance_valid() {
+                return;
+            }
+            self.pending_free = None;
+        }
+
+        // Without blocking the frame, unlike joining them would.
+        if let Some(deadline) = self.threads_deadline {
+            if threads::still_running() && Instant::now() < deadline {
+                return;
+            }
+            self.threads_deadline = None;
+        }
+
+        if self.finished_test.is_some() {
+            self.report_finished_test();
+            return;
+        }
+
+        if self.test_root.is_some() {
+            profiler::sample(delta);
+
+            if let Some(viewport) = self.test_viewport() {
+                frames::capture(viewport);
+            }
+        }
+
+        if !self.started {
+            self.started = true;
+            self.start_suite();
+
+            if self.quitting {
+                return;
+            }
+        }
+
+        if self.warmup_frames_left > 0 {
+            self.warmup_frames_left -= 1;
+            if self.warmup_frames_left == 0 {
+                self.suite_started_at = Instant::now();
+            }
+            return;
+        }
+
+        self.time_counter += match config::with(|config| config.fixed_fps) {
+            Some(fps) => 1.0 / fps as f64,
+            None => delta,
+        };
 
         let delay = DELAY_BEFORE_NEXT_TEST_RUN.lock().unwrap().clone();
 
         if self.time_counter > delay {
             self.time_counter = 0.0;
-            self.run_test();
+
+            // Tests that don't wait run back to back within the same frame,
+            // until one waits or the frame budget is used up.
+            let budget = config::with(|config| config.frame_budget);
+            let frame_started_at = Instant::now();
+
+            while self.run_test()
+                && !self.quitting
+                && frame_started_at.elapsed() < budget
+                && DELAY_BEFORE_NEXT_TEST_RUN.lock().unwrap().clone() <= 0.0
+            {}
         }
     }
 }
 
 impl TestRunner {
+    // Runs once, on the first frame, after the user has registered their
+    // tests.
+    fn start_suite(&mut self) {
+        gdscript::register_scripts();
+
+        if REGISTERED_TESTS.lock().unwrap().is_empty() {
+            println_red!("No tests were registered.");
+            println_red!(
+                "Register them with test!(...) before the TestRunner's first frame, e.g. from your ExtensionLibrary's on_level_init or a node's ready()."
+            );
+            self.exit(EXIT_NO_TESTS);
+            return;
+        }
+
+        if !self.remove_duplicate_tests() {
+            self.exit(1);
+            return;
+        }
+
+        *DELAY_BEFORE_NEXT_TEST_RUN.lock().unwrap() = config::with(|config| config.default_delay);
+
+        let registered: Vec<TestCase> = REGISTERED_TESTS.lock().unwrap().iter().cloned().collect();
+
+        if config::with(|config| config.rerun_failed) {
+            let failed = report::previously_failed_tests();
+            let mut tests = REGISTERED_TESTS.lock().unwrap();
+
+            if failed.is_empty() {
+                println_yellow!("No failures recorded in the previous run, running all tests");
+            } else {
+                tests.retain(|test| failed.iter().any(|name| *name == test.name));
+                println_yellow!("Rerunning {} test(s) that failed last time", tests.len());
+            }
+        }
+
+        let focused: Vec<String> =
+            FOCUSED_TESTS.lock().unwrap().iter().map(|test| test.name.clone()).collect();
+        let patterns = FOCUS_PATTERNS.lock().unwrap().clone();
+
+        if !focused.is_empty() || !patterns.is_empty() {
+            if config::with(|config| config.forbid_focus) {
+                println_red!("Focused tests are not allowed in this run (forbid_focus is set):");
+                for name in &focused {
+                    println_red!("  focus!({})", name);
+                }
+                for pattern in &patterns {
+                    println_red!("  focus_named!({:?})", pattern);
+                }
+                println_red!("Replace them with test! before merging.");
+                self.exit(1);
+                return;
+            }
+
+            let mut tests = REGISTERED_TESTS.lock().unwrap();
+            tests.retain(|test| {
+                focused.contains(&test.name)
+                    || patterns.iter().any(|pattern| test.name.contains(pattern.as_str()))
+            });
+            println_yellow!("Running {} focused test(s)", tests.len());
+        }
+
+        REGISTERED_TESTS
+            .lock()
+            .unwrap()
+            .retain(|test| config::matches_filters(&test.name) && config::matches_modules(test.module_path));
+
+        {
+            let mut tests = REGISTERED_TESTS.lock().unwrap();
+            *tests = schedule::with_prerequisites(&registered, std::mem::take(&mut *tests));
+        }
+
+        if let Some(shard) = config::with(|config| config.shard) {
+            let mut tests = REGISTERED_TESTS.lock().unwrap();
+            let mut position = 0;
+            tests.retain(|_| {
+                position += 1;
+                shard.includes(position - 1)
+            });
+            println_blue!("Shard {}/{}: running {} test(s)", shard.index, shard.total, tests.len());
+        }
+
+        let ordered = schedule::order(&registered, std::mem::take(&mut *REGISTERED_TESTS.lock().unwrap()));
+        match ordered {
+            Ok(tests) => *REGISTERED_TESTS.lock().unwrap() = tests,
+            Err(message) => {
+                println_red!("{}", message);
+                self.exit(1);
+                return;
+            }
+        }
+
+        if config::with(|config| config.list_only) {
+            for test in REGISTERED_TESTS.lock().unwrap().iter() {
+                output::print_plain(&test.describe(), true);
+            }
+
+            self.exit(0);
+            return;
+        }
+
+        output::set_muted(config::with(|config| config.verbosity == config::Verbosity::Quiet));
+        self.suite_started_at = Instant::now();
+
+        println_blue!("Randomized with seed {}", random::suite_seed());
+        progress::start(REGISTERED_TESTS.lock().unwrap().len());
+
+        if let Some(size) = config::with(|config| config.viewport_size) {
+            viewport::apply_suite_size(size);
+        }
+
+        // Shards of one run share the directory, and one starting late would
+        // delete what the others saved.
+        if config::with(|config| config.clean_artifacts && config.shard.is_none()) {
+            artifacts::clean_previous_run();
+        }
+
+        if config::with(|config| config.mute_audio) {
+            audio::mute_master();
+        }
+
+        if let Some(fps) = config::with(|config| config.fixed_fps) {
+            // One physics tick per frame, without jitter correction, so the
+            // number of ticks only depends on the number of frames.
+            let mut engine = godot::engine::Engine::singleton();
+            engine.set_max_fps(fps as i32);
+            engine.set_physics_ticks_per_second(fps as i32);
+            engine.set_max_physics_steps_per_frame(1);
+            engine.set_physics_jitter_fix(0.0);
+            println_blue!("Stepping at a fixed {} frames per second", fps);
+        }
+
+        let names: Vec<String> = REGISTERED_TESTS.lock().unwrap().iter().map(|test| test.name.clone()).collect();
+        observer::suite_started(&names);
+
+        // Each test straight after itself; the second runs are only compared
+        // with the first, see report_finished_test.
+        if config::with(|config| config.double_run) {
+            let mut tests = REGISTERED_TESTS.lock().unwrap();
+            *tests = tests.drain(..).flat_map(|test| [test.clone(), test]).collect();
+            println_blue!("Running every test twice to find state leaking between tests");
+        }
+
+        warmup::run(&self.base().clone());
+        self.warmup_frames_left = config::with(|config| config.warmup_frames);
+    }
+
     fn quit(&mut self) {
+        output::set_muted(false);
+        fixtures::free_all();
+
         let passes = self.passes;
         let failures = self.failures;
-        let total = passes + failures;
+        let skipped = self.skipped;
+        let total = passes + failures + skipped + self.pending + self.expected_failures;
+
+        if !self.leak_reports.is_empty() {
+            output::print_plain("\n", true);
+            for report in &self.leak_reports {
+                println_yellow!("{}", report);
+            }
+
+            if let Some(start) = self.suite_counts_start {
+                let end = leaks::ObjectCounts::current();
+                println_yellow!("\n{}", leaks::describe_trend(start, end));
+            }
+        }
+
+        if config::with(|config| config.format) != report::Format::Documentation {
+            for (status, heading) in [(TestStatus::Skipped, "Skipped"), (TestStatus::Pending, "Pending")] {
+                let tests: Vec<&TestResult> =
+                    self.results.iter().filter(|result| result.status == status).collect();
+                if tests.is_empty() {
+                    continue;
+                }
+
+                println_yellow!("\n\n{}:", heading);
+                for result in tests {
+                    println_yellow!(
+                        "  {} ({})",
+                        result.name,
+                        result.skip_reason.as_deref().unwrap_or("no reason given")
+                    );
+                }
+            }
+        }
+
+        if config::with(|config| config.verbosity == config::Verbosity::Quiet) {
+            let failed_tests: Vec<&TestResult> =
+                self.results.iter().filter(|result| result.status == TestStatus::Failed).collect();
+            if !failed_tests.is_empty() {
+                println_red!("\nFailures:");
+                for result in failed_tests {
+                    let location = result.location.as_ref().or(result.defined_at.as_ref());
+                    match location {
+                        Some(location) => println_red!("\n  {} ({})", result.name, location),
+                        None => println_red!("\n  {}", result.name),
+                    }
+                    for line in result.failure.as_deref().unwrap_or("failed").lines() {
+                        println_red!("    {}", line);
+                    }
+                }
+            }
+        }
+
+        if let Some(threshold) = config::with(|config| config.slow_test_threshold) {
+            let mut slow_tests: Vec<&TestResult> = self
+                .results
+                .iter()
+                .filter(|result| result.status != TestStatus::Skipped && result.duration > threshold)
+                .collect();
+            slow_tests.sort_by(|a, b| b.duration.cmp(&a.duration));
+
+            if !slow_tests.is_empty() {
+                println_yellow!("\n\nSlow tests (over {:?}):", threshold);
+                for result in slow_tests {
+                    println_yellow!("  {} ({:.2}s)", result.name, result.duration.as_secs_f64());
+                }
+            }
+        }
+
+        let mut counts = vec![format!("{} examples", total), format!("{} failures", failures)];
+        for (count, label) in [
+            (skipped, "skipped"),
+            (self.pending, "pending"),
+            (self.expected_failures, "expected failures"),
+        ] {
+            if count > 0 {
+                counts.push(format!("{} {}", count, label));
+            }
+        }
+        let summary = counts.join(", ");
+
+        if failures > 0 {
+            println_red!("\n\n{}", summary);
+        } else if total > passes {
+            println_yellow!("\n\n{}", summary);
+        } else {
+            println_green!("\n\n{}", summary);
+        }
+
+        // Everything needed to run the same suite again.
+        println_blue!("Finished in {:.2}s", self.suite_started_at.elapsed().as_secs_f64());
+        println_blue!("Randomized with seed {}", random::suite_seed());
+        let (filters, modules, shard) =
+            config::with(|config| (config.filters.clone(), config.modules.clone(), config.shard));
+        if !filters.is_empty() {
+            println_blue!("Filtered by: {}", filters.join(", "));
+        }
+        if !modules.is_empty() {
+            println_blue!("Modules: {}", modules.join(", "));
+        }
+        if let Some(shard) = shard {
+            println_blue!("Shard {}/{}", shard.index, shard.total);
+        }
 
         if failures > 0 {
-            println_red!("\n\n{} examples, {} failures", total, failures);
+            let failed: Vec<&str> = self
+                .results
+                .iter()
+                .filter(|result| result.status == TestStatus::Failed)
+                .map(|result| result.name.as_str())
+                .collect();
+            let format = config::with(|config| config.format);
+
+            println_blue!("\nRerun the failures with:");
+            println_blue!("  {}", report::rerun_command(&failed, random::suite_seed(), format));
+        }
+
+        #[cfg(feature = "bench")]
+        bench::print_report();
+
+        observer::suite_ended(&self.results);
+        let flaky = history::record(&self.results);
+        history::print_flaky(&flaky);
+        report::write_results_file(&self.results, &flaky);
+
+        if !self.double_run_changes.is_empty() {
+            println_red!("\nOutcome changed when run a second time (state leaking between tests?):");
+            for change in &self.double_run_changes {
+                println_red!("  {}", change);
+            }
+        }
+
+        self.exit(if failures > 0 || !self.double_run_changes.is_empty() { 1 } else { 0 });
+    }
+
+    fn exit(&mut self, exit_code: i32) {
+        self.quitting = true;
+        panics::restore_hook();
+        coverage::flush();
+
+        let results = serde_json::to_string(&report::results_json(&self.results)).unwrap_or_default();
+        let results = godot::engine::Json::parse_string(results.into());
+        self.base_mut().emit_signal("tests_finished".into(), &[results, exit_code.to_variant()]);
+
+        if let Some(hook) = quit::take_hook() {
+            hook(exit_code);
+            return;
+        }
+
+        if config::with(|config| config.embedded) {
+            return;
+        }
+
+        self.base().get_tree().unwrap().quit_ex().exit_code(exit_code).done();
+    }
+
+    // Each test gets a fresh child node as its root, so nothing it adds ends
+    // up directly under the runner. It lives until the test finishes,
+    // including across `wait!` replays.
+    fn test_root(&mut self, root_type: Option<config::RootType>) -> Gd<Node> {
+        if let Some(root) = &self.test_root {
+            return root.clone();
+        }
+
+        errors::start_capture();
+        globals::snapshot();
+        panics::set_in_test(true);
+
+        self.counts_before = leaks::ObjectCounts::current();
+        self.memory_before = memory::MemoryUsage::current();
+        self.test_started_at = Instant::now();
+        if self.suite_counts_start.is_none() {
+            self.suite_counts_start = Some(self.counts_before);
+        }
+
+        let (isolate_viewports, isolate_physics) =
+            config::with(|config| (config.isolate_viewports, config.isolate_physics));
+
+        let mut root = if isolate_viewports || isolate_physics {
+            viewport::isolated_root(isolate_physics)
         } else {
-            println_green!("\n\n{} examples, 0 failures", passes);
+            root_type.unwrap_or_else(|| config::with(|config| config.root_type)).instantiate()
+        };
+        root.set_name("TestRoot".into());
+        // The runner always processes (so a test pausing the tree can't stop
+        // the suite), but the test's nodes should pause like a game's would.
+        root.set_process_mode(ProcessMode::PAUSABLE);
+        self.base_mut().add_child(root.clone());
+        self.test_root = Some(root.clone());
+
+        root
+    }
+
+    // Drops repeated registrations of the same test, warning about each. Returns
+    // false if there were any and the config says to fail instead.
+    fn remove_duplicate_tests(&self) -> bool {
+        let policy = config::with(|config| config.duplicates);
+        let mut tests = REGISTERED_TESTS.lock().unwrap();
+        let mut unique: VecDeque<TestCase> = VecDeque::new();
+        let mut found = false;
+
+        for test in tests.drain(..) {
+            let duplicate = unique.iter().any(|other| {
+                other.name == test.name
+                    || (other.fn_address.is_some() && other.fn_address == test.fn_address)
+            });
+
+            if duplicate {
+                found = true;
+                match policy {
+                    config::DuplicatePolicy::Warn => {
+                        println_yellow!("Warning: {} is registered more than once, running it once", test.name)
+                    }
+                    config::DuplicatePolicy::Fail => {
+                        println_red!("{} is registered more than once", test.name)
+                    }
+                }
+            } else {
+                unique.push_back(test);
+            }
         }
 
+        *tests = unique;
 
-        self.base().get_tree().unwrap().quit();
+        !(found && policy == config::DuplicatePolicy::Fail)
     }
 
-    // Remove all node's childen between tests
+    // What the current test renders to: its own SubViewport when isolated,
+    // otherwise the root window.
+    fn test_viewport(&self) -> Option<Gd<Viewport>> {
+        match self.test_root.clone().map(|root| root.try_cast::<Viewport>()) {
+            Some(Ok(viewport)) => Some(viewport),
+            _ => self.base().get_viewport(),
+        }
+    }
+
+    // Free the test's root (and everything the test added under it)
     fn cleanup(&mut self) {
         let mut value = CURRENT_TEST_ITERATION.lock().unwrap();
         *value = 0;
 
         let mut value = DELAY_BEFORE_NEXT_TEST_RUN.lock().unwrap();
-        *value = 0.0;
+        *value = config::with(|config| config.default_delay);
 
         let mut value = WANTS_REPLAY.lock().unwrap();
         *value = false;
 
-        let mut value = FOCUSED_TEST.lock().unwrap();
-        *value = None;
+        input::reset();
+        recording::reset();
+        state::reset();
+        executor::reset();
+        #[cfg(feature = "http-mock")]
+        http_mock::reset();
+        sandbox::reset();
+        physics::reset();
+        viewport::reset();
+        autoload::reset();
+        integration::reset();
+        let disconnected = signals::reset(self.test_root.as_ref());
+        multiplayer::reset();
 
-        let children = self.base().get_children();
-        for child in children.iter_shared() {
-            child.free();
+        panics::set_in_test(false);
+
+        let restored = globals::restore();
+        if !restored.is_empty() && config::with(|config| config.verbosity == config::Verbosity::Verbose) {
+            output::print_plain(&format!("\nRestored after the test: {}", restored.join(", ")), true);
+        }
+        if disconnected > 0 && config::with(|config| config.verbosity == config::Verbosity::Verbose) {
+            output::print_plain(&format!("\nDisconnected {} signal connection(s) the test made", disconnected), true);
         }
-    }
 
-    fn run_test(&mut self) {
-        let focus = FOCUSED_TEST.lock().unwrap().clone();
-        let tests = crate::REGISTERED_TESTS.lock().unwrap();
+        if let Some(mut root) = self.test_root.take() {
+            let strategy = config::with(|config| config.cleanup);
 
-        let current_test: Option<&fn(&Gd<Node>)>;
+            if config::with(|config| config.isolate_physics) {
+                physics::deactivate_spaces(&root);
+            }
 
-        if focus.is_some() {
-            current_test = focus.as_ref();
-        } else {
-            let current_test_index = CURRENT_TEST_INDEX.lock().unwrap().clone();
-            current_test = tests.iter().nth(current_test_index);
+            match strategy {
+                CleanupStrategy::Immediate => root.free(),
+                CleanupStrategy::Deferred => {
+                    root.queue_free();
+                    self.pending_free = Some(root);
+                }
+                CleanupStrategy::Incremental => {
+                    // Nothing left in it should run while it's taken apart.
+                    root.set_process_mode(ProcessMode::DISABLED);
+                    self.pending_free = Some(root);
+                }
+            }
         }
+    }
+
+    // Runs (or continues) the current test. True if it finished and was
+    // reported, i.e. the next test can start right away.
+    fn run_test(&mut self) -> bool {
+        let tests = crate::REGISTERED_TESTS.lock().unwrap();
+
+        let current_test_index = CURRENT_TEST_INDEX.lock().unwrap().clone();
+        let current_test = tests.iter().nth(current_test_index);
 
         if current_test.is_none() {
             self.quit();
-            return;
+            return false;
         }
 
-        let result = panic::catch_unwind(|| {
-            current_test.unwrap()(&self.base());
-        });
+        let test = current_test.unwrap().clone();
+        drop(tests);
+
+        let blocked_by = test.depends_on.iter().find(|name| self.unmet_dependencies.contains(*name)).cloned();
+        if blocked_by.is_some() {
+            self.unmet_dependencies.insert(test.name.clone());
+        }
+
+        let root = self.test_root(test.root_type);
+        let context = TestContext::new(root, test.name.clone());
+
+        if CURRENT_TEST_ITERATION.lock().unwrap().clone() == 0 {
+            random::seed_test(&test.name);
+            artifacts::set_current_test(&test.name);
+            observer::test_started(&test.name);
+        }
+
+        let capturing = config::with(|config| config.capture_output) && capture::start_stdout();
+
+        output::pause_log(capturing);
+
+        let dry_run = config::with(|config| config.dry_run);
+        let first_frame = CURRENT_TEST_ITERATION.lock().unwrap().clone() == 0;
+        let mut body_started = false;
+
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            if test.requires_rendering {
+                skip::require_rendering();
+            }
+
+            if !dry_run && test.pending.is_none() && blocked_by.is_none() {
+                if first_frame {
+                    for required in &test.required_autoloads {
+                        autoload::require_autoload(&required.name, required.substitute.as_deref());
+                    }
+                }
+
+                if test.integration && first_frame {
+                    integration::setup(&context);
+                }
 
-        match result {
+                body_started = true;
+                (test.func)(&context);
+            }
+        }));
+
+        capture::stop_stdout();
+
+        output::pause_log(false);
+
+        let mut failure = None;
+        let mut location = None;
+        let mut skip_reason = None;
+
+        let timeout = config::with(|config| config.test_timeout);
+        let max_iterations = config::with(|config| config.max_iterations);
+        let iterations = CURRENT_TEST_ITERATION.lock().unwrap().clone() + 1;
+
+        let mut outcome = match result {
             Ok(_) => {
                 if WANTS_REPLAY.lock().unwrap().clone() {
                     let mut value = WANTS_REPLAY.lock().unwrap();
                     *value = false;
 
-                    let mut value = CURRENT_TEST_ITERATION.lock().unwrap();
-                    *value += 1;
+                    match timeout {
+                        Some(timeout) if self.test_started_at.elapsed() > timeout => {
+                            failure = Some(format!("timed out after {:?}", timeout));
+                            TestStatus::Failed
+                        }
+                        _ if max_iterations.map_or(false, |max| iterations >= max) => {
+                            failure = Some(format!(
+                                "exceeded {} iterations — possible infinite wait loop",
+                                iterations
+                            ));
+                            TestStatus::Failed
+                        }
+                        _ => {
+                            let mut value = CURRENT_TEST_ITERATION.lock().unwrap();
+                            *value += 1;
 
-                    return;
+                            return false;
+                        }
+                    }
                 } else {
-                    self.passes += 1;
-                    print_green!(".");
+                    TestStatus::Passed
                 }
             }
-            Err(_error) => {
+            Err(error) if error.downcast_ref::<skip::Skip>().is_some() => {
+                skip_reason = error.downcast_ref::<skip::Skip>().map(|skip| skip.reason.clone());
+                TestStatus::Skipped
+            }
+            Err(error) => {
+                failure = Some(panics::panic_message(&error));
+                location = panics::take_last_location();
+
+                if config::with(|config| config.screenshot_on_failure) {
+                    let path = self.test_viewport().and_then(|viewport| screenshot::capture(viewport, &test.name));
+                    if let Some(path) = path {
+                        println_blue!("Screenshot saved to {}", path);
+                    }
+                }
+
+                TestStatus::Failed
+            }
+        };
+
+        if dry_run {
+            outcome = TestStatus::Skipped;
+            skip_reason = Some("dry run".to_string());
+        }
+
+        if let Some(reason) = &test.pending {
+            outcome = TestStatus::Pending;
+            skip_reason = Some(reason.clone());
+        }
+
+        // Run by finish_test, so with pause_on_failure the scene is still
+        // there to look at while paused.
+        self.pending_teardown = test.teardown.clone().filter(|_| body_started).map(|teardown| (teardown, context));
+
+        if let Some(dependency) = &blocked_by {
+            outcome = TestStatus::Skipped;
+            skip_reason = Some(format!("blocked, {} failed", dependency));
+        }
+
+        #[cfg(feature = "tokio")]
+        for message in runtime::finish_test() {
+            outcome = TestStatus::Failed;
+            failure.get_or_insert_with(|| format!("task spawned on the tokio runtime panicked: {}", message));
+        }
+
+        let stderr = errors::finish_capture();
+        let engine_messages = errors::parse_engine_messages(&stderr);
+        if errors::should_fail(&engine_messages) {
+            outcome = TestStatus::Failed;
+            failure.get_or_insert_with(|| "engine errors were reported".to_string());
+        }
+
+        if outcome == TestStatus::Failed && config::with(|config| config.save_scene_on_failure) {
+            let path = self.test_root.as_ref().and_then(|root| scene::save_failure_scene(root, &test.name));
+            if let Some(path) = path {
+                println_blue!("Scene saved to {}", path);
+            }
+        }
+
+        let input_recording = recording::take();
+        let frames = frames::take();
+        let metadata = metadata::take();
+        let log = logging::take();
+
+        let finished = FinishedTest {
+            name: test.name.clone(),
+            outcome,
+            duration: self.test_started_at.elapsed(),
+            output: capture::take_stdout(),
+            stderr,
+            engine_messages,
+            failure,
+            location,
+            seed: random::current_seed(),
+            skip_reason,
+            defined_at: panics::SourceLocation { file: test.file.to_string(), line: test.line },
+            expected_failure: test.expected_failure.clone(),
+            input_recording,
+            frames,
+            metadata,
+            log,
+        };
+
+        if outcome == TestStatus::Failed && config::with(|config| config.pause_on_failure) {
+            debug::pause_on_failure(&finished.name, finished.failure.as_deref());
+            self.paused_on_failure = Some(finished);
+            return false;
+        }
+
+        self.finish_test(finished)
+    }
+
+    // Cleans up after a test that has run to completion and reports it,
+    // unless its root is still being freed. True if it was reported.
+    fn finish_test(&mut self, mut finished: FinishedTest) -> bool {
+        // This runs for failures the body never saw too, e.g. timeouts.
+        if let Some((teardown, context)) = self.pending_teardown.take() {
+            if let Err(error) = panic::catch_unwind(panic::AssertUnwindSafe(|| teardown(&context))) {
+                if finished.outcome != TestStatus::Failed {
+                    finished.outcome = TestStatus::Failed;
+                    finished.location = panics::take_last_location();
+                }
+                finished
+                    .failure
+                    .get_or_insert_with(|| format!("teardown failed: {}", panics::panic_message(&error)));
+            }
+        }
+
+        let cleanup_started_at = Instant::now();
+        self.cleanup();
+        finished.duration += cleanup_started_at.elapsed();
+
+        self.finished_test = Some(finished);
+
+        if threads::still_running() {
+            self.threads_deadline = Some(Instant::now() + threads::LEAK_GRACE);
+        }
+
+        if self.pending_free.is_some() || self.threads_deadline.is_some() {
+            return false;
+        }
+
+        self.report_finished_test();
+        true
+    }
+
+    // Runs once the finished test's root is gone, so the post-test checks
+    // see the engine state after cleanup.
+    fn report_finished_test(&mut self) {
+        let Some(finished) = self.finished_test.take() else {
+            return;
+        };
+
+        let mut outcome = finished.outcome;
+        let mut failure = finished.failure.clone();
+
+        for message in threads::finish_test() {
+            outcome = TestStatus::Failed;
+            failure.get_or_insert(message);
+        }
+
+        let label = finished.name.as_str();
+
+        let delta = leaks::ObjectCounts::current() - self.counts_before - fixtures::take_retained();
+        let leak_check = leaks::check(label, delta);
+        if leak_check.failed {
+            outcome = TestStatus::Failed;
+            failure.get_or_insert_with(|| leak_check.reports.join("\n"));
+        }
+        self.leak_reports.extend(leak_check.reports);
+
+        let memory_delta = memory::MemoryUsage::current() - self.memory_before;
+        if config::with(|config| config.verbosity == config::Verbosity::Verbose) {
+            output::print_plain(&format!("\n{}", memory::describe(label, memory_delta)), true);
+        }
+
+        if let Some(reason) = &finished.expected_failure {
+            match outcome {
+                TestStatus::Failed => outcome = TestStatus::ExpectedFailure,
+                TestStatus::Passed => {
+                    outcome = TestStatus::Failed;
+                    failure = Some(format!("expected to fail ({}), but it passed", reason));
+                }
+                _ => {}
+            }
+        }
+
+        if outcome == TestStatus::Failed {
+            self.unmet_dependencies.insert(finished.name.clone());
+        }
+
+        if config::with(|config| config.double_run) {
+            if *CURRENT_TEST_INDEX.lock().unwrap() % 2 == 1 {
+                if let Some(first) = self.first_run_outcome.take().filter(|first| *first != outcome) {
+                    let mut change =
+                        format!("{}: {} the first time, {} the second", label, first.as_str(), outcome.as_str());
+                    if let Some(failure) = failure.as_deref().and_then(|failure| failure.lines().next()) {
+                        change.push_str(&format!(" ({})", failure));
+                    }
+                    self.double_run_changes.push(change);
+                }
+
+                *CURRENT_TEST_INDEX.lock().unwrap() += 1;
+                return;
+            }
+
+            self.first_run_outcome = Some(outcome);
+        }
+
+        let profile = profiler::finish(label, outcome == TestStatus::Failed, finished.duration);
+        if let Some(path) = profile {
+            output::print_plain(&format!("\nProfile of {} saved to {}", label, path), true);
+        }
+
+        if outcome == TestStatus::Failed {
+            if let Some(path) = frames::save(label, &finished.frames) {
+                output::print_plain(&format!("\nLast frames of {} saved to {}", label, path), true);
+            }
+
+            if let Some(path) = recording::save(label, &finished.input_recording) {
+                output::print_plain(
+                    &format!("\nInput of {} saved to {} (replay it with replay_input!)", label, path),
+                    true,
+                );
+            }
+        }
+
+        // Verbose output lists every test, like the documentation format,
+        // with its duration.
+        let verbose = config::with(|config| config.verbosity == config::Verbosity::Verbose);
+        let format = match config::with(|config| config.format) {
+            _ if verbose => report::Format::Documentation,
+            format => format,
+        };
+        let duration = if verbose {
+            format!(" ({:.3}s)", finished.duration.as_secs_f64())
+        } else {
+            String::new()
+        };
+
+        match outcome {
+            TestStatus::Passed => {
+                self.passes += 1;
+                let slow = config::with(|config| config.slow_test_threshold)
+                    .map_or(false, |threshold| finished.duration > threshold);
+                match format {
+                    report::Format::Documentation if slow => println_yellow!(
+                        "{} (slow: {:.2}s)",
+                        finished.name,
+                        finished.duration.as_secs_f64()
+                    ),
+                    report::Format::Documentation => println_green!("{}{}", finished.name, duration),
+                    _ => print_green!("."),
+                }
+            }
+            TestStatus::Failed => {
                 self.failures += 1;
-                print_red!("F");
+                match format {
+                    report::Format::Documentation => println_red!("{} (FAILED){}", finished.name, duration),
+                    _ => print_red!("F"),
+                }
+
+                match &finished.location {
+                    Some(location) if *location != finished.defined_at => println_red!(
+                        "\n{} failed at {} (defined at {})",
+                        finished.name,
+                        location,
+                        finished.defined_at
+                    ),
+                    _ => println_red!("\n{} failed at {}", finished.name, finished.defined_at),
+                }
+
+                // Failures without a panic (timeouts, engine errors, leaks) are
+                // annotated on the test's definition instead.
+                let message = failure.as_deref().unwrap_or("failed");
+                let location = finished.location.as_ref().unwrap_or(&finished.defined_at);
+                report::report_failure(&finished.name, message, Some(location));
+
+                println_yellow!(
+                    "\n{} ran with seed {} (reproduce with --seed {})",
+                    finished.name,
+                    finished.seed,
+                    random::suite_seed()
+                );
+            }
+            TestStatus::Pending => {
+                self.pending += 1;
+                match format {
+                    report::Format::Documentation => {
+                        println_yellow!("{} (pending: {})", finished.name, finished.skip_reason.as_deref().unwrap_or(""))
+                    }
+                    _ => print_yellow!("*"),
+                }
+            }
+            TestStatus::ExpectedFailure => {
+                self.expected_failures += 1;
+                match format {
+                    report::Format::Documentation => println_yellow!(
+                        "{} (expected failure: {}){}",
+                        finished.name,
+                        finished.expected_failure.as_deref().unwrap_or(""),
+                        duration
+                    ),
+                    _ => print_yellow!("x"),
+                }
+            }
+            TestStatus::Skipped => {
+                self.skipped += 1;
+                match format {
+                    report::Format::Documentation => match &finished.skip_reason {
+                        Some(reason) => println_yellow!("{} (skipped: {})", finished.name, reason),
+                        None => println_yellow!("{} (skipped)", finished.name),
+                    },
+                    _ => print_yellow!("*"),
+                }
+            }
+        }
+
+        progress::test_finished();
+
+        self.results.push(TestResult {
+            name: finished.name.clone(),
+            status: outcome,
+            duration: finished.duration,
+            failure: failure.clone(),
+            location: finished.location.clone(),
+            defined_at: Some(finished.defined_at.clone()),
+            memory: memory_delta,
+            seed: finished.seed,
+            skip_reason: finished.skip_reason.clone(),
+            metadata: finished.metadata.clone(),
+            // Passing tests' logs only make it into reports in verbose runs.
+            log: if outcome == TestStatus::Failed || verbose { finished.log.clone() } else { Vec::new() },
+        });
+        observer::test_finished(self.results.last().unwrap());
+
+        if !finished.output.is_empty() && (outcome == TestStatus::Failed || verbose) {
+            output::print_plain(&format!("\n--- output of {} ---", finished.name), true);
+            output::print_plain(&finished.output, false);
+            output::print_plain("--- end of output ---", true);
+        }
+
+        if !finished.metadata.is_empty() && (outcome == TestStatus::Failed || verbose) {
+            output::print_plain(&format!("\n--- recorded by {} ---", finished.name), true);
+            output::print_plain(&metadata::describe(&finished.metadata), true);
+        }
+
+        if !finished.log.is_empty() && (outcome == TestStatus::Failed || verbose) {
+            output::print_plain(&format!("\n--- log of {} ---", finished.name), true);
+            output::print_plain(&logging::describe(&finished.log), true);
+            output::print_plain("--- end of log ---", true);
+        }
+
+        if !finished.stderr.is_empty() && (outcome == TestStatus::Failed || verbose) {
+            output::print_plain(&format!("\n--- stderr of {} ---", finished.name), true);
+            output::print_plain(&finished.stderr, false);
+            output::print_plain("--- end of stderr ---", true);
+        }
+
+        if !finished.engine_messages.is_empty() && outcome == TestStatus::Failed {
+            println_red!(
+                "\n{} reported {} engine error(s)/warning(s):",
+                finished.name,
+                finished.engine_messages.len()
+            );
+            for message in &finished.engine_messages {
+                println_red!("{}", message.text);
             }
         }
 
         let mut value = CURRENT_TEST_INDEX.lock().unwrap();
         *value += 1;
-        self.cleanup();
+        drop(value);
 
-        if focus.is_some() {
+        if outcome == TestStatus::Failed && debug::stop_requested() {
+            println_red!("\nStopping, as asked while paused on the failure");
             self.quit();
-            return;
+        } else if outcome == TestStatus::Failed && config::with(|config| config.fail_fast) {
+            println_red!("\nStopping after the first failure (--fail-fast)");
+            self.quit();
+        }
+    }
+}
+
+// Used by CleanupStrategy::Incremental. Frees the last, deepest node under
+// `root` over and over until `budget` is used up (at least one node per
+// call), and `root` itself once it's empty. Returns the time it took.
+fn free_incrementally(root: &Gd<Node>, budget: Duration) -> Duration {
+    let started_at = Instant::now();
+
+    loop {
+        let mut node = root.clone();
+        while node.get_child_count() > 0 {
+            node = node.get_child(node.get_child_count() - 1).unwrap();
+        }
+
+        let is_root = node == *root;
+        node.free();
+
+        if is_root || started_at.elapsed() >= budget {
+            return started_at.elapsed();
         }
     }
 }
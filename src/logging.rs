@@ -0,0 +1,203 @@
+use crate::output;
+use std::fmt;
+use std::sync::Mutex;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    pub fn parse(name: &str) -> Option<Level> {
+        match name {
+            "trace" => Some(Level::Trace),
+            "debug" => Some(Level::Debug),
+            "info" => Some(Level::Info),
+            "warn" => Some(Level::Warn),
+            "error" => Some(Level::Error),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Trace => "trace",
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LogRecord {
+    pub level: Level,
+    pub message: String,
+    pub file: &'static str,
+    pub line: u32,
+    // Which run of the test (see wait!) logged it.
+    pub iteration: usize,
+}
+
+impl fmt::Display for LogRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{:<5}] {} ({}:{}, iteration {})",
+            self.level.as_str(),
+            self.message,
+            self.file,
+            self.line,
+            self.iteration
+        )
+    }
+}
+
+lazy_static::lazy_static! {
+    // What the current test logged. A Mutex rather than test state so that
+    // threads the test spawns can log too.
+    static ref RECORDS: Mutex<Vec<LogRecord>> = Mutex::new(Vec::new());
+}
+
+// Instead of println! debugging in specs:
+//
+//     spec_log!(debug, "enemy at {:?}", enemy.get_position());
+//     spec_log!(Level::Warn, "no spawn points, using the origin");
+//
+// Records at or above the config's log_level are kept with the test and
+// shown (and written to the results file) when it fails, or with every test
+// in verbose output. They're printed straight away when output isn't
+// captured, or outside of a test.
+#[macro_export]
+macro_rules! spec_log {
+    (trace, $($arg:tt)+) => {
+        $crate::spec_log!($crate::logging::Level::Trace, $($arg)+)
+    };
+    (debug, $($arg:tt)+) => {
+        $crate::spec_log!($crate::logging::Level::Debug, $($arg)+)
+    };
+    (info, $($arg:tt)+) => {
+        $crate::spec_log!($crate::logging::Level::Info, $($arg)+)
+    };
+    (warn, $($arg:tt)+) => {
+        $crate::spec_log!($crate::logging::Level::Warn, $($arg)+)
+    };
+    (error, $($arg:tt)+) => {
+        $crate::spec_log!($crate::logging::Level::Error, $($arg)+)
+    };
+    ($level:expr, $($arg:tt)+) => {
+        $crate::logging::log($level, format!($($arg)+), file!(), line!())
+    };
+}
+
+pub fn log(level: Level, message: String, file: &'static str, line: u32) {
+    let (minimum, captured) = crate::config::with(|config| (config.log_level, config.capture_output));
+    if level < minimum {
+        return;
+    }
+
+    let record = LogRecord {
+        level,
+        message,
+        file,
+        line,
+        iteration: *crate::CURRENT_TEST_ITERATION.lock().unwrap(),
+    };
+
+    if !crate::panics::in_test() {
+        print(&record);
+        return;
+    }
+
+    if !captured {
+        print(&record);
+    }
+
+    RECORDS.lock().unwrap().push(record);
+}
+
+fn print(record: &LogRecord) {
+    let color = match record.level {
+        Level::Trace | Level::Debug => output::BLUE,
+        Level::Info => "",
+        Level::Warn => output::YELLOW,
+        Level::Error => output::RED,
+    };
+
+    if color.is_empty() {
+        output::print_plain(&record.to_string(), true);
+    } else {
+        output::print_colored(color, &record.to_string(), true);
+    }
+}
+
+// Called by the runner when a test finishes.
+pub(crate) fn take() -> Vec<LogRecord> {
+    std::mem::take(&mut *RECORDS.lock().unwrap())
+}
+
+pub(crate) fn describe(records: &[LogRecord]) -> String {
+    records.iter().map(|record| record.to_string()).collect::<Vec<_>>().join("\n")
+}
+
+// Everything the current test logged so far: its spec_log! records, then
+// what it (or the engine) printed to stdout and stderr.
+fn logged_so_far() -> Vec<String> {
+    let mut lines: Vec<String> = RECORDS.lock().unwrap().iter().map(|record| record.to_string()).collect();
+    lines.extend(crate::capture::captured_stdout().lines().map(|line| line.to_string()));
+    lines.extend(crate::errors::captured_stderr().lines().map(|line| line.to_string()));
+    lines
+}
+
+// Used by assert_logged! and assert_not_logged!. Matches lines containing
+// `pattern`, ignoring color codes.
+#[track_caller]
+pub fn assert_logged(pattern: &str, expected: bool) {
+    let lines = logged_so_far();
+    let matching: Vec<&String> = lines.iter().filter(|line| output::strip_ansi(line).contains(pattern)).collect();
+
+    if matching.is_empty() == expected {
+        let captured = if crate::config::with(|config| config.capture_output) {
+            ""
+        } else {
+            " (stdout isn't captured, as capture_output is off)"
+        };
+
+        let dump = if expected {
+            lines.join("\n")
+        } else {
+            matching.iter().map(|line| line.as_str()).collect::<Vec<_>>().join("\n")
+        };
+
+        panic!(
+            "expected {:?} {}to be logged{}, {}:\n{}",
+            pattern,
+            if expected { "" } else { "not " },
+            captured,
+            if expected { "but the test's log is" } else { "but it was" },
+            if dump.is_empty() { "(nothing)" } else { dump.as_str() }
+        );
+    }
+}
+
+// assert_logged!("saved game to slot 2") checks what the test logged with
+// spec_log! and everything printed to stdout and stderr (so also print! and
+// push_error from game code) since the test started.
+#[macro_export]
+macro_rules! assert_logged {
+    ($pattern:expr) => {
+        $crate::logging::assert_logged(&$pattern, true)
+    };
+}
+
+#[macro_export]
+macro_rules! assert_not_logged {
+    ($pattern:expr) => {
+        $crate::logging::assert_logged(&$pattern, false)
+    };
+}
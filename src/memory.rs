@@ -0,0 +1,57 @@
+use godot::engine::performance::Monitor;
+use godot::engine::{Os, Performance};
+use std::ops::Sub;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub static_bytes: i64,
+    pub static_peak_bytes: i64,
+    pub message_buffer_max_bytes: i64,
+}
+
+impl MemoryUsage {
+    pub fn current() -> Self {
+        let os = Os::singleton();
+        let performance = Performance::singleton();
+
+        Self {
+            static_bytes: os.get_static_memory_usage() as i64,
+            static_peak_bytes: os.get_static_memory_peak_usage() as i64,
+            message_buffer_max_bytes: performance.get_monitor(Monitor::MEMORY_MESSAGE_BUFFER_MAX)
+                as i64,
+        }
+    }
+}
+
+impl Sub for MemoryUsage {
+    type Output = MemoryUsage;
+
+    fn sub(self, other: MemoryUsage) -> MemoryUsage {
+        MemoryUsage {
+            static_bytes: self.static_bytes - other.static_bytes,
+            static_peak_bytes: self.static_peak_bytes - other.static_peak_bytes,
+            message_buffer_max_bytes: self.message_buffer_max_bytes
+                - other.message_buffer_max_bytes,
+        }
+    }
+}
+
+pub fn format_bytes(bytes: i64) -> String {
+    let kib = bytes as f64 / 1024.0;
+
+    if kib.abs() >= 1024.0 {
+        format!("{:+.1} MiB", kib / 1024.0)
+    } else {
+        format!("{:+.1} KiB", kib)
+    }
+}
+
+pub(crate) fn describe(test: &str, delta: MemoryUsage) -> String {
+    format!(
+        "{}: static memory {}, peak {}, message buffer {}",
+        test,
+        format_bytes(delta.static_bytes),
+        format_bytes(delta.static_peak_bytes),
+        format_bytes(delta.message_buffer_max_bytes)
+    )
+}
@@ -0,0 +1,47 @@
+use crate::state::test_state;
+use crate::TestContext;
+use serde_json::Value;
+
+// Key/value pairs the current test recorded, in the order they were first
+// recorded.
+#[derive(Default)]
+struct Metadata(Vec<(String, Value)>);
+
+// Attaches `value` to the current test's result, replacing what was recorded
+// under `key` before. It's shown in verbose output (and with failures) and
+// written to the results file, so CI-only failures come with some context:
+//
+//     ctx.record("spawned_enemies", 14);
+//     ctx.record("level", "forest_2");
+pub fn record(key: &str, value: impl Into<Value>) {
+    let value = value.into();
+
+    test_state(|metadata: &mut Metadata| {
+        match metadata.0.iter_mut().find(|(existing, _)| existing == key) {
+            Some((_, existing)) => *existing = value,
+            None => metadata.0.push((key.to_string(), value)),
+        }
+    });
+}
+
+impl TestContext {
+    pub fn record(&self, key: &str, value: impl Into<Value>) {
+        record(key, value);
+    }
+}
+
+// Called by the runner when a test finishes, before its state is dropped.
+pub(crate) fn take() -> Vec<(String, Value)> {
+    test_state(|metadata: &mut Metadata| std::mem::take(&mut metadata.0))
+}
+
+pub(crate) fn describe(metadata: &[(String, Value)]) -> String {
+    metadata
+        .iter()
+        .map(|(key, value)| match value {
+            Value::String(text) => format!("  {}: {}", key, text),
+            value => format!("  {}: {}", key, value),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,249 @@
+use crate::replication::{ReplicationLog, RpcSpyApi};
+use crate::TestContext;
+use godot::engine::global::Error;
+use godot::engine::multiplayer_peer::ConnectionStatus;
+use godot::engine::{ENetMultiplayerPeer, Engine, OfflineMultiplayerPeer, SceneMultiplayer};
+use godot::prelude::*;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+thread_local! {
+    // The current test's harness, so `ctx.multiplayer(..)` keeps returning
+    // the same one across `wait!` replays.
+    static HARNESS: RefCell<Option<MultiplayerHarness>> = RefCell::new(None);
+}
+
+// Next port to try a server on. Every harness gets a new one so a socket
+// still closing from the previous test can't get in the way.
+static NEXT_PORT: AtomicU32 = AtomicU32::new(24_500);
+
+// Names of the branches, unique for the run so a path the SceneTree still
+// has a MultiplayerAPI for is never reused.
+static NEXT_HARNESS: AtomicU32 = AtomicU32::new(0);
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// One side of the connection. Nodes added under `root` use `api` (as their
+// `multiplayer`), and their RPC paths are relative to `root`, so the same
+// scene added under the server and under a client talks to itself.
+#[derive(Clone)]
+pub struct Peer {
+    pub root: Gd<Node>,
+    pub api: Gd<SceneMultiplayer>,
+    pub peer: Gd<ENetMultiplayerPeer>,
+    // What the tree actually uses as the branch's API, see replication.rs.
+    pub(crate) spy: Gd<RpcSpyApi>,
+    pub(crate) replication: ReplicationLog,
+}
+
+impl Peer {
+    fn new(parent: &Gd<Node>, name: String) -> Self {
+        let mut root = Node::new_alloc();
+        root.set_name(name.into());
+        parent.clone().add_child(root.clone());
+
+        let api = SceneMultiplayer::new_gd();
+        let spy = RpcSpyApi::wrap(api.clone(), &root);
+        tree()
+            .set_multiplayer_ex(spy.clone().upcast())
+            .root_path(root.get_path())
+            .done();
+
+        Self {
+            root,
+            api,
+            peer: ENetMultiplayerPeer::new_gd(),
+            spy,
+            replication: ReplicationLog::default(),
+        }
+    }
+
+    // The peer id, 1 for the server.
+    pub fn id(&self) -> i32 {
+        self.api.get_unique_id()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.peer.get_connection_status() == ConnectionStatus::CONNECTED
+    }
+
+    // Adds `node` under this peer's root, e.g. a player scene instanced for
+    // each side.
+    pub fn add<T: GodotClass + Inherits<Node>>(&self, node: Gd<T>) -> Gd<T> {
+        self.root.clone().add_child(node.clone().upcast());
+        node
+    }
+
+    fn poll(&mut self) {
+        if self.root.is_instance_valid() {
+            self.api.poll();
+        }
+    }
+
+    fn close(&mut self) {
+        self.peer.close();
+
+        // Leaves the branch's path with a harmless offline API rather than a
+        // closed ENet one.
+        let mut offline = SceneMultiplayer::new_gd();
+        offline.set_multiplayer_peer(OfflineMultiplayerPeer::new_gd().upcast());
+        if self.root.is_instance_valid() {
+            tree()
+                .set_multiplayer_ex(offline.upcast())
+                .root_path(self.root.get_path())
+                .done();
+        }
+    }
+}
+
+// A server and clients connected over ENet on localhost, all in the test's
+// process and tree:
+//
+//     fn hits_are_replicated(ctx: &TestContext) {
+//         let mut net = ctx.multiplayer(1);
+//         let scene = load::<PackedScene>("res://player.tscn");
+//         let mut server_player = net.server.add(instantiate_as::<Player>(&scene));
+//         let client_player = net.clients[0].add(instantiate_as::<Player>(&scene));
+//
+//         server_player.bind_mut().take_damage(10);
+//         assert!(net.pump_until(|| client_player.bind().health == 90));
+//     }
+#[derive(Clone)]
+pub struct MultiplayerHarness {
+    pub server: Peer,
+    pub clients: Vec<Peer>,
+}
+
+impl MultiplayerHarness {
+    // Listens, connects `clients` clients and pumps until they're all in.
+    #[track_caller]
+    pub fn start(root: &Gd<Node>, clients: usize) -> Self {
+        let index = NEXT_HARNESS.fetch_add(1, Ordering::SeqCst);
+
+        let mut server = Peer::new(root, format!("Server{}", index));
+        let port = listen(&mut server.peer, clients);
+        server.api.set_multiplayer_peer(server.peer.clone().upcast());
+
+        let clients = (0..clients)
+            .map(|client| {
+                let mut peer = Peer::new(root, format!("Client{}_{}", index, client + 1));
+                let error = peer.peer.create_client("127.0.0.1".into(), port as i32);
+                if error != Error::OK {
+                    panic!("multiplayer: client {} could not connect to port {}: {:?}", client + 1, port, error);
+                }
+                peer.api.set_multiplayer_peer(peer.peer.clone().upcast());
+                peer
+            })
+            .collect();
+
+        let mut harness = Self { server, clients };
+
+        let count = harness.clients.len();
+        let connected = harness.pump_until_timeout(CONNECT_TIMEOUT, |harness| {
+            harness.clients.iter().all(Peer::is_connected)
+                && harness.server.api.get_peers().len() == count
+        });
+
+        if !connected {
+            harness.close();
+            panic!(
+                "multiplayer: {} of {} clients connected within {:?}",
+                harness.clients.iter().filter(|client| client.is_connected()).count(),
+                count,
+                CONNECT_TIMEOUT
+            );
+        }
+
+        harness
+    }
+
+    // Polls every peer once, delivering whatever RPCs and replication
+    // messages have arrived.
+    pub fn poll(&mut self) {
+        self.server.poll();
+        for client in &mut self.clients {
+            client.poll();
+        }
+    }
+
+    // Polls (without waiting for a frame) until `condition` holds, for up to
+    // five seconds. False if it never did.
+    pub fn pump_until(&mut self, mut condition: impl FnMut() -> bool) -> bool {
+        self.pump_until_timeout(CONNECT_TIMEOUT, |_| condition())
+    }
+
+    pub fn pump_until_timeout(&mut self, timeout: Duration, mut condition: impl FnMut(&Self) -> bool) -> bool {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            self.poll();
+
+            if condition(self) {
+                return true;
+            }
+
+            if Instant::now() >= deadline {
+                return false;
+            }
+
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    // Polls for a while, for when there's nothing to wait for except that
+    // nothing else arrives.
+    pub fn pump_for(&mut self, duration: Duration) {
+        self.pump_until_timeout(duration, |_| false);
+    }
+
+    pub fn close(&mut self) {
+        for client in &mut self.clients {
+            client.close();
+        }
+        self.server.close();
+    }
+}
+
+fn tree() -> Gd<SceneTree> {
+    Engine::singleton()
+        .get_main_loop()
+        .and_then(|main_loop| main_loop.try_cast::<SceneTree>().ok())
+        .expect("multiplayer: no SceneTree is running")
+}
+
+#[track_caller]
+fn listen(peer: &mut Gd<ENetMultiplayerPeer>, clients: usize) -> u32 {
+    for _ in 0..20 {
+        let port = NEXT_PORT.fetch_add(1, Ordering::SeqCst);
+        let error = peer.create_server_ex(port as i32).max_clients(clients.max(1) as i32).done();
+
+        if error == Error::OK {
+            return port;
+        }
+    }
+
+    panic!("multiplayer: could not listen on any port from {}", NEXT_PORT.load(Ordering::SeqCst) - 20);
+}
+
+impl TestContext {
+    // The test's multiplayer harness with `clients` clients, started (and
+    // connected) the first time it's asked for.
+    #[track_caller]
+    pub fn multiplayer(&self, clients: usize) -> MultiplayerHarness {
+        if let Some(harness) = HARNESS.with(|harness| harness.borrow().clone()) {
+            return harness;
+        }
+
+        let harness = MultiplayerHarness::start(self.root(), clients);
+        HARNESS.with(|current| *current.borrow_mut() = Some(harness.clone()));
+        harness
+    }
+}
+
+// Called by the runner once a test has finished, before its root is freed.
+pub(crate) fn reset() {
+    if let Some(mut harness) = HARNESS.with(|harness| harness.borrow_mut().take()) {
+        harness.close();
+    }
+}
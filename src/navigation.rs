@@ -0,0 +1,239 @@
+use crate::state::test_state;
+use godot::engine::{NavigationRegion2D, NavigationRegion3D, NavigationServer2D, NavigationServer3D};
+use godot::prelude::*;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::time::Instant;
+
+// What the helpers below need from NavigationRegion2D and
+// NavigationRegion3D, so they work with either.
+pub trait Region: GodotClass + Inherits<Node> {
+    type Point: Copy + Debug;
+
+    fn map(region: &Gd<Self>) -> Rid;
+    fn rid(region: &Gd<Self>) -> Rid;
+    fn is_baking(region: &Gd<Self>) -> bool;
+    fn map_regions(map: Rid) -> Vec<Rid>;
+    fn cell_size(map: Rid) -> f32;
+    fn force_update(map: Rid);
+    fn path(map: Rid, from: Self::Point, to: Self::Point) -> Vec<Self::Point>;
+    fn distance(a: Self::Point, b: Self::Point) -> f32;
+}
+
+impl Region for NavigationRegion2D {
+    type Point = Vector2;
+
+    fn map(region: &Gd<Self>) -> Rid {
+        region.get_navigation_map()
+    }
+
+    fn rid(region: &Gd<Self>) -> Rid {
+        region.get_region_rid()
+    }
+
+    fn is_baking(region: &Gd<Self>) -> bool {
+        region.is_baking()
+    }
+
+    fn map_regions(map: Rid) -> Vec<Rid> {
+        NavigationServer2D::singleton().map_get_regions(map).iter_shared().collect()
+    }
+
+    fn cell_size(map: Rid) -> f32 {
+        NavigationServer2D::singleton().map_get_cell_size(map)
+    }
+
+    fn force_update(map: Rid) {
+        NavigationServer2D::singleton().map_force_update(map);
+    }
+
+    fn path(map: Rid, from: Vector2, to: Vector2) -> Vec<Vector2> {
+        NavigationServer2D::singleton().map_get_path(map, from, to, true).to_vec()
+    }
+
+    fn distance(a: Vector2, b: Vector2) -> f32 {
+        a.distance_to(b)
+    }
+}
+
+impl Region for NavigationRegion3D {
+    type Point = Vector3;
+
+    fn map(region: &Gd<Self>) -> Rid {
+        region.get_navigation_map()
+    }
+
+    fn rid(region: &Gd<Self>) -> Rid {
+        region.get_region_rid()
+    }
+
+    fn is_baking(region: &Gd<Self>) -> bool {
+        region.is_baking()
+    }
+
+    fn map_regions(map: Rid) -> Vec<Rid> {
+        NavigationServer3D::singleton().map_get_regions(map).iter_shared().collect()
+    }
+
+    fn cell_size(map: Rid) -> f32 {
+        NavigationServer3D::singleton().map_get_cell_size(map)
+    }
+
+    fn force_update(map: Rid) {
+        NavigationServer3D::singleton().map_force_update(map);
+    }
+
+    fn path(map: Rid, from: Vector3, to: Vector3) -> Vec<Vector3> {
+        NavigationServer3D::singleton().map_get_path(map, from, to, true).to_vec()
+    }
+
+    fn distance(a: Vector3, b: Vector3) -> f32 {
+        a.distance_to(b)
+    }
+}
+
+// When each wait_for_navigation! of the current test (by call site) started
+// waiting.
+#[derive(Default)]
+struct Waits(HashMap<&'static str, Instant>);
+
+// Called by wait_for_navigation!. Whether the region has finished baking
+// and its map has picked it up. The NavigationServer only syncs maps once
+// per physics frame, so until then paths come back empty or stale.
+#[track_caller]
+pub fn poll_navigation<R: Region>(region: &Gd<R>, timeout_ms: u64, key: &'static str) -> bool {
+    let started_at = test_state(|waits: &mut Waits| *waits.0.entry(key).or_insert_with(Instant::now));
+
+    let map = R::map(region);
+    let baking = R::is_baking(region);
+    let synced = map.is_valid() && R::map_regions(map).contains(&R::rid(region));
+
+    if !baking && synced {
+        test_state(|waits: &mut Waits| waits.0.remove(key));
+        return true;
+    }
+
+    if started_at.elapsed().as_millis() as u64 >= timeout_ms {
+        test_state(|waits: &mut Waits| waits.0.remove(key));
+
+        let node = region.clone().upcast::<Node>();
+        panic!(
+            "navigation for {} wasn't ready within {}ms ({})",
+            node.get_path(),
+            timeout_ms,
+            if !map.is_valid() {
+                "the region isn't on a navigation map, is it in the tree?"
+            } else if baking {
+                "still baking"
+            } else {
+                "its map doesn't have the region yet"
+            }
+        );
+    }
+
+    false
+}
+
+fn describe_path<P: Debug>(path: &[P]) -> String {
+    if path.is_empty() {
+        return "(empty)".to_string();
+    }
+
+    path.iter().map(|point| format!("{:?}", point)).collect::<Vec<_>>().join(" -> ")
+}
+
+fn path_length<R: Region>(path: &[R::Point]) -> f32 {
+    path.windows(2).map(|pair| R::distance(pair[0], pair[1])).sum()
+}
+
+// The path between two points on the region's map, after applying any
+// pending changes to it. It "reaches" when it ends within two cells of `to`
+// (a path to an unreachable point ends at the closest reachable one).
+fn computed_path<R: Region>(region: &Gd<R>, from: R::Point, to: R::Point) -> (Vec<R::Point>, bool) {
+    let map = R::map(region);
+    R::force_update(map);
+
+    let path = R::path(map, from, to);
+    let reaches = path.last().map_or(false, |last| R::distance(*last, to) <= R::cell_size(map) * 2.0);
+
+    (path, reaches)
+}
+
+#[track_caller]
+pub fn assert_path<R: Region>(region: &Gd<R>, from: R::Point, to: R::Point, expected: bool) {
+    let (path, reaches) = computed_path(region, from, to);
+
+    if reaches != expected {
+        panic!(
+            "expected {}a navigation path from {:?} to {:?} on {}. Computed path: {}",
+            if expected { "" } else { "no " },
+            from,
+            to,
+            region.clone().upcast::<Node>().get_path(),
+            describe_path(&path)
+        );
+    }
+}
+
+#[track_caller]
+pub fn assert_path_length<R: Region>(region: &Gd<R>, from: R::Point, to: R::Point, length: f32, epsilon: f32) {
+    let (path, reaches) = computed_path(region, from, to);
+    let actual = path_length::<R>(&path);
+
+    if !reaches || (actual - length).abs() > epsilon {
+        panic!(
+            "expected a navigation path from {:?} to {:?} of length {} (epsilon {}), but {}. Computed path: {}",
+            from,
+            to,
+            length,
+            epsilon,
+            if reaches { format!("it's {}", actual) } else { "there's none".to_string() },
+            describe_path(&path)
+        );
+    }
+}
+
+// Waits (across frames) until a NavigationRegion2D/3D is baked and synced
+// to its map, so paths on it can be asserted. Fails once `timeout_ms` have
+// passed:
+//
+//     wait_for_navigation!(region, 1000);
+//     assert_path_exists!(region, Vector3::ZERO, Vector3::new(10.0, 0.0, 4.0));
+#[macro_export]
+macro_rules! wait_for_navigation {
+    ($region:expr, $timeout_ms:expr) => {
+        if !$crate::navigation::poll_navigation(
+            &$region,
+            $timeout_ms,
+            concat!(file!(), ":", line!(), ":", column!()),
+        ) {
+            $crate::wait!(0.0);
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! assert_path_exists {
+    ($region:expr, $from:expr, $to:expr) => {
+        $crate::navigation::assert_path(&$region, $from, $to, true)
+    };
+}
+
+#[macro_export]
+macro_rules! assert_no_path {
+    ($region:expr, $from:expr, $to:expr) => {
+        $crate::navigation::assert_path(&$region, $from, $to, false)
+    };
+}
+
+// assert_path_length!(region, from, to, 12.0) or with an epsilon,
+// assert_path_length!(region, from, to, 12.0, 0.5)
+#[macro_export]
+macro_rules! assert_path_length {
+    ($region:expr, $from:expr, $to:expr, $length:expr) => {
+        $crate::navigation::assert_path_length(&$region, $from, $to, $length, 0.01)
+    };
+    ($region:expr, $from:expr, $to:expr, $length:expr, $epsilon:expr) => {
+        $crate::navigation::assert_path_length(&$region, $from, $to, $length, $epsilon)
+    };
+}
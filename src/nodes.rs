@@ -0,0 +1,244 @@
+use crate::snapshot::tree_to_string;
+use godot::prelude::*;
+
+#[track_caller]
+pub fn assert_node_exists(root: &Gd<Node>, path: &str) -> Gd<Node> {
+    match root.get_node_or_null(path.into()) {
+        Some(node) => node,
+        None => panic!(
+            "expected a node at {:?} under {}, but there is none. Actual tree:\n{}",
+            path,
+            root.get_path(),
+            tree_to_string(root, &[])
+        ),
+    }
+}
+
+#[track_caller]
+pub fn assert_node_not_exists(root: &Gd<Node>, path: &str) {
+    if let Some(node) = root.get_node_or_null(path.into()) {
+        panic!(
+            "expected no node at {:?} under {}, but found a {}. Actual tree:\n{}",
+            path,
+            root.get_path(),
+            node.get_class(),
+            tree_to_string(root, &[])
+        );
+    }
+}
+
+#[track_caller]
+pub fn assert_child_count(node: &Gd<Node>, expected: usize, at_least: bool) {
+    let count = node.get_child_count() as usize;
+    let passed = if at_least { count >= expected } else { count == expected };
+
+    if !passed {
+        panic!(
+            "expected {} to have {}{} children, but it has {}. Actual tree:\n{}",
+            node.get_path(),
+            if at_least { "at least " } else { "" },
+            expected,
+            count,
+            tree_to_string(node, &[])
+        );
+    }
+}
+
+#[macro_export]
+macro_rules! assert_node_exists {
+    ($root:expr, $path:expr) => {
+        $crate::nodes::assert_node_exists(&$root.clone().upcast(), $path)
+    };
+}
+
+#[macro_export]
+macro_rules! assert_node_not_exists {
+    ($root:expr, $path:expr) => {
+        $crate::nodes::assert_node_not_exists(&$root.clone().upcast(), $path)
+    };
+}
+
+// assert_child_count!(node, 3) or assert_child_count!(node, >= 3)
+#[macro_export]
+macro_rules! assert_child_count {
+    ($node:expr, >= $count:expr) => {
+        $crate::nodes::assert_child_count(&$node.clone().upcast(), $count, true)
+    };
+    ($node:expr, $count:expr) => {
+        $crate::nodes::assert_child_count(&$node.clone().upcast(), $count, false)
+    };
+}
+
+fn list<T: std::fmt::Display>(items: impl Iterator<Item = T>) -> String {
+    let items: Vec<String> = items.map(|item| item.to_string()).collect();
+
+    if items.is_empty() {
+        "(none)".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+#[track_caller]
+pub fn assert_in_group(node: &Gd<Node>, group: &str, expected: bool) {
+    if node.is_in_group(group.into()) != expected {
+        panic!(
+            "expected {} {}to be in group {:?}. Its groups: {}",
+            node.get_path(),
+            if expected { "" } else { "not " },
+            group,
+            list(node.get_groups().iter_shared())
+        );
+    }
+}
+
+#[track_caller]
+pub fn assert_has_meta(node: &Gd<Node>, key: &str, expected: Option<Variant>) {
+    let keys = || list(node.get_meta_list().iter_shared());
+
+    if !node.has_meta(key.into()) {
+        panic!(
+            "expected {} to have meta {:?}. Its meta keys: {}",
+            node.get_path(),
+            key,
+            keys()
+        );
+    }
+
+    if let Some(expected) = expected {
+        let actual = node.get_meta(key.into());
+        if actual != expected {
+            panic!(
+                "expected meta {:?} of {} to be {}, but it is {}. Its meta keys: {}",
+                key,
+                node.get_path(),
+                expected,
+                actual,
+                keys()
+            );
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! assert_in_group {
+    ($node:expr, $group:expr) => {
+        $crate::nodes::assert_in_group(&$node.clone().upcast(), $group, true)
+    };
+}
+
+#[macro_export]
+macro_rules! assert_not_in_group {
+    ($node:expr, $group:expr) => {
+        $crate::nodes::assert_in_group(&$node.clone().upcast(), $group, false)
+    };
+}
+
+#[macro_export]
+macro_rules! assert_has_meta {
+    ($node:expr, $key:expr) => {
+        $crate::nodes::assert_has_meta(&$node.clone().upcast(), $key, None)
+    };
+    ($node:expr, $key:expr, $value:expr) => {
+        $crate::nodes::assert_has_meta(
+            &$node.clone().upcast(),
+            $key,
+            Some(godot::prelude::ToGodot::to_variant(&$value)),
+        )
+    };
+}
+
+fn descendants(node: &Gd<Node>, found: &mut Vec<Gd<Node>>) {
+    for child in node.get_children().iter_shared() {
+        found.push(child.clone());
+        descendants(&child, found);
+    }
+}
+
+// `path (Class)` of every node under `root`, relative to it.
+fn describe_nodes(root: &Gd<Node>, nodes: &[Gd<Node>]) -> String {
+    if nodes.is_empty() {
+        return "  (none)".to_string();
+    }
+
+    nodes
+        .iter()
+        .map(|node| format!("  {} ({})", root.get_path_to(node.clone()), node.get_class()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Looks `name_or_path` up under `root` as a relative path when it contains
+// a `/`, otherwise as the name of any descendant, then casts it to `T`.
+#[track_caller]
+pub fn find<T>(root: &Gd<Node>, name_or_path: &str) -> Gd<T>
+where
+    T: GodotClass + Inherits<Node>,
+{
+    let mut all = Vec::new();
+    descendants(root, &mut all);
+
+    let node = if name_or_path.contains('/') {
+        root.get_node_or_null(name_or_path.into())
+    } else {
+        all.iter().find(|node| node.get_name().to_string() == name_or_path).cloned()
+    };
+
+    let Some(node) = node else {
+        panic!(
+            "find::<{}>: no node {:?} under {}. Nodes there:\n{}",
+            T::class_name(),
+            name_or_path,
+            root.get_path(),
+            describe_nodes(root, &all)
+        );
+    };
+
+    node.try_cast::<T>().unwrap_or_else(|node| {
+        let of_type: Vec<Gd<Node>> = all
+            .iter()
+            .filter(|node| node.is_class(T::class_name().to_string().into()))
+            .cloned()
+            .collect();
+
+        panic!(
+            "find::<{}>: {} is a {}, not a {} ({}). {} nodes there:\n{}",
+            T::class_name(),
+            root.get_path_to(node.clone()),
+            node.get_class(),
+            T::class_name(),
+            std::any::type_name::<T>(),
+            T::class_name(),
+            describe_nodes(root, &of_type)
+        )
+    })
+}
+
+// Every node under `root` that is a `T`, in tree order.
+pub fn find_all<T>(root: &Gd<Node>) -> Vec<Gd<T>>
+where
+    T: GodotClass + Inherits<Node>,
+{
+    let mut all = Vec::new();
+    descendants(root, &mut all);
+
+    all.into_iter().filter_map(|node| node.try_cast::<T>().ok()).collect()
+}
+
+impl crate::TestContext {
+    // E.g. `ctx.find::<Player>("Player")` or `ctx.find::<Label>("Hud/Score")`.
+    #[track_caller]
+    pub fn find<T>(&self, name_or_path: &str) -> Gd<T>
+    where
+        T: GodotClass + Inherits<Node>,
+    {
+        find(self.root(), name_or_path)
+    }
+
+    pub fn find_all<T>(&self) -> Vec<Gd<T>>
+    where
+        T: GodotClass + Inherits<Node>,
+    {
+        find_all(self.root())
+    }
+}
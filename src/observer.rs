@@ -0,0 +1,65 @@
+use crate::report::TestResult;
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+    static ref OBSERVERS: Mutex<Vec<Box<dyn LifecycleObserver>>> = Mutex::new(Vec::new());
+}
+
+// Gets told about the run as it happens, independently of what the runner
+// prints. E.g. to push timings to a dashboard or to reset an external
+// service before every test:
+//
+//     struct ResetBackend;
+//
+//     impl LifecycleObserver for ResetBackend {
+//         fn on_test_start(&mut self, _name: &str) {
+//             backend::truncate_tables();
+//         }
+//     }
+//
+//     add_observer(ResetBackend);
+pub trait LifecycleObserver: Send {
+    // With the names of the tests that will run, after filtering.
+    fn on_suite_start(&mut self, _tests: &[String]) {}
+
+    // Before the test's first frame.
+    fn on_test_start(&mut self, _name: &str) {}
+
+    fn on_test_finished(&mut self, _result: &TestResult) {}
+
+    fn on_suite_end(&mut self, _results: &[TestResult]) {}
+}
+
+pub fn add_observer(observer: impl LifecycleObserver + 'static) {
+    OBSERVERS.lock().unwrap().push(Box::new(observer));
+}
+
+// Taken out while they're called, so an observer can add another one.
+fn each(f: impl Fn(&mut dyn LifecycleObserver)) {
+    let mut observers = std::mem::take(&mut *OBSERVERS.lock().unwrap());
+
+    for observer in observers.iter_mut() {
+        f(observer.as_mut());
+    }
+
+    let mut current = OBSERVERS.lock().unwrap();
+    let added = std::mem::take(&mut *current);
+    *current = observers;
+    current.extend(added);
+}
+
+pub(crate) fn suite_started(tests: &[String]) {
+    each(|observer| observer.on_suite_start(tests));
+}
+
+pub(crate) fn test_started(name: &str) {
+    each(|observer| observer.on_test_start(name));
+}
+
+pub(crate) fn test_finished(result: &TestResult) {
+    each(|observer| observer.on_test_finished(result));
+}
+
+pub(crate) fn suite_ended(results: &[TestResult]) {
+    each(|observer| observer.on_suite_end(results));
+}
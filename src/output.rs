@@ -0,0 +1,179 @@
+use crate::screenshot::globalize_path;
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+
+pub const RED: &str = "\x1B[31m";
+pub const GREEN: &str = "\x1B[32m";
+pub const YELLOW: &str = "\x1B[33m";
+pub const BLUE: &str = "\x1B[34m";
+pub const RESET: &str = "\x1B[0m";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+    // Colored unless NO_COLOR is set, stdout isn't a terminal or the console
+    // can't display ANSI colors.
+    Auto,
+    Always,
+    Never,
+}
+
+lazy_static::lazy_static! {
+    static ref LOG: Mutex<Option<std::fs::File>> = Mutex::new(None);
+    static ref LOG_PAUSED: Mutex<bool> = Mutex::new(false);
+    // Set in quiet mode until the summary; the log file still gets everything.
+    static ref MUTED: Mutex<bool> = Mutex::new(false);
+    // Checked once, as stdout is temporarily redirected while output is
+    // being captured.
+    static ref STDOUT_IS_TERMINAL: bool = io::stdout().is_terminal();
+    // Stock cmd/PowerShell consoles only understand ANSI escapes once
+    // virtual terminal processing is switched on.
+    static ref ANSI_SUPPORTED: bool = enable_virtual_terminal();
+}
+
+#[cfg(windows)]
+fn enable_virtual_terminal() -> bool {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+        STD_OUTPUT_HANDLE,
+    };
+
+    unsafe {
+        let handle = GetStdHandle(STD_OUTPUT_HANDLE);
+        let mut mode = 0;
+
+        if GetConsoleMode(handle, &mut mode) == 0 {
+            return false;
+        }
+
+        if mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0 {
+            return true;
+        }
+
+        SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+    }
+}
+
+#[cfg(not(windows))]
+fn enable_virtual_terminal() -> bool {
+    true
+}
+
+pub fn colors_enabled() -> bool {
+    match crate::config::with(|config| config.color) {
+        ColorMode::Always => {
+            let _ = *ANSI_SUPPORTED;
+            true
+        }
+        ColorMode::Never => false,
+        ColorMode::Auto => {
+            let no_color = std::env::var_os("NO_COLOR").map_or(false, |value| !value.is_empty());
+            !no_color && *STDOUT_IS_TERMINAL && *ANSI_SUPPORTED
+        }
+    }
+}
+
+// Wraps `text` in the given color code, or returns it as is when colors are off.
+pub fn colorize(color: &str, text: &str) -> String {
+    if colors_enabled() {
+        format!("{}{}{}", color, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn print_colored(color: &str, text: &str, newline: bool) {
+    tee(text, newline);
+    if MUTED.lock().unwrap().clone() {
+        return;
+    }
+    crate::progress::clear();
+
+    let text = colorize(color, text);
+
+    if newline {
+        println!("{}", text);
+    } else {
+        print!("{}", text);
+        let _ = io::stdout().flush();
+    }
+}
+
+pub fn print_plain(text: &str, newline: bool) {
+    tee(text, newline);
+    if MUTED.lock().unwrap().clone() {
+        return;
+    }
+    crate::progress::clear();
+
+    if newline {
+        println!("{}", text);
+    } else {
+        print!("{}", text);
+        let _ = io::stdout().flush();
+    }
+}
+
+// Removes ANSI escape sequences, e.g. from colored diffs embedded in
+// failure messages.
+pub fn strip_ansi(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(character) = chars.next() {
+        if character == '\x1B' {
+            for next in chars.by_ref() {
+                if next.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            output.push(character);
+        }
+    }
+
+    output
+}
+
+pub(crate) fn set_muted(muted: bool) {
+    *MUTED.lock().unwrap() = muted;
+}
+
+// Prints even in quiet mode, for output other tools parse (annotations).
+pub(crate) fn unmuted(f: impl FnOnce()) {
+    let muted = std::mem::replace(&mut *MUTED.lock().unwrap(), false);
+    f();
+    *MUTED.lock().unwrap() = muted;
+}
+
+// While a test's stdout is being captured, whatever it prints only reaches
+// the log if it's replayed afterwards (i.e. when the test fails).
+pub(crate) fn pause_log(paused: bool) {
+    *LOG_PAUSED.lock().unwrap() = paused;
+}
+
+fn tee(text: &str, newline: bool) {
+    if LOG_PAUSED.lock().unwrap().clone() {
+        return;
+    }
+
+    let Some(path) = crate::config::with(|config| config.log_file.clone()) else {
+        return;
+    };
+
+    let mut log = LOG.lock().unwrap();
+
+    if log.is_none() {
+        let path = globalize_path(&path);
+        if let Some(parent) = std::path::Path::new(&path).parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        *log = std::fs::File::create(&path).ok();
+    }
+
+    if let Some(file) = log.as_mut() {
+        let _ = file.write_all(strip_ansi(text).as_bytes());
+        if newline {
+            let _ = file.write_all(b"\n");
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::any::Any;
+use std::panic;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+
+type PanicHook = dyn Fn(&panic::PanicInfo) + Send + Sync + 'static;
+
+lazy_static::lazy_static! {
+    // While set, the runner's panic hook stays quiet. Used for panics a test
+    // expects to happen.
+    pub static ref SUPPRESS_PANIC_OUTPUT: Mutex<bool> = Mutex::new(false);
+    // Where the most recent panic happened, recorded by the runner's hook.
+    pub(crate) static ref LAST_PANIC_LOCATION: Mutex<Option<SourceLocation>> = Mutex::new(None);
+    // The hook that was installed before the runner's, which still handles
+    // panics that happen while no test is running.
+    static ref PREVIOUS_HOOK: Mutex<Option<Arc<PanicHook>>> = Mutex::new(None);
+    // The thread running the current test. Panics on other threads (e.g.
+    // ctx.spawn's) aren't the test's failure, they're reported through
+    // threads.rs, so they go to the previous hook.
+    static ref IN_TEST: Mutex<Option<ThreadId>> = Mutex::new(None);
+}
+
+// Installs `hook` for panics during tests, keeping whatever hook the host
+// project (or other tooling) installed for all other panics.
+pub(crate) fn install_hook(hook: impl Fn(&panic::PanicInfo) + Send + Sync + 'static) {
+    let mut previous_hook = PREVIOUS_HOOK.lock().unwrap();
+    if previous_hook.is_some() {
+        return;
+    }
+
+    let previous: Arc<PanicHook> = Arc::from(panic::take_hook());
+    *previous_hook = Some(previous.clone());
+
+    panic::set_hook(Box::new(move |info| {
+        if IN_TEST.lock().map_or(false, |in_test| *in_test == Some(thread::current().id())) {
+            hook(info);
+        } else {
+            previous(info);
+        }
+    }));
+}
+
+// Puts the previous hook back, once the suite is done.
+pub(crate) fn restore_hook() {
+    if let Some(previous) = PREVIOUS_HOOK.lock().unwrap().take() {
+        panic::set_hook(Box::new(move |info| previous(info)));
+    }
+}
+
+// Set by the runner from a test's first frame until its cleanup.
+pub(crate) fn set_in_test(in_test: bool) {
+    *IN_TEST.lock().unwrap() = in_test.then(|| thread::current().id());
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: u32,
+}
+
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+pub(crate) fn record_location(info: &panic::PanicInfo) {
+    *LAST_PANIC_LOCATION.lock().unwrap() = info.location().map(|location| SourceLocation {
+        file: location.file().to_string(),
+        line: location.line(),
+    });
+}
+
+pub(crate) fn take_last_location() -> Option<SourceLocation> {
+    LAST_PANIC_LOCATION.lock().unwrap().take()
+}
+
+pub fn panic_message(payload: &Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "<non-string panic payload>".to_string()
+    }
+}
+
+// Runs `f`, returning the panic message if it panicked.
+pub fn catch_expected_panic<F: FnOnce()>(f: F) -> Option<String> {
+    let previous = SUPPRESS_PANIC_OUTPUT.lock().unwrap().clone();
+    *SUPPRESS_PANIC_OUTPUT.lock().unwrap() = true;
+
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(f));
+
+    *SUPPRESS_PANIC_OUTPUT.lock().unwrap() = previous;
+
+    result.err().map(|payload| panic_message(&payload))
+}
+
+#[macro_export]
+macro_rules! assert_panics {
+    ($expression:expr $(,)?) => {
+        if $crate::panics::catch_expected_panic(|| {
+            let _ = $expression;
+        })
+        .is_none()
+        {
+            panic!("expected `{}` to panic, but it didn't", stringify!($expression));
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! assert_panics_with {
+    ($expression:expr, $substring:expr $(,)?) => {
+        match $crate::panics::catch_expected_panic(|| {
+            let _ = $expression;
+        }) {
+            None => panic!(
+                "expected `{}` to panic with {:?}, but it didn't panic",
+                stringify!($expression),
+                $substring
+            ),
+            Some(message) => {
+                if !message.contains($substring) {
+                    panic!(
+                        "expected `{}` to panic with {:?}, but it panicked with {:?}",
+                        stringify!($expression),
+                        $substring,
+                        message
+                    );
+                }
+            }
+        }
+    };
+}
+
+pub(crate) fn in_test() -> bool {
+    IN_TEST.lock().unwrap().is_some()
+}
@@ -0,0 +1,95 @@
+use crate::TestContext;
+use godot::engine::Engine;
+use godot::prelude::*;
+
+fn tree() -> Gd<SceneTree> {
+    Engine::singleton()
+        .get_main_loop()
+        .and_then(|main_loop| main_loop.try_cast::<SceneTree>().ok())
+        .expect("no SceneTree is running")
+}
+
+// Pauses (or unpauses) the SceneTree. The runner keeps running either way,
+// so the test can wait! for a pause menu to react, and the tree is unpaused
+// again after the test, with the rest of globals::restore.
+pub fn set_tree_paused(paused: bool) {
+    tree().set_pause(paused);
+}
+
+pub fn is_tree_paused() -> bool {
+    tree().is_paused()
+}
+
+impl TestContext {
+    pub fn pause_tree(&self) {
+        set_tree_paused(true);
+    }
+
+    pub fn unpause_tree(&self) {
+        set_tree_paused(false);
+    }
+
+    pub fn is_tree_paused(&self) -> bool {
+        is_tree_paused()
+    }
+}
+
+// Whether `node` processes right now, given the tree's pause state and its
+// (inherited) process_mode, e.g. that the pause menu works while the game is
+// paused and the player doesn't.
+#[track_caller]
+pub fn assert_processing(node: &Gd<Node>, expected: bool) {
+    if node.can_process() != expected {
+        panic!(
+            "{}",
+            processing_message(
+                &node.get_path().to_string(),
+                expected,
+                is_tree_paused(),
+                &format!("{:?}", node.get_process_mode())
+            )
+        );
+    }
+}
+
+fn processing_message(path: &str, expected: bool, tree_paused: bool, process_mode: &str) -> String {
+    format!(
+        "expected {} {}to be processing, but it {} (tree paused: {}, process_mode: {})",
+        path,
+        if expected { "" } else { "not " },
+        if expected { "isn't" } else { "is" },
+        tree_paused,
+        process_mode
+    )
+}
+
+#[macro_export]
+macro_rules! assert_processing {
+    ($node:expr) => {
+        $crate::pause::assert_processing(&$node.clone().upcast(), true)
+    };
+}
+
+#[macro_export]
+macro_rules! assert_not_processing {
+    ($node:expr) => {
+        $crate::pause::assert_processing(&$node.clone().upcast(), false)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explains_processing_failures() {
+        assert_eq!(
+            processing_message("/root/Player", true, true, "PROCESS_MODE_PAUSABLE"),
+            "expected /root/Player to be processing, but it isn't (tree paused: true, process_mode: PROCESS_MODE_PAUSABLE)"
+        );
+        assert_eq!(
+            processing_message("/root/Menu", false, false, "PROCESS_MODE_ALWAYS"),
+            "expected /root/Menu not to be processing, but it is (tree paused: false, process_mode: PROCESS_MODE_ALWAYS)"
+        );
+    }
+}
@@ -0,0 +1,393 @@
+use crate::TestContext;
+use godot::engine::{
+    CollisionObject2D, CollisionObject3D, INode, PhysicsRayQueryParameters2D,
+    PhysicsRayQueryParameters3D, PhysicsServer2D, PhysicsServer3D, PhysicsShapeQueryParameters2D,
+    PhysicsShapeQueryParameters3D, Viewport,
+};
+use godot::prelude::*;
+use std::cell::RefCell;
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+    // Physics ticks still to run before the simulation is paused again. None
+    // while physics runs normally.
+    static ref REMAINING_STEPS: Mutex<Option<u32>> = Mutex::new(None);
+}
+
+thread_local! {
+    // Nodes under the test root whose _physics_process was switched off
+    // while physics is paused.
+    static PAUSED_NODES: RefCell<Vec<Gd<Node>>> = RefCell::new(Vec::new());
+}
+
+// Takes over physics for the rest of the test: the simulation (and the
+// _physics_process of everything under the test root) is paused, and this
+// lets exactly `ticks` physics ticks run. The ticks happen on the following
+// frames, so wait while `physics_steps_pending()`:
+//
+//     if tick!() == 0 {
+//         ctx.step_physics(10);
+//     }
+//     if physics_steps_pending() {
+//         wait!(0.0);
+//     }
+//     assert!(ball.get_position().y > 0.0);
+pub fn step_physics(root: &Gd<Node>, ticks: u32) {
+    let mut remaining = REMAINING_STEPS.lock().unwrap();
+
+    if remaining.is_none() {
+        let mut stepper = PhysicsStepper::new_alloc();
+        stepper.set_name("PhysicsStepper".into());
+        // Before every other node, so it decides whether a tick runs.
+        stepper.set_physics_process_priority(i32::MIN);
+        root.clone().add_child(stepper.upcast());
+
+        set_simulation_active(root, false);
+    }
+
+    *remaining = Some(remaining.unwrap_or(0) + ticks);
+}
+
+impl TestContext {
+    pub fn step_physics(&self, ticks: u32) {
+        step_physics(self.root(), ticks);
+    }
+}
+
+pub fn physics_steps_pending() -> bool {
+    REMAINING_STEPS.lock().unwrap().map_or(false, |remaining| remaining > 0)
+}
+
+fn set_simulation_active(root: &Gd<Node>, active: bool) {
+    PhysicsServer2D::singleton().set_active(active);
+    PhysicsServer3D::singleton().set_active(active);
+
+    if active {
+        for mut node in PAUSED_NODES.with(|nodes| nodes.borrow_mut().drain(..).collect::<Vec<_>>()) {
+            if node.is_instance_valid() {
+                node.set_physics_process(true);
+            }
+        }
+    } else {
+        let mut paused = Vec::new();
+        collect_physics_processing(root.clone(), &mut paused);
+        for node in &mut paused {
+            node.set_physics_process(false);
+        }
+        PAUSED_NODES.with(|nodes| nodes.borrow_mut().extend(paused));
+    }
+}
+
+fn collect_physics_processing(node: Gd<Node>, nodes: &mut Vec<Gd<Node>>) {
+    for child in node.get_children().iter_shared() {
+        collect_physics_processing(child, nodes);
+    }
+
+    if node.is_physics_processing() && node.get_class() != "PhysicsStepper".into() {
+        nodes.push(node);
+    }
+}
+
+#[derive(GodotClass)]
+#[class(base=Node)]
+struct PhysicsStepper {
+    #[base]
+    base: Base<Node>,
+    active: bool,
+}
+
+#[godot_api]
+impl INode for PhysicsStepper {
+    fn init(base: Base<Node>) -> Self {
+        Self { base, active: false }
+    }
+
+    fn physics_process(&mut self, _delta: f64) {
+        let run_tick = {
+            let mut remaining = REMAINING_STEPS.lock().unwrap();
+            match remaining.as_mut() {
+                Some(ticks) if *ticks > 0 => {
+                    *ticks -= 1;
+                    true
+                }
+                _ => false,
+            }
+        };
+
+        if run_tick != self.active {
+            self.active = run_tick;
+            let root = self.base().get_parent().unwrap();
+            set_simulation_active(&root, run_tick);
+        }
+    }
+}
+
+// Called by the runner once a test has finished.
+pub(crate) fn reset() {
+    if REMAINING_STEPS.lock().unwrap().take().is_some() {
+        PhysicsServer2D::singleton().set_active(true);
+        PhysicsServer3D::singleton().set_active(true);
+        PAUSED_NODES.with(|nodes| nodes.borrow_mut().clear());
+    }
+}
+
+// Called by the runner before freeing an isolate_physics test root. Stops
+// its spaces, so nothing in them collides, triggers or leaves an area while
+// the nodes are freed; the spaces themselves go with the root's worlds.
+pub(crate) fn deactivate_spaces(root: &Gd<Node>) {
+    let Ok(viewport) = root.clone().try_cast::<Viewport>() else {
+        return;
+    };
+
+    if let Some(world) = viewport.get_world_2d() {
+        PhysicsServer2D::singleton().space_set_active(world.get_space(), false);
+    }
+    if let Some(world) = viewport.get_world_3d() {
+        PhysicsServer3D::singleton().space_set_active(world.get_space(), false);
+    }
+}
+
+// Every collision object whose shapes overlap one of `node`'s shapes right
+// now, found with a shape query on the node's physics space. `node` must be
+// a CollisionObject2D or CollisionObject3D (Area, body, ...).
+pub fn overlapping(node: &Gd<Node>) -> Vec<Gd<Node>> {
+    if let Ok(object) = node.clone().try_cast::<CollisionObject2D>() {
+        overlapping_2d(object)
+    } else if let Ok(object) = node.clone().try_cast::<CollisionObject3D>() {
+        overlapping_3d(object)
+    } else {
+        panic!(
+            "{} is a {}, not a CollisionObject2D or CollisionObject3D",
+            node.get_path(),
+            node.get_class()
+        )
+    }
+}
+
+fn colliders(results: Array<Dictionary>, own: Rid) -> Vec<Gd<Node>> {
+    let mut colliders: Vec<Gd<Node>> = Vec::new();
+
+    for result in results.iter_shared() {
+        let rid = result.get("rid").map(|rid| rid.to::<Rid>());
+        if rid == Some(own) {
+            continue;
+        }
+
+        let collider = result
+            .get("collider")
+            .and_then(|collider| collider.try_to::<Gd<Node>>().ok());
+
+        if let Some(collider) = collider {
+            if !colliders.contains(&collider) {
+                colliders.push(collider);
+            }
+        }
+    }
+
+    colliders
+}
+
+fn overlapping_2d(object: Gd<CollisionObject2D>) -> Vec<Gd<Node>> {
+    let Some(mut space) = object.get_world_2d().and_then(|world| world.get_direct_space_state()) else {
+        panic!("{} is not in a 2D physics space", object.get_path());
+    };
+
+    let own = object.get_rid();
+    let mut found = Vec::new();
+
+    for owner in object.get_shape_owners().to_vec() {
+        let owner = owner as u32;
+        let transform = object.get_global_transform() * object.shape_owner_get_transform(owner);
+
+        for index in 0..object.shape_owner_get_shape_count(owner) {
+            let Some(shape) = object.shape_owner_get_shape(owner, index) else {
+                continue;
+            };
+
+            let mut query = PhysicsShapeQueryParameters2D::new_gd();
+            query.set_shape_rid(shape.get_rid());
+            query.set_transform(transform);
+            query.set_collision_mask(u32::MAX);
+            query.set_collide_with_areas(true);
+            query.set_collide_with_bodies(true);
+
+            let results = space.intersect_shape_ex(query).max_results(64).done();
+            for collider in colliders(results, own) {
+                if !found.contains(&collider) {
+                    found.push(collider);
+                }
+            }
+        }
+    }
+
+    found
+}
+
+fn overlapping_3d(object: Gd<CollisionObject3D>) -> Vec<Gd<Node>> {
+    let Some(mut space) = object.get_world_3d().and_then(|world| world.get_direct_space_state()) else {
+        panic!("{} is not in a 3D physics space", object.get_path());
+    };
+
+    let own = object.get_rid();
+    let mut found = Vec::new();
+
+    for owner in object.get_shape_owners().to_vec() {
+        let owner = owner as u32;
+        let transform = object.get_global_transform() * object.shape_owner_get_transform(owner);
+
+        for index in 0..object.shape_owner_get_shape_count(owner) {
+            let Some(shape) = object.shape_owner_get_shape(owner, index) else {
+                continue;
+            };
+
+            let mut query = PhysicsShapeQueryParameters3D::new_gd();
+            query.set_shape_rid(shape.get_rid());
+            query.set_transform(transform);
+            query.set_collision_mask(u32::MAX);
+            query.set_collide_with_areas(true);
+            query.set_collide_with_bodies(true);
+
+            let results = space.intersect_shape_ex(query).max_results(64).done();
+            for collider in colliders(results, own) {
+                if !found.contains(&collider) {
+                    found.push(collider);
+                }
+            }
+        }
+    }
+
+    found
+}
+
+fn describe_nodes(nodes: &[Gd<Node>]) -> String {
+    if nodes.is_empty() {
+        return "nothing".to_string();
+    }
+
+    nodes
+        .iter()
+        .map(|node| format!("{} ({})", node.get_path(), node.get_class()))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[track_caller]
+pub fn assert_colliding(a: &Gd<Node>, b: &Gd<Node>, expected: bool) {
+    let overlapping = overlapping(a);
+    let colliding = overlapping.contains(b);
+
+    if colliding != expected {
+        panic!(
+            "expected {} {}to overlap {}, but it overlaps {}",
+            a.get_path(),
+            if expected { "" } else { "not " },
+            b.get_path(),
+            describe_nodes(&overlapping)
+        );
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RayHit2D {
+    pub collider: Option<Gd<Node>>,
+    pub position: Vector2,
+    pub normal: Vector2,
+}
+
+#[derive(Clone, Debug)]
+pub struct RayHit3D {
+    pub collider: Option<Gd<Node>>,
+    pub position: Vector3,
+    pub normal: Vector3,
+}
+
+// Casts a ray (global coordinates) in the 2D space `node` lives in and
+// returns the first hit.
+pub fn raycast_2d(node: &Gd<Node2D>, from: Vector2, to: Vector2) -> Option<RayHit2D> {
+    let mut space = node
+        .get_world_2d()
+        .and_then(|world| world.get_direct_space_state())
+        .unwrap_or_else(|| panic!("{} is not in a 2D physics space", node.get_path()));
+
+    let mut query = PhysicsRayQueryParameters2D::create(from, to)?;
+    query.set_collide_with_areas(true);
+
+    let hit = space.intersect_ray(query);
+    if hit.is_empty() {
+        return None;
+    }
+
+    Some(RayHit2D {
+        collider: hit.get("collider").and_then(|collider| collider.try_to::<Gd<Node>>().ok()),
+        position: hit.get("position").map_or(Vector2::ZERO, |position| position.to()),
+        normal: hit.get("normal").map_or(Vector2::ZERO, |normal| normal.to()),
+    })
+}
+
+pub fn raycast_3d(node: &Gd<Node3D>, from: Vector3, to: Vector3) -> Option<RayHit3D> {
+    let mut space = node
+        .get_world_3d()
+        .and_then(|world| world.get_direct_space_state())
+        .unwrap_or_else(|| panic!("{} is not in a 3D physics space", node.get_path()));
+
+    let mut query = PhysicsRayQueryParameters3D::create(from, to)?;
+    query.set_collide_with_areas(true);
+
+    let hit = space.intersect_ray(query);
+    if hit.is_empty() {
+        return None;
+    }
+
+    Some(RayHit3D {
+        collider: hit.get("collider").and_then(|collider| collider.try_to::<Gd<Node>>().ok()),
+        position: hit.get("position").map_or(Vector3::ZERO, |position| position.to()),
+        normal: hit.get("normal").map_or(Vector3::ZERO, |normal| normal.to()),
+    })
+}
+
+// Asserts the ray from `from` to `to` hits `expected` first.
+#[track_caller]
+pub fn assert_ray_hits_2d(node: &Gd<Node2D>, from: Vector2, to: Vector2, expected: &Gd<Node>) {
+    let hit = raycast_2d(node, from, to);
+    let collider = hit.as_ref().and_then(|hit| hit.collider.clone());
+
+    if collider.as_ref() != Some(expected) {
+        panic!(
+            "expected the ray from {} to {} to hit {}, but it hit {}",
+            from,
+            to,
+            expected.get_path(),
+            describe_nodes(&collider.into_iter().collect::<Vec<_>>())
+        );
+    }
+}
+
+#[track_caller]
+pub fn assert_ray_hits_3d(node: &Gd<Node3D>, from: Vector3, to: Vector3, expected: &Gd<Node>) {
+    let hit = raycast_3d(node, from, to);
+    let collider = hit.as_ref().and_then(|hit| hit.collider.clone());
+
+    if collider.as_ref() != Some(expected) {
+        panic!(
+            "expected the ray from {} to {} to hit {}, but it hit {}",
+            from,
+            to,
+            expected.get_path(),
+            describe_nodes(&collider.into_iter().collect::<Vec<_>>())
+        );
+    }
+}
+
+#[macro_export]
+macro_rules! assert_colliding {
+    ($a:expr, $b:expr) => {
+        $crate::physics::assert_colliding(&$a.clone().upcast(), &$b.clone().upcast(), true)
+    };
+}
+
+#[macro_export]
+macro_rules! assert_not_colliding {
+    ($a:expr, $b:expr) => {
+        $crate::physics::assert_colliding(&$a.clone().upcast(), &$b.clone().upcast(), false)
+    };
+}
@@ -0,0 +1,186 @@
+use godot::engine::Resource;
+use godot::prelude::*;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+// Values that read better in failure messages than their Debug output,
+// mostly engine types: a Gd prints as its class, name and path instead of an
+// instance id, a Dictionary as its entries.
+pub trait Pretty {
+    fn pretty(&self) -> String;
+}
+
+// Past this many characters a Dictionary or Array is split over lines.
+const INLINE_WIDTH: usize = 60;
+
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("  {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn collection(open: &str, items: Vec<String>, close: &str) -> String {
+    let inline = format!("{}{}{}", open, items.join(", "), close);
+
+    if inline.len() <= INLINE_WIDTH && !inline.contains('\n') {
+        return inline;
+    }
+
+    let items: Vec<String> = items.iter().map(|item| format!("{},", indent(item))).collect();
+    format!("{}\n{}\n{}", open, items.join("\n"), close)
+}
+
+pub fn object(object: &Gd<Object>) -> String {
+    if !object.is_instance_valid() {
+        return "<freed object>".to_string();
+    }
+
+    let class = object.get_class();
+
+    if let Ok(node) = object.clone().try_cast::<Node>() {
+        let name = node.get_name();
+        return if node.is_inside_tree() {
+            format!("{} {:?} at {}", class, name.to_string(), node.get_path())
+        } else {
+            format!("{} {:?} (not in the tree)", class, name.to_string())
+        };
+    }
+
+    if let Ok(resource) = object.clone().try_cast::<Resource>() {
+        let path = resource.get_path().to_string();
+        if !path.is_empty() {
+            return format!("{} {}", class, path);
+        }
+    }
+
+    format!("{}#{}", class, object.instance_id().to_i64())
+}
+
+pub fn variant(value: &Variant) -> String {
+    match value.get_type() {
+        VariantType::Nil => "null".to_string(),
+        VariantType::Bool | VariantType::Int | VariantType::Float => value.stringify().to_string(),
+        VariantType::String => format!("{:?}", value.stringify().to_string()),
+        VariantType::StringName => format!("&{:?}", value.stringify().to_string()),
+        VariantType::NodePath => format!("^{:?}", value.stringify().to_string()),
+        VariantType::Object => match value.try_to::<Gd<Object>>() {
+            Ok(gd) => object(&gd),
+            Err(_) => "<freed object>".to_string(),
+        },
+        VariantType::Dictionary => value.to::<Dictionary>().pretty(),
+        VariantType::Array => value.to::<VariantArray>().pretty(),
+        other => format!("{:?}{}", other, value.stringify()),
+    }
+}
+
+impl Pretty for Variant {
+    fn pretty(&self) -> String {
+        variant(self)
+    }
+}
+
+impl Pretty for Dictionary {
+    fn pretty(&self) -> String {
+        let entries = self
+            .iter_shared()
+            .map(|(key, value)| format!("{}: {}", variant(&key), variant(&value)))
+            .collect();
+        collection("{", entries, "}")
+    }
+}
+
+impl<T: GodotType + ToGodot> Pretty for Array<T> {
+    fn pretty(&self) -> String {
+        let items = self.iter_shared().map(|item| variant(&item.to_variant())).collect();
+        collection("[", items, "]")
+    }
+}
+
+impl Pretty for GString {
+    fn pretty(&self) -> String {
+        format!("{:?}", self.to_string())
+    }
+}
+
+impl Pretty for StringName {
+    fn pretty(&self) -> String {
+        format!("&{:?}", self.to_string())
+    }
+}
+
+impl Pretty for NodePath {
+    fn pretty(&self) -> String {
+        format!("^{:?}", self.to_string())
+    }
+}
+
+impl<T: GodotClass + Inherits<Object>> Pretty for Gd<T> {
+    fn pretty(&self) -> String {
+        object(&self.clone().upcast())
+    }
+}
+
+impl<T: Pretty> Pretty for Option<T> {
+    fn pretty(&self) -> String {
+        match self {
+            Some(value) => format!("Some({})", value.pretty()),
+            None => "None".to_string(),
+        }
+    }
+}
+
+impl<T: Pretty> Pretty for Vec<T> {
+    fn pretty(&self) -> String {
+        collection("[", self.iter().map(Pretty::pretty).collect(), "]")
+    }
+}
+
+impl<T: Pretty + ?Sized> Pretty for &T {
+    fn pretty(&self) -> String {
+        (**self).pretty()
+    }
+}
+
+// Picks Pretty when a type implements it and falls back to Debug otherwise,
+// at the call site (where the concrete type is known):
+//
+//     use godot_rust_specs::pretty::{ViaDebug, ViaPretty};
+//     let format = (&Formatter::of(&value)).formatter();
+//
+// `expect!` and `assert_eq_diff!` do this for you.
+pub struct Formatter<T>(PhantomData<T>);
+
+impl<T> Formatter<T> {
+    pub fn of(_value: &T) -> Self {
+        Formatter(PhantomData)
+    }
+}
+
+pub trait ViaPretty<T> {
+    fn formatter(&self) -> Option<fn(&T) -> String>;
+}
+
+impl<T: Pretty> ViaPretty<T> for Formatter<T> {
+    fn formatter(&self) -> Option<fn(&T) -> String> {
+        Some(|value| value.pretty())
+    }
+}
+
+pub trait ViaDebug<T> {
+    fn formatter(&self) -> Option<fn(&T) -> String>;
+}
+
+impl<T> ViaDebug<T> for &Formatter<T> {
+    fn formatter(&self) -> Option<fn(&T) -> String> {
+        None
+    }
+}
+
+// Pretty output when there is one, otherwise Debug.
+pub fn format_with<T: Debug>(value: &T, formatter: Option<fn(&T) -> String>) -> String {
+    match formatter {
+        Some(formatter) => formatter(value),
+        None => format!("{:?}", value),
+    }
+}
@@ -0,0 +1,145 @@
+use godot::engine::performance::Monitor;
+use godot::engine::Performance;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+lazy_static::lazy_static! {
+    static ref FRAMES: Mutex<Vec<FrameSample>> = Mutex::new(Vec::new());
+    static ref SCOPES: Mutex<HashMap<&'static str, ScopeTiming>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameSample {
+    pub delta: f64,
+    pub process_time: f64,
+    pub physics_time: f64,
+    pub objects: f64,
+    pub draw_calls: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ScopeTiming {
+    pub calls: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+fn enabled() -> bool {
+    crate::config::with(|config| config.profile_tests)
+}
+
+// Called by the runner every frame a test is running.
+pub(crate) fn sample(delta: f64) {
+    if !enabled() {
+        return;
+    }
+
+    let performance = Performance::singleton();
+    FRAMES.lock().unwrap().push(FrameSample {
+        delta,
+        process_time: performance.get_monitor(Monitor::TIME_PROCESS),
+        physics_time: performance.get_monitor(Monitor::TIME_PHYSICS_PROCESS),
+        objects: performance.get_monitor(Monitor::OBJECT_COUNT),
+        draw_calls: performance.get_monitor(Monitor::RENDER_TOTAL_DRAW_CALLS_IN_FRAME),
+    });
+}
+
+// Times the rest of the enclosing block into the test's profile (when
+// profile_tests is on):
+//
+//     fn generate_chunk(..) {
+//         let _scope = ProfileScope::new("generate_chunk");
+//         ...
+//     }
+pub struct ProfileScope {
+    name: &'static str,
+    started_at: Option<Instant>,
+}
+
+impl ProfileScope {
+    pub fn new(name: &'static str) -> Self {
+        Self { name, started_at: enabled().then(Instant::now) }
+    }
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        let Some(started_at) = self.started_at else {
+            return;
+        };
+
+        let elapsed = started_at.elapsed();
+        let mut scopes = SCOPES.lock().unwrap();
+        let timing = scopes.entry(self.name).or_default();
+        timing.calls += 1;
+        timing.total += elapsed;
+        timing.max = timing.max.max(elapsed);
+    }
+}
+
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::profiler::ProfileScope::new($name);
+    };
+}
+
+// Called by the runner when a test has finished. Writes the test's profile
+// to `<artifacts_dir>/<test>/profile.json` if it failed or was slower than
+// profile_slow_threshold, and returns that path.
+pub(crate) fn finish(test_name: &str, failed: bool, duration: Duration) -> Option<String> {
+    let frames = std::mem::take(&mut *FRAMES.lock().unwrap());
+    let scopes = std::mem::take(&mut *SCOPES.lock().unwrap());
+
+    if !enabled() {
+        return None;
+    }
+
+    let slow = crate::config::with(|config| config.profile_slow_threshold)
+        .map_or(false, |threshold| duration > threshold);
+    if !failed && !slow {
+        return None;
+    }
+
+    let average = |value: fn(&FrameSample) -> f64| {
+        if frames.is_empty() {
+            0.0
+        } else {
+            frames.iter().map(value).sum::<f64>() / frames.len() as f64
+        }
+    };
+
+    let mut scope_list: Vec<(&&str, &ScopeTiming)> = scopes.iter().collect();
+    scope_list.sort_by(|a, b| b.1.total.cmp(&a.1.total));
+
+    let profile = json!({
+        "test": test_name,
+        "duration_ms": duration.as_secs_f64() * 1000.0,
+        "frames": frames.len(),
+        "average": {
+            "frame_time_ms": average(|frame| frame.delta) * 1000.0,
+            "process_time_ms": average(|frame| frame.process_time) * 1000.0,
+            "physics_time_ms": average(|frame| frame.physics_time) * 1000.0,
+        },
+        "frame_samples": frames.iter().map(|frame| json!({
+            "delta_ms": frame.delta * 1000.0,
+            "process_time_ms": frame.process_time * 1000.0,
+            "physics_time_ms": frame.physics_time * 1000.0,
+            "objects": frame.objects,
+            "draw_calls": frame.draw_calls,
+        })).collect::<Vec<_>>(),
+        "scopes": scope_list.iter().map(|(name, timing)| json!({
+            "name": name,
+            "calls": timing.calls,
+            "total_ms": timing.total.as_secs_f64() * 1000.0,
+            "max_ms": timing.max.as_secs_f64() * 1000.0,
+        })).collect::<Vec<_>>(),
+    });
+
+    let path = crate::artifacts::path_for(test_name, "profile.json");
+
+    std::fs::write(&path, serde_json::to_string_pretty(&profile).unwrap_or_default()).ok()?;
+    Some(path)
+}
@@ -0,0 +1,81 @@
+use crate::output;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+lazy_static::lazy_static! {
+    static ref PROGRESS: Mutex<Option<Progress>> = Mutex::new(None);
+    // Whether the status is currently drawn after the cursor.
+    static ref SHOWN: Mutex<bool> = Mutex::new(false);
+}
+
+struct Progress {
+    total: usize,
+    done: usize,
+    started_at: Instant,
+}
+
+// `[123/480] ETA 1m20s`, drawn after the dots of the progress format and
+// redrawn as tests finish. Only in a terminal; piped output keeps plain dots.
+fn enabled() -> bool {
+    crate::config::with(|config| {
+        config.progress
+            && config.format == crate::report::Format::Progress
+            && config.verbosity == crate::config::Verbosity::Normal
+    })
+        && output::colors_enabled()
+}
+
+pub(crate) fn start(total: usize) {
+    if !enabled() {
+        return;
+    }
+
+    *PROGRESS.lock().unwrap() = Some(Progress { total, done: 0, started_at: Instant::now() });
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    if seconds >= 60 {
+        format!("{}m{:02}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+// Called by the runner after each test's dot.
+pub(crate) fn test_finished() {
+    let mut progress = PROGRESS.lock().unwrap();
+    let Some(progress) = progress.as_mut() else {
+        return;
+    };
+
+    progress.done += 1;
+
+    let remaining = progress.total.saturating_sub(progress.done);
+    let eta = if progress.done == 0 || remaining == 0 {
+        String::new()
+    } else {
+        let average = progress.started_at.elapsed() / progress.done as u32;
+        format!(" ETA {}", format_duration(average * remaining as u32))
+    };
+
+    // Save the cursor, draw the status and go back, so the next dot lands
+    // where this one left off and overwrites it.
+    let status = format!("\x1B[K\x1B7 [{}/{}]{}\x1B8", progress.done, progress.total, eta);
+    print!("{}", output::colorize(output::BLUE, &status));
+    let _ = io::stdout().flush();
+
+    *SHOWN.lock().unwrap() = remaining > 0;
+}
+
+// Clears the status before anything else is printed, called by output.
+pub(crate) fn clear() {
+    let mut shown = SHOWN.lock().unwrap();
+
+    if *shown {
+        print!("\x1B[K");
+        *shown = false;
+    }
+}
@@ -0,0 +1,374 @@
+use crate::panics::catch_expected_panic;
+use crate::random::{current_seed, TestRng};
+use crate::state::test_state;
+use crate::{TestCase, TestContext};
+use godot::prelude::*;
+use std::fmt::Debug;
+use std::ops::Range;
+
+// Gives up shrinking after this many attempts, reporting the smallest
+// failing value found so far.
+const MAX_SHRINK_ATTEMPTS: usize = 500;
+
+// Generates values for a property test and proposes simpler versions of a
+// failing one.
+pub trait Strategy: Send + Sync + 'static {
+    type Value: Clone + Debug + 'static;
+
+    fn generate(&self, rng: &mut TestRng) -> Self::Value;
+
+    // Candidates that are "smaller" than `value`, most aggressive first.
+    fn shrink(&self, _value: &Self::Value) -> Vec<Self::Value> {
+        Vec::new()
+    }
+}
+
+pub struct Ints(Range<i64>);
+
+// Integers in `range`, shrinking towards zero (or the closest bound).
+pub fn ints(range: Range<i64>) -> Ints {
+    Ints(range)
+}
+
+impl Strategy for Ints {
+    type Value = i64;
+
+    fn generate(&self, rng: &mut TestRng) -> i64 {
+        rng.range_i64(self.0.start, self.0.end)
+    }
+
+    fn shrink(&self, value: &i64) -> Vec<i64> {
+        let target = 0.clamp(self.0.start, self.0.end - 1);
+        let mut candidates = Vec::new();
+        // In i128, as the distance between two i64s doesn't always fit in one.
+        let mut distance = *value as i128 - target as i128;
+
+        while distance != 0 {
+            candidates.push((*value as i128 - distance) as i64);
+            distance /= 2;
+        }
+
+        candidates
+    }
+}
+
+pub struct Floats(Range<f64>);
+
+// Floats in `range`, shrinking towards zero (or the closest bound) and
+// towards whole numbers.
+pub fn floats(range: Range<f64>) -> Floats {
+    Floats(range)
+}
+
+impl Strategy for Floats {
+    type Value = f64;
+
+    fn generate(&self, rng: &mut TestRng) -> f64 {
+        rng.range_f64(self.0.start, self.0.end)
+    }
+
+    fn shrink(&self, value: &f64) -> Vec<f64> {
+        let target = 0.0_f64.clamp(self.0.start, self.0.end);
+        let mut candidates = vec![target];
+
+        if value.fract() != 0.0 && self.0.contains(&value.trunc()) {
+            candidates.push(value.trunc());
+        }
+
+        let mut distance = (value - target) / 2.0;
+        for _ in 0..8 {
+            candidates.push(value - distance);
+            distance /= 2.0;
+        }
+
+        candidates.retain(|candidate| candidate != value);
+        candidates
+    }
+}
+
+pub struct Bools;
+
+pub fn bools() -> Bools {
+    Bools
+}
+
+impl Strategy for Bools {
+    type Value = bool;
+
+    fn generate(&self, rng: &mut TestRng) -> bool {
+        rng.next_bool()
+    }
+
+    fn shrink(&self, value: &bool) -> Vec<bool> {
+        if *value {
+            vec![false]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+pub struct Vector2s(Floats, Floats);
+
+// Vectors with each component in `range`.
+pub fn vector2s(range: Range<f32>) -> Vector2s {
+    let range = range.start as f64..range.end as f64;
+    Vector2s(floats(range.clone()), floats(range))
+}
+
+impl Strategy for Vector2s {
+    type Value = Vector2;
+
+    fn generate(&self, rng: &mut TestRng) -> Vector2 {
+        Vector2::new(self.0.generate(rng) as f32, self.1.generate(rng) as f32)
+    }
+
+    fn shrink(&self, value: &Vector2) -> Vec<Vector2> {
+        let xs = self.0.shrink(&(value.x as f64)).into_iter().map(|x| Vector2::new(x as f32, value.y));
+        let ys = self.1.shrink(&(value.y as f64)).into_iter().map(|y| Vector2::new(value.x, y as f32));
+        xs.chain(ys).collect()
+    }
+}
+
+pub struct VecOf<S> {
+    element: S,
+    length: Range<usize>,
+}
+
+// Vecs of `element` values, with a length in `length`. Shrinks by dropping
+// elements, then by shrinking them.
+pub fn vec_of<S: Strategy>(element: S, length: Range<usize>) -> VecOf<S> {
+    VecOf { element, length }
+}
+
+impl<S: Strategy> Strategy for VecOf<S> {
+    type Value = Vec<S::Value>;
+
+    fn generate(&self, rng: &mut TestRng) -> Self::Value {
+        let length = rng.range_i64(self.length.start as i64, self.length.end as i64) as usize;
+        (0..length).map(|_| self.element.generate(rng)).collect()
+    }
+
+    fn shrink(&self, value: &Self::Value) -> Vec<Self::Value> {
+        let mut candidates = Vec::new();
+
+        if value.len() > self.length.start {
+            candidates.push(value[..self.length.start.max(value.len() / 2)].to_vec());
+            for index in 0..value.len() {
+                let mut shorter = value.clone();
+                shorter.remove(index);
+                candidates.push(shorter);
+            }
+        }
+
+        for (index, element) in value.iter().enumerate() {
+            for smaller in self.element.shrink(element) {
+                let mut candidate = value.clone();
+                candidate[index] = smaller;
+                candidates.push(candidate);
+            }
+        }
+
+        candidates
+    }
+}
+
+impl<A: Strategy, B: Strategy> Strategy for (A, B) {
+    type Value = (A::Value, B::Value);
+
+    fn generate(&self, rng: &mut TestRng) -> Self::Value {
+        (self.0.generate(rng), self.1.generate(rng))
+    }
+
+    fn shrink(&self, value: &Self::Value) -> Vec<Self::Value> {
+        let firsts = self.0.shrink(&value.0).into_iter().map(|a| (a, value.1.clone()));
+        let seconds = self.1.shrink(&value.1).into_iter().map(|b| (value.0.clone(), b));
+        firsts.chain(seconds).collect()
+    }
+}
+
+enum Phase<V> {
+    Generating,
+    Shrinking { candidates: Vec<V>, attempts: usize },
+}
+
+struct PropState<V> {
+    rng: Option<TestRng>,
+    case: usize,
+    value: Option<V>,
+    phase: Phase<V>,
+    // The smallest failing value so far, with its panic message.
+    failure: Option<(V, String)>,
+    original_failure: Option<(V, String)>,
+}
+
+impl<V> Default for PropState<V> {
+    fn default() -> Self {
+        Self {
+            rng: None,
+            case: 0,
+            value: None,
+            phase: Phase::Generating,
+            failure: None,
+            original_failure: None,
+        }
+    }
+}
+
+enum Step<V> {
+    // The body called wait!, so it runs again with the same value.
+    Waiting,
+    // Run again on the next frame with the next value.
+    Pending,
+    Passed,
+    Failed(V, String, V, String, usize),
+}
+
+// A property test: `body` runs for `cases` values generated by `strategy`.
+// The body may span frames with `wait!` (it's called with the same value
+// until it stops waiting). A failing value is shrunk to a minimal one, which
+// is reported together with the seed that reproduces it.
+#[track_caller]
+pub fn prop_case<S, F>(name: impl Into<String>, cases: usize, strategy: S, body: F) -> TestCase
+where
+    S: Strategy,
+    F: Fn(&TestContext, S::Value) + Send + Sync + 'static,
+{
+    TestCase::new(name, move |context| run(context, cases, &strategy, &body))
+}
+
+fn run<S, F>(context: &TestContext, cases: usize, strategy: &S, body: &F)
+where
+    S: Strategy,
+    F: Fn(&TestContext, S::Value),
+{
+    let step = test_state(|state: &mut PropState<S::Value>| step(context, cases, strategy, body, state));
+
+    match step {
+        Step::Waiting => *crate::WANTS_REPLAY.lock().unwrap() = true,
+        Step::Pending => {
+            *crate::WANTS_REPLAY.lock().unwrap() = true;
+            *crate::DELAY_BEFORE_NEXT_TEST_RUN.lock().unwrap() = 0.0;
+        }
+        Step::Passed => {}
+        Step::Failed(minimal, message, original, original_message, case) => panic!(
+            "property failed after {} case(s) (seed {}, reproduce with --seed {})\n\
+             minimal failing value: {:?}\n{}\n\
+             originally failed with: {:?}\n{}",
+            case + 1,
+            current_seed(),
+            crate::random::suite_seed(),
+            minimal,
+            message,
+            original,
+            original_message
+        ),
+    }
+}
+
+fn step<S, F>(
+    context: &TestContext,
+    cases: usize,
+    strategy: &S,
+    body: &F,
+    state: &mut PropState<S::Value>,
+) -> Step<S::Value>
+where
+    S: Strategy,
+    F: Fn(&TestContext, S::Value),
+{
+    let rng = state.rng.get_or_insert_with(|| TestRng::new(current_seed()));
+
+    let value = match &state.value {
+        Some(value) => value.clone(),
+        None => {
+            let value = strategy.generate(rng);
+            state.value = Some(value.clone());
+            value
+        }
+    };
+
+    let previous_replay = std::mem::replace(&mut *crate::WANTS_REPLAY.lock().unwrap(), false);
+    let panic = catch_expected_panic(|| body(context, value.clone()));
+    let wants_replay = std::mem::replace(&mut *crate::WANTS_REPLAY.lock().unwrap(), previous_replay);
+
+    if panic.is_none() && wants_replay {
+        return Step::Waiting;
+    }
+
+    // This value is done with; the next one starts from an empty test root.
+    state.value = None;
+    clear_root(context);
+
+    if let Some(message) = panic {
+        if state.original_failure.is_none() {
+            state.original_failure = Some((value.clone(), message.clone()));
+        }
+        state.failure = Some((value.clone(), message));
+        state.phase = Phase::Shrinking { candidates: strategy.shrink(&value), attempts: 0 };
+    } else if let Phase::Generating = state.phase {
+        state.case += 1;
+        if state.case >= cases {
+            return Step::Passed;
+        }
+        return Step::Pending;
+    }
+
+    let Phase::Shrinking { candidates, attempts } = &mut state.phase else {
+        unreachable!();
+    };
+
+    if candidates.is_empty() || *attempts >= MAX_SHRINK_ATTEMPTS {
+        let (minimal, message) = state.failure.take().unwrap();
+        let (original, original_message) = state.original_failure.take().unwrap();
+        return Step::Failed(minimal, message, original, original_message, state.case);
+    }
+
+    *attempts += 1;
+    state.value = Some(candidates.remove(0));
+    Step::Pending
+}
+
+fn clear_root(context: &TestContext) {
+    for mut child in context.root().get_children().iter_shared() {
+        child.free();
+    }
+}
+
+// prop_test!("path_is_never_longer_than_budget", 200, prop::ints(0..1000), |ctx, budget| { ... })
+#[macro_export]
+macro_rules! prop_test {
+    ($name:expr, $cases:expr, $strategy:expr, $body:expr) => {{
+        $crate::register($crate::prop::prop_case($name, $cases, $strategy, $body).module_path(module_path!()));
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shrinks_ints_towards_the_closest_bound() {
+        assert_eq!(ints(-5..5).shrink(&4), vec![0, 2, 3]);
+        assert_eq!(ints(10..20).shrink(&15), vec![10, 13, 14]);
+        assert_eq!(ints(-20..-10).shrink(&-15), vec![-11, -13, -14]);
+        assert_eq!(ints(10..20).shrink(&10), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn shrinks_ints_across_the_whole_range() {
+        let whole = ints(i64::MIN..i64::MAX);
+
+        let candidates = whole.shrink(&i64::MIN);
+        assert_eq!(candidates.len(), 64);
+        assert_eq!(candidates.first(), Some(&0));
+        assert_eq!(candidates.last(), Some(&(i64::MIN + 1)));
+
+        let candidates = whole.shrink(&(i64::MAX - 1));
+        assert_eq!(candidates.first(), Some(&0));
+        assert_eq!(candidates.last(), Some(&(i64::MAX - 2)));
+
+        let candidates = ints(i64::MIN..i64::MIN + 10).shrink(&(i64::MIN + 5));
+        assert_eq!(candidates, vec![i64::MIN + 9, i64::MIN + 7, i64::MIN + 6]);
+    }
+}
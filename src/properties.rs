@@ -0,0 +1,135 @@
+use crate::approx::{ApproxEq, Tolerance};
+use crate::pretty;
+use godot::engine::global::PropertyUsageFlags;
+use godot::prelude::*;
+
+fn property_names(object: &Gd<Object>) -> Vec<(String, bool)> {
+    object
+        .get_property_list()
+        .iter_shared()
+        .filter_map(|property| {
+            let name = property.get("name")?.to_string();
+            let usage = property.get("usage").and_then(|usage| usage.try_to::<u64>().ok()).unwrap_or(0);
+            let script_variable = usage & PropertyUsageFlags::SCRIPT_VARIABLE.ord() as u64 != 0;
+            Some((name, script_variable))
+        })
+        .collect()
+}
+
+fn describe_object(object: &Gd<Object>) -> String {
+    match object.clone().try_cast::<Node>() {
+        Ok(node) if node.is_inside_tree() => node.get_path().to_string(),
+        Ok(node) => node.get_name().to_string(),
+        Err(object) => format!("{}#{}", object.get_class(), object.instance_id()),
+    }
+}
+
+// None when they match, otherwise the component they diverged at, if that
+// applies. Numbers (ints and floats mix) and float based types like vectors
+// and colors compare within `epsilon`, everything else exactly.
+fn difference(actual: &Variant, expected: &Variant, epsilon: f64) -> Option<Option<String>> {
+    let tolerance = Tolerance::Absolute(epsilon);
+
+    macro_rules! compare_as {
+        ($($type:ty),*) => {
+            $(
+                if let (Ok(actual), Ok(expected)) = (actual.try_to::<$type>(), expected.try_to::<$type>()) {
+                    return actual.diverging_component(&expected, tolerance).map(Some);
+                }
+            )*
+        };
+    }
+
+    // Two ints without an epsilon compare exactly, even past f64's precision.
+    let both_ints = actual.get_type() == VariantType::Int && expected.get_type() == VariantType::Int;
+
+    if !(both_ints && epsilon == 0.0) {
+        if let (Some(actual), Some(expected)) = (number(actual), number(expected)) {
+            return ((actual - expected).abs() > epsilon).then_some(None);
+        }
+    }
+
+    if expected.get_type() == actual.get_type() {
+        compare_as!(Vector2, Vector3, Vector4, Quaternion, Color, Basis, Transform2D, Transform3D);
+    }
+
+    (actual != expected).then_some(None)
+}
+
+fn number(value: &Variant) -> Option<f64> {
+    match value.get_type() {
+        VariantType::Int => value.try_to::<i64>().ok().map(|value| value as f64),
+        VariantType::Float => value.try_to::<f64>().ok(),
+        _ => None,
+    }
+}
+
+// Reads `property` through Object::get (or get_indexed, for paths like
+// "position:x"), so it works for exported GDScript variables and engine
+// properties alike. Ints and floats compare by value; with an epsilon,
+// numbers, vectors, colors and transforms compare within it.
+#[track_caller]
+pub fn assert_property_eq(object: &Gd<Object>, property: &str, expected: Variant, epsilon: Option<f64>) {
+    let name = property.split(':').next().unwrap_or(property);
+    let names = property_names(object);
+
+    if !names.iter().any(|(existing, _)| existing == name) {
+        let script_variables: Vec<&str> =
+            names.iter().filter(|(_, script)| *script).map(|(name, _)| name.as_str()).collect();
+
+        panic!(
+            "{} ({}) has no property {:?}{}",
+            describe_object(object),
+            object.get_class(),
+            name,
+            if script_variables.is_empty() {
+                String::new()
+            } else {
+                format!(". Its script variables: {}", script_variables.join(", "))
+            }
+        );
+    }
+
+    let actual = if property.contains(':') {
+        object.get_indexed(property.into())
+    } else {
+        object.get(property.into())
+    };
+
+    if let Some(component) = difference(&actual, &expected, epsilon.unwrap_or(0.0)) {
+        panic!(
+            "expected {}.{} to be {} ({:?}){}, but it's {} ({:?}){}",
+            describe_object(object),
+            property,
+            pretty::variant(&expected),
+            expected.get_type(),
+            epsilon.map(|epsilon| format!(" within {}", epsilon)).unwrap_or_default(),
+            pretty::variant(&actual),
+            actual.get_type(),
+            component.map(|component| format!(", diverged at {}", component)).unwrap_or_default()
+        );
+    }
+}
+
+// assert_property_eq!(enemy, "health", 100), or with a tolerance,
+// assert_property_eq!(enemy, "velocity", Vector2::new(0.0, 98.0), 0.01).
+// `enemy` can be any Gd<T> where T inherits Object.
+#[macro_export]
+macro_rules! assert_property_eq {
+    ($object:expr, $property:expr, $expected:expr) => {
+        $crate::properties::assert_property_eq(
+            &$object.clone().upcast(),
+            $property,
+            godot::prelude::ToGodot::to_variant(&$expected),
+            None,
+        )
+    };
+    ($object:expr, $property:expr, $expected:expr, $epsilon:expr) => {
+        $crate::properties::assert_property_eq(
+            &$object.clone().upcast(),
+            $property,
+            godot::prelude::ToGodot::to_variant(&$expected),
+            Some(($epsilon) as f64),
+        )
+    };
+}
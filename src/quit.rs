@@ -0,0 +1,26 @@
+use std::cell::RefCell;
+
+type Hook = Box<dyn FnOnce(i32)>;
+
+thread_local! {
+    static HOOK: RefCell<Option<Hook>> = RefCell::new(None);
+}
+
+// Called with the exit code when the suite is done, instead of the runner
+// quitting. E.g. to go back to the game's main menu after running the
+// suite from a debug menu, or to hand over to other in-engine tooling:
+//
+//     set_quit_hook(move |exit_code| {
+//         godot_print!("specs finished with {}", exit_code);
+//         menu.clone().show();
+//     });
+//
+// For reacting to the results from GDScript, see TestConfig::embedded and
+// the runner's tests_finished signal.
+pub fn set_quit_hook(hook: impl FnOnce(i32) + 'static) {
+    HOOK.with(|current| *current.borrow_mut() = Some(Box::new(hook)));
+}
+
+pub(crate) fn take_hook() -> Option<Hook> {
+    HOOK.with(|current| current.borrow_mut().take())
+}
@@ -0,0 +1,135 @@
+use crate::state::test_state;
+use crate::TestContext;
+use godot::engine::RandomNumberGenerator;
+use godot::prelude::*;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+lazy_static::lazy_static! {
+    static ref CURRENT_SEED: Mutex<u64> = Mutex::new(0);
+}
+
+// The run's seed: --seed / GODOT_SPECS_SEED when given, otherwise random
+// (and stored in the config so it ends up in the results file).
+pub(crate) fn suite_seed() -> u64 {
+    if let Some(seed) = crate::config::with(|config| config.seed) {
+        return seed;
+    }
+
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64);
+    let seed = splitmix64(seed);
+
+    crate::config::update(|config| config.seed = Some(seed));
+    seed
+}
+
+// Derived from the test's name rather than its position, so a failure
+// reproduces with the same --seed even when running it on its own.
+pub fn test_seed(suite_seed: u64, test_name: &str) -> u64 {
+    // FNV-1a
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in test_name.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    splitmix64(suite_seed ^ hash)
+}
+
+// Called by the runner before a test's first frame. Seeds Godot's global RNG
+// (randi(), randf(), Array.shuffle() ...) and the test's TestRng.
+pub(crate) fn seed_test(test_name: &str) -> u64 {
+    let seed = test_seed(suite_seed(), test_name);
+
+    *CURRENT_SEED.lock().unwrap() = seed;
+    godot::engine::utilities::seed(seed as i64);
+
+    seed
+}
+
+pub fn current_seed() -> u64 {
+    CURRENT_SEED.lock().unwrap().clone()
+}
+
+fn splitmix64(mut state: u64) -> u64 {
+    state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+// A small deterministic RNG (SplitMix64) for test code and for injecting into
+// game code that takes its randomness from the outside.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestRng {
+    state: u64,
+}
+
+impl TestRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        splitmix64(self.state)
+    }
+
+    // In [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+
+    // In [low, high).
+    pub fn range_i64(&mut self, low: i64, high: i64) -> i64 {
+        assert!(low < high, "range_i64: {} is not less than {}", low, high);
+        let span = high.wrapping_sub(low) as u64;
+        low.wrapping_add((self.next_u64() % span) as i64)
+    }
+
+    // In [low, high).
+    pub fn range_f64(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.next_f64()
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            None
+        } else {
+            items.get(self.range_i64(0, items.len() as i64) as usize)
+        }
+    }
+}
+
+impl Default for TestRng {
+    fn default() -> Self {
+        Self::new(current_seed())
+    }
+}
+
+impl TestContext {
+    // The seed this test was given, printed when it fails.
+    pub fn seed(&self) -> u64 {
+        current_seed()
+    }
+
+    // The test's TestRng. It keeps its state across wait!.
+    pub fn with_rng<R>(&self, f: impl FnOnce(&mut TestRng) -> R) -> R {
+        test_state(f)
+    }
+
+    // A RandomNumberGenerator seeded from the test's seed, for game code
+    // that takes one.
+    pub fn godot_rng(&self) -> Gd<RandomNumberGenerator> {
+        let mut rng = RandomNumberGenerator::new_gd();
+        rng.set_seed(current_seed());
+        rng
+    }
+}
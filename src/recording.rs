@@ -0,0 +1,156 @@
+use crate::screenshot::globalize_path;
+use crate::state::test_state;
+use godot::engine::utilities::{str_to_var, var_to_str};
+use godot::engine::InputEvent;
+use godot::prelude::*;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+    static ref RECORDING: Mutex<Vec<RecordedEvent>> = Mutex::new(Vec::new());
+    // Set while input::send_event is dispatching, so the runner's `input`
+    // callback can tell synthetic events from real ones.
+    static ref SENDING: Mutex<bool> = Mutex::new(false);
+}
+
+// An input event sent during a test, `frame` frames after it started. The
+// event is kept in Godot's var_to_str form, which str_to_var turns back into
+// the same InputEvent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedEvent {
+    pub frame: u64,
+    pub event: String,
+}
+
+fn current_frame() -> u64 {
+    crate::CURRENT_TEST_ITERATION.lock().unwrap().clone() as u64
+}
+
+// Called by input::send_event.
+pub(crate) fn record_synthetic(event: &Gd<InputEvent>, send: impl FnOnce()) {
+    if crate::config::with(|config| config.record_input) {
+        push(event);
+    }
+
+    *SENDING.lock().unwrap() = true;
+    send();
+    *SENDING.lock().unwrap() = false;
+}
+
+// Called by the runner for every event it receives.
+pub(crate) fn record_real(event: &Gd<InputEvent>) {
+    if SENDING.lock().unwrap().clone() {
+        return;
+    }
+
+    if crate::config::with(|config| config.record_input && config.record_real_input) {
+        push(event);
+    }
+}
+
+fn push(event: &Gd<InputEvent>) {
+    RECORDING.lock().unwrap().push(RecordedEvent {
+        frame: current_frame(),
+        event: var_to_str(event.to_variant()).to_string(),
+    });
+}
+
+// The events recorded for the current test, taken by the runner before
+// cleanup (which releases held buttons through send_event).
+pub(crate) fn take() -> Vec<RecordedEvent> {
+    std::mem::take(&mut *RECORDING.lock().unwrap())
+}
+
+// Called by the runner between tests.
+pub(crate) fn reset() {
+    RECORDING.lock().unwrap().clear();
+    *SENDING.lock().unwrap() = false;
+}
+
+// Writes a failed test's recording to `<artifacts_dir>/<test>/input.json` and
+// returns that path.
+pub(crate) fn save(test_name: &str, events: &[RecordedEvent]) -> Option<String> {
+    if events.is_empty() {
+        return None;
+    }
+
+    let contents = json!({
+        "test": test_name,
+        "events": events.iter().map(|event| json!({
+            "frame": event.frame,
+            "event": event.event,
+        })).collect::<Vec<_>>(),
+    });
+
+    let path = crate::artifacts::path_for(test_name, "input.json");
+
+    std::fs::write(&path, serde_json::to_string_pretty(&contents).unwrap_or_default()).ok()?;
+    Some(path)
+}
+
+pub fn load(path: &str) -> Vec<RecordedEvent> {
+    let path = globalize_path(path);
+    let contents = std::fs::read_to_string(&path)
+        .unwrap_or_else(|error| panic!("replay_input: could not read {}: {}", path, error));
+    let contents: Value = serde_json::from_str(&contents)
+        .unwrap_or_else(|error| panic!("replay_input: {} is not valid JSON: {}", path, error));
+
+    let events = contents
+        .get("events")
+        .and_then(Value::as_array)
+        .unwrap_or_else(|| panic!("replay_input: {} has no \"events\"", path));
+
+    events
+        .iter()
+        .filter_map(|event| {
+            Some(RecordedEvent {
+                frame: event.get("frame")?.as_u64()?,
+                event: event.get("event")?.as_str()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct Replay {
+    events: Option<VecDeque<RecordedEvent>>,
+    frame: u64,
+}
+
+// Sends this frame's events of the recording at `path`. True while there are
+// events left, see replay_input!.
+pub fn replay_step(path: &str) -> bool {
+    test_state(|replay: &mut Replay| {
+        let frame = replay.frame;
+        let events = replay.events.get_or_insert_with(|| load(path).into());
+
+        while events.front().map_or(false, |event| event.frame <= frame) {
+            let recorded = events.pop_front().unwrap();
+            let event = str_to_var(recorded.event.clone().into())
+                .try_to::<Gd<InputEvent>>()
+                .unwrap_or_else(|_| panic!("replay_input: not an InputEvent: {}", recorded.event));
+            crate::input::send_event(event);
+        }
+
+        replay.frame += 1;
+        !events.is_empty()
+    })
+}
+
+// Re-sends a recording written for a failed test, frame by frame, returning
+// from the test (like wait!) until all of it has been sent:
+//
+//     fn inventory_drag_bug(ctx: &TestContext) {
+//         let inventory = ctx.load_scene::<Control>("res://inventory.tscn");
+//         replay_input!("res://test_artifacts/inventory_drag_bug.input.json");
+//         assert_eq!(inventory.get_child_count(), 3);
+//     }
+#[macro_export]
+macro_rules! replay_input {
+    ($path:expr) => {
+        if $crate::recording::replay_step($path) {
+            $crate::wait!(0.0);
+        }
+    };
+}
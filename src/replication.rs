@@ -0,0 +1,324 @@
+use crate::multiplayer::Peer;
+use godot::engine::global::Error;
+use godot::engine::{
+    IMultiplayerApiExtension, MultiplayerApiExtension, MultiplayerPeer, MultiplayerSpawner,
+    MultiplayerSynchronizer, SceneMultiplayer,
+};
+use godot::prelude::*;
+use std::sync::{Arc, Mutex};
+
+// An RPC a peer sent. `path` is relative to the peer's root, so it's the
+// same on every peer.
+#[derive(Clone, Debug)]
+pub struct RpcCall {
+    pub from: i32,
+    // 0 for everyone, -id for everyone but id.
+    pub to: i32,
+    pub path: String,
+    pub method: String,
+    pub args: Vec<Variant>,
+}
+
+impl RpcCall {
+    // Whether peer `id` is among the receivers.
+    pub fn reaches(&self, id: i32) -> bool {
+        self.to == id || self.to == 0 || (self.to < 0 && self.to != -id)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplicationKind {
+    Spawned,
+    Despawned,
+    Synchronized,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplicationEvent {
+    pub kind: ReplicationKind,
+    // The spawned node, or the MultiplayerSynchronizer, relative to the
+    // peer's root.
+    pub path: String,
+}
+
+pub type ReplicationLog = Arc<Mutex<Vec<ReplicationEvent>>>;
+
+// The MultiplayerAPI the harness gives each peer: a SceneMultiplayer that
+// does the actual work, plus a record of every RPC sent through it.
+#[derive(GodotClass)]
+#[class(init, base=MultiplayerApiExtension)]
+pub struct RpcSpyApi {
+    #[base]
+    base: Base<MultiplayerApiExtension>,
+    inner: Option<Gd<SceneMultiplayer>>,
+    root_path: String,
+    calls: Vec<RpcCall>,
+}
+
+impl RpcSpyApi {
+    pub(crate) fn wrap(inner: Gd<SceneMultiplayer>, root: &Gd<Node>) -> Gd<Self> {
+        let mut spy = Self::new_gd();
+        {
+            let mut bound = spy.bind_mut();
+            bound.inner = Some(inner.clone());
+            bound.root_path = root.get_path().to_string();
+        }
+
+        // Game code listens for these on `multiplayer`, which is the spy.
+        for signal in [
+            "peer_connected",
+            "peer_disconnected",
+            "connected_to_server",
+            "connection_failed",
+            "server_disconnected",
+        ] {
+            // By id, as the callable has to be Send.
+            let target = spy.instance_id();
+            let callable = Callable::from_fn("godot_rust_specs::forward_signal", move |args| {
+                if let Ok(mut target) = Gd::<Object>::try_from_instance_id(target) {
+                    let args: Vec<Variant> = args.iter().map(|arg| (*arg).clone()).collect();
+                    target.emit_signal(signal.into(), &args);
+                }
+                Ok(Variant::nil())
+            });
+            inner.clone().connect(signal.into(), callable.clone());
+            crate::signals::track(&inner.clone().upcast(), signal, callable);
+        }
+
+        spy
+    }
+
+    fn inner(&self) -> Gd<SceneMultiplayer> {
+        self.inner.clone().expect("RpcSpyApi used without RpcSpyApi::wrap")
+    }
+
+    pub fn calls(&self) -> &[RpcCall] {
+        &self.calls
+    }
+
+    pub fn clear(&mut self) {
+        self.calls.clear();
+    }
+
+    fn relative_path(&self, object: &Gd<Object>) -> String {
+        match object.clone().try_cast::<Node>() {
+            Ok(node) if node.is_inside_tree() => {
+                let path = node.get_path().to_string();
+                path.strip_prefix(&format!("{}/", self.root_path)).unwrap_or(&path).to_string()
+            }
+            Ok(node) => node.get_name().to_string(),
+            Err(object) => object.get_class().to_string(),
+        }
+    }
+}
+
+#[godot_api]
+impl IMultiplayerApiExtension for RpcSpyApi {
+    fn poll(&mut self) -> Error {
+        self.inner().poll()
+    }
+
+    fn set_multiplayer_peer(&mut self, multiplayer_peer: Gd<MultiplayerPeer>) {
+        self.inner().set_multiplayer_peer(multiplayer_peer);
+    }
+
+    fn get_multiplayer_peer(&mut self) -> Option<Gd<MultiplayerPeer>> {
+        self.inner().get_multiplayer_peer()
+    }
+
+    fn get_unique_id(&self) -> i32 {
+        self.inner().get_unique_id()
+    }
+
+    fn get_peer_ids(&self) -> PackedInt32Array {
+        self.inner().get_peers()
+    }
+
+    fn rpc(&mut self, peer: i32, object: Gd<Object>, method: StringName, args: VariantArray) -> Error {
+        self.calls.push(RpcCall {
+            from: self.inner().get_unique_id(),
+            to: peer,
+            path: self.relative_path(&object),
+            method: method.to_string(),
+            args: args.iter_shared().collect(),
+        });
+
+        self.inner().rpc_ex(peer, object, method).arguments(args).done()
+    }
+
+    fn get_remote_sender_id(&self) -> i32 {
+        self.inner().get_remote_sender_id()
+    }
+
+    fn object_configuration_add(&mut self, object: Gd<Object>, configuration: Variant) -> Error {
+        self.inner().object_configuration_add(object, configuration)
+    }
+
+    fn object_configuration_remove(&mut self, object: Gd<Object>, configuration: Variant) -> Error {
+        self.inner().object_configuration_remove(object, configuration)
+    }
+}
+
+impl Peer {
+    // RPCs this peer sent.
+    pub fn rpcs_sent(&self) -> Vec<RpcCall> {
+        self.spy.bind().calls().to_vec()
+    }
+
+    // Starts recording spawns, despawns and syncs of the MultiplayerSpawners
+    // and MultiplayerSynchronizers currently under this peer's root. Call it
+    // after adding the scenes that have them.
+    pub fn watch_replication(&self) {
+        let root_path = self.root.get_path().to_string();
+        let mut nodes = Vec::new();
+        descendants(&self.root, &mut nodes);
+
+        for node in nodes {
+            if let Ok(mut spawner) = node.clone().try_cast::<MultiplayerSpawner>() {
+                for (signal, kind) in [
+                    ("spawned", ReplicationKind::Spawned),
+                    ("despawned", ReplicationKind::Despawned),
+                ] {
+                    let log = self.replication.clone();
+                    let root_path = root_path.clone();
+                    let callable = Callable::from_fn("godot_rust_specs::watch_spawner", move |args| {
+                        let path = args
+                            .first()
+                            .and_then(|node| node.try_to::<Gd<Node>>().ok())
+                            .map(|node| relative(&root_path, &node.get_path().to_string()))
+                            .unwrap_or_default();
+                        log.lock().unwrap().push(ReplicationEvent { kind, path });
+                        Ok(Variant::nil())
+                    });
+                    spawner.connect(signal.into(), callable.clone());
+                    crate::signals::track(&spawner.clone().upcast(), signal, callable);
+                }
+            } else if let Ok(mut synchronizer) = node.clone().try_cast::<MultiplayerSynchronizer>() {
+                let log = self.replication.clone();
+                let path = relative(&root_path, &synchronizer.get_path().to_string());
+                let callable = Callable::from_fn("godot_rust_specs::watch_synchronizer", move |_| {
+                    log.lock().unwrap().push(ReplicationEvent {
+                        kind: ReplicationKind::Synchronized,
+                        path: path.clone(),
+                    });
+                    Ok(Variant::nil())
+                });
+                synchronizer.connect("synchronized".into(), callable.clone());
+                crate::signals::track(&synchronizer.clone().upcast(), "synchronized", callable);
+            }
+        }
+    }
+
+    pub fn replication_events(&self) -> Vec<ReplicationEvent> {
+        self.replication.lock().unwrap().clone()
+    }
+}
+
+fn descendants(node: &Gd<Node>, found: &mut Vec<Gd<Node>>) {
+    for child in node.get_children().iter_shared() {
+        found.push(child.clone());
+        descendants(&child, found);
+    }
+}
+
+fn relative(root_path: &str, path: &str) -> String {
+    path.strip_prefix(&format!("{}/", root_path)).unwrap_or(path).to_string()
+}
+
+fn describe_calls(calls: &[RpcCall]) -> String {
+    if calls.is_empty() {
+        return "  (none)".to_string();
+    }
+
+    calls
+        .iter()
+        .map(|call| {
+            let args: Vec<String> = call.args.iter().map(crate::pretty::variant).collect();
+            format!("  {}.{}({}) from {} to {}", call.path, call.method, args.join(", "), call.from, call.to)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Used by assert_rpc_called!. `args` of None matches any arguments.
+#[track_caller]
+pub fn assert_rpc_called(peer: &Peer, method: &str, args: Option<Vec<Variant>>, expected: bool) {
+    let calls = peer.rpcs_sent();
+    let called = calls
+        .iter()
+        .any(|call| call.method == method && args.as_ref().map_or(true, |args| *args == call.args));
+
+    if called != expected {
+        let with = match &args {
+            Some(args) => format!(
+                " with ({})",
+                args.iter().map(crate::pretty::variant).collect::<Vec<_>>().join(", ")
+            ),
+            None => String::new(),
+        };
+
+        panic!(
+            "expected peer {} {}to have sent an RPC to {}{}. RPCs it sent:\n{}",
+            peer.id(),
+            if expected { "" } else { "not " },
+            method,
+            with,
+            describe_calls(&calls)
+        );
+    }
+}
+
+// Used by assert_spawned!.
+#[track_caller]
+pub fn assert_replicated(peer: &Peer, kind: ReplicationKind, path: &str) {
+    let events = peer.replication_events();
+
+    if !events.iter().any(|event| event.kind == kind && event.path == path) {
+        let actual: Vec<String> =
+            events.iter().map(|event| format!("  {:?} {}", event.kind, event.path)).collect();
+
+        panic!(
+            "expected {:?} {} on peer {}. Recorded (see watch_replication):\n{}",
+            kind,
+            path,
+            peer.id(),
+            if actual.is_empty() { "  (none)".to_string() } else { actual.join("\n") }
+        );
+    }
+}
+
+// assert_rpc_called!(net.server, "take_damage") or, checking the arguments,
+// assert_rpc_called!(net.server, "take_damage", [10, "fire"])
+#[macro_export]
+macro_rules! assert_rpc_called {
+    ($peer:expr, $method:expr) => {
+        $crate::replication::assert_rpc_called(&$peer, $method, None, true)
+    };
+    ($peer:expr, $method:expr, [$($arg:expr),* $(,)?]) => {
+        $crate::replication::assert_rpc_called(
+            &$peer,
+            $method,
+            Some(vec![$(godot::prelude::ToGodot::to_variant(&$arg)),*]),
+            true,
+        )
+    };
+}
+
+#[macro_export]
+macro_rules! assert_rpc_not_called {
+    ($peer:expr, $method:expr) => {
+        $crate::replication::assert_rpc_called(&$peer, $method, None, false)
+    };
+}
+
+// assert_spawned!(net.clients[0], "Players/2")
+#[macro_export]
+macro_rules! assert_spawned {
+    ($peer:expr, $path:expr) => {
+        $crate::replication::assert_replicated(
+            &$peer,
+            $crate::replication::ReplicationKind::Spawned,
+            $path,
+        )
+    };
+}
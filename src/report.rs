@@ -0,0 +1,385 @@
+use crate::config::Shard;
+use crate::history::FlakyCandidate;
+use crate::logging::LogRecord;
+use crate::memory::MemoryUsage;
+use crate::output;
+use crate::panics::SourceLocation;
+use crate::screenshot::globalize_path;
+use godot::engine::{Engine, Os};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    // A dot (or F) per test.
+    Progress,
+    // A line with the name of each test.
+    Documentation,
+    // Progress, plus GitHub Actions annotations for failures.
+    Github,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Format> {
+        match name {
+            "progress" => Some(Format::Progress),
+            "documentation" | "doc" => Some(Format::Documentation),
+            "github" => Some(Format::Github),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Progress => "progress",
+            Format::Documentation => "documentation",
+            Format::Github => "github",
+        }
+    }
+}
+
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+pub fn github_annotation(test: &str, message: &str, location: Option<&SourceLocation>) -> String {
+    let message = output::strip_ansi(message);
+    let title = escape_property(&format!("{} failed", test));
+
+    match location {
+        Some(location) => format!(
+            "::error file={},line={},title={}::{}",
+            escape_property(&location.file),
+            location.line,
+            title,
+            escape_data(&message)
+        ),
+        None => format!("::error title={}::{}", title, escape_data(&message)),
+    }
+}
+
+pub(crate) fn report_failure(test: &str, message: &str, location: Option<&SourceLocation>) {
+    if crate::config::with(|config| config.github_annotations) {
+        output::unmuted(|| {
+            output::print_plain(&format!("\n{}", github_annotation(test, message, location)), true)
+        });
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Skipped,
+    Pending,
+    ExpectedFailure,
+}
+
+impl TestStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TestStatus::Passed => "passed",
+            TestStatus::Failed => "failed",
+            TestStatus::Skipped => "skipped",
+            TestStatus::Pending => "pending",
+            TestStatus::ExpectedFailure => "expected_failure",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub status: TestStatus,
+    pub duration: Duration,
+    pub failure: Option<String>,
+    // Where it panicked, if it did.
+    pub location: Option<SourceLocation>,
+    // Where it was registered.
+    pub defined_at: Option<SourceLocation>,
+    pub memory: MemoryUsage,
+    pub seed: u64,
+    pub skip_reason: Option<String>,
+    // What the test recorded with ctx.record.
+    pub metadata: Vec<(String, Value)>,
+    // What it logged with spec_log!, if it failed.
+    pub log: Vec<LogRecord>,
+}
+
+impl TestResult {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "status": self.status.as_str(),
+            "duration_ms": self.duration.as_secs_f64() * 1000.0,
+            "failure": self.failure.as_deref().map(output::strip_ansi),
+            "seed": self.seed,
+            "skip_reason": self.skip_reason,
+            "metadata": self.metadata.iter().cloned().collect::<serde_json::Map<String, Value>>(),
+            "log": self.log.iter().map(|record| json!({
+                "level": record.level.as_str(),
+                "message": record.message,
+                "file": record.file,
+                "line": record.line,
+                "iteration": record.iteration,
+            })).collect::<Vec<_>>(),
+            "location": self.location.as_ref().map(|location| json!({
+                "file": location.file,
+                "line": location.line,
+            })),
+            "defined_at": self.defined_at.as_ref().map(|location| json!({
+                "file": location.file,
+                "line": location.line,
+            })),
+            "memory": {
+                "static_bytes": self.memory.static_bytes,
+                "static_peak_bytes": self.memory.static_peak_bytes,
+                "message_buffer_max_bytes": self.memory.message_buffer_max_bytes,
+            },
+        })
+    }
+}
+
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=+,@%".contains(c));
+
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+// The command line of this run (Godot's own arguments, like the scene) with
+// runner arguments that run exactly `failed` again, in the same order and
+// seed, for the failure summary.
+pub fn rerun_command(failed: &[&str], seed: u64, format: Format) -> String {
+    let mut os = Os::singleton();
+    let mut args = vec![os.get_executable_path().to_string()];
+    args.extend(os.get_cmdline_args().to_vec().iter().map(|arg| arg.to_string()));
+
+    args.push("--".to_string());
+    args.push(format!("--seed={}", seed));
+    args.push(format!("--format={}", format.name()));
+    args.push("--exact".to_string());
+    for name in failed {
+        args.push(format!("--filter={}", name));
+    }
+
+    args.iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>().join(" ")
+}
+
+fn godot_version() -> String {
+    Engine::singleton()
+        .get_version_info()
+        .get("string")
+        .map(|version| version.to_string())
+        .unwrap_or_default()
+}
+
+pub fn results_json(results: &[TestResult]) -> Value {
+    let count = |status: TestStatus| results.iter().filter(|result| result.status == status).count();
+    let duration: Duration = results.iter().map(|result| result.duration).sum();
+
+    json!({
+        "suite": {
+            "godot_version": godot_version(),
+            "seed": crate::config::with(|config| config.seed),
+            "filters": crate::config::with(|config| config.filters.clone()),
+            "modules": crate::config::with(|config| config.modules.clone()),
+            "examples": results.len(),
+            "failures": count(TestStatus::Failed),
+            "skipped": count(TestStatus::Skipped),
+            "pending": count(TestStatus::Pending),
+            "expected_failures": count(TestStatus::ExpectedFailure),
+            "duration_ms": duration.as_secs_f64() * 1000.0,
+            "shard": crate::config::with(|config| config.shard)
+                .map(|shard| format!("{}/{}", shard.index, shard.total)),
+        },
+        "tests": results.iter().map(TestResult::to_json).collect::<Vec<Value>>(),
+    })
+}
+
+// `results.json` becomes `results.shard-2-of-8.json`, so shards running side
+// by side don't overwrite each other's results.
+pub fn shard_results_path(path: &str, shard: Shard) -> String {
+    let suffix = format!(".shard-{}-of-{}", shard.index, shard.total);
+
+    match path.rfind('.') {
+        Some(dot) if !path[dot..].contains('/') && !path[dot..].contains('\\') => {
+            format!("{}{}{}", &path[..dot], suffix, &path[dot..])
+        }
+        _ => format!("{}{}", path, suffix),
+    }
+}
+
+// The configured results file, per shard when sharding.
+pub(crate) fn results_path() -> Option<String> {
+    let (path, shard) = crate::config::with(|config| (config.results_file.clone(), config.shard));
+
+    match shard {
+        Some(shard) => path.map(|path| shard_results_path(&path, shard)),
+        None => path,
+    }
+}
+
+pub(crate) fn write_results_file(results: &[TestResult], flaky: &[FlakyCandidate]) {
+    let Some(path) = results_path() else {
+        return;
+    };
+
+    let path = globalize_path(&path);
+    if let Some(parent) = std::path::Path::new(&path).parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+
+    let mut json = results_json(results);
+    json["flaky_candidates"] = flaky.iter().map(FlakyCandidate::to_json).collect();
+
+    let json = serde_json::to_string_pretty(&json).unwrap_or_default();
+    if let Err(error) = std::fs::write(&path, json) {
+        output::print_plain(&format!("Could not write results to {}: {}", path, error), true);
+    }
+}
+
+// Names of the tests that failed in the previous run, read from the
+// results file.
+pub fn previously_failed_tests() -> Vec<String> {
+    let Some(path) = results_path() else {
+        return Vec::new();
+    };
+
+    let Ok(contents) = std::fs::read_to_string(globalize_path(&path)) else {
+        return Vec::new();
+    };
+
+    let Ok(json) = serde_json::from_str::<Value>(&contents) else {
+        return Vec::new();
+    };
+
+    json["tests"]
+        .as_array()
+        .map(|tests| {
+            tests
+                .iter()
+                .filter(|test| test["status"] == "failed")
+                .filter_map(|test| test["name"].as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Combines the results files of the shards of one run into one.
+pub fn merge_results(shards: &[Value]) -> Value {
+    let mut tests: Vec<Value> = Vec::new();
+    let mut failures = 0;
+    let mut skipped = 0;
+    let mut pending = 0;
+    let mut expected_failures = 0;
+    let mut duration_ms = 0.0;
+
+    for shard in shards {
+        if let Some(shard_tests) = shard["tests"].as_array() {
+            tests.extend(shard_tests.iter().cloned());
+        }
+        failures += shard["suite"]["failures"].as_u64().unwrap_or(0);
+        skipped += shard["suite"]["skipped"].as_u64().unwrap_or(0);
+        pending += shard["suite"]["pending"].as_u64().unwrap_or(0);
+        expected_failures += shard["suite"]["expected_failures"].as_u64().unwrap_or(0);
+        duration_ms += shard["suite"]["duration_ms"].as_f64().unwrap_or(0.0);
+    }
+
+    let first = shards.first().map(|shard| shard["suite"].clone()).unwrap_or(Value::Null);
+
+    json!({
+        "suite": {
+            "godot_version": first["godot_version"],
+            "seed": first["seed"],
+            "filters": first["filters"],
+            "modules": first["modules"],
+            "examples": tests.len(),
+            "failures": failures,
+            "skipped": skipped,
+            "pending": pending,
+            "expected_failures": expected_failures,
+            "duration_ms": duration_ms,
+            "shards": shards.len(),
+        },
+        "tests": tests,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shard(examples: &[&str], failures: u64, duration_ms: f64) -> Value {
+        json!({
+            "suite": {
+                "godot_version": "4.2",
+                "seed": 42,
+                "filters": ["player"],
+                "modules": ["specs::player"],
+                "examples": examples.len(),
+                "failures": failures,
+                "skipped": 1,
+                "pending": 0,
+                "expected_failures": 0,
+                "duration_ms": duration_ms,
+            },
+            "tests": examples.iter().map(|name| json!({ "name": name })).collect::<Vec<_>>(),
+        })
+    }
+
+    #[test]
+    fn merges_shards() {
+        let merged = merge_results(&[shard(&["a", "b"], 1, 10.0), shard(&["c"], 0, 5.0)]);
+
+        assert_eq!(merged["suite"]["examples"], 3);
+        assert_eq!(merged["suite"]["failures"], 1);
+        assert_eq!(merged["suite"]["skipped"], 2);
+        assert_eq!(merged["suite"]["duration_ms"], 15.0);
+        assert_eq!(merged["suite"]["shards"], 2);
+        assert_eq!(merged["suite"]["seed"], 42);
+        assert_eq!(merged["suite"]["filters"], json!(["player"]));
+        assert_eq!(merged["suite"]["modules"], json!(["specs::player"]));
+        assert_eq!(merged["tests"], json!([{ "name": "a" }, { "name": "b" }, { "name": "c" }]));
+    }
+
+    #[test]
+    fn merges_nothing() {
+        let merged = merge_results(&[]);
+
+        assert_eq!(merged["suite"]["examples"], 0);
+        assert_eq!(merged["suite"]["failures"], 0);
+        assert_eq!(merged["suite"]["shards"], 0);
+        assert_eq!(merged["suite"]["seed"], Value::Null);
+        assert_eq!(merged["tests"], json!([]));
+    }
+
+    #[test]
+    fn merges_malformed_shards() {
+        let merged = merge_results(&[json!({}), json!({ "tests": "none", "suite": { "failures": "2" } })]);
+
+        assert_eq!(merged["suite"]["examples"], 0);
+        assert_eq!(merged["suite"]["failures"], 0);
+        assert_eq!(merged["suite"]["shards"], 2);
+    }
+
+    #[test]
+    fn names_shard_results_files() {
+        let shard = || Shard { index: 2, total: 8 };
+
+        assert_eq!(shard_results_path("results.json", shard()), "results.shard-2-of-8.json");
+        assert_eq!(shard_results_path("results", shard()), "results.shard-2-of-8");
+        assert_eq!(shard_results_path("out.d/results", shard()), "out.d/results.shard-2-of-8");
+        assert_eq!(shard_results_path("out.d\\results", shard()), "out.d\\results.shard-2-of-8");
+    }
+}
@@ -0,0 +1,153 @@
+use crate::errors::{captured_stderr, parse_engine_messages};
+use godot::engine::{DirAccess, Resource, ResourceLoader};
+use godot::prelude::*;
+
+// Every file under `dir`, recursively, as res:// paths. Imported and
+// remapped files (as in exported projects) are listed by their original
+// path.
+pub(crate) fn files_in(dir: &str) -> Vec<String> {
+    let mut files = Vec::new();
+    collect_files(dir.trim_end_matches('/'), &mut files);
+    files.sort();
+    files.dedup();
+    files
+}
+
+fn collect_files(dir: &str, files: &mut Vec<String>) {
+    let Some(access) = DirAccess::open(dir.into()) else {
+        panic!("{} can't be opened", dir);
+    };
+
+    for file in access.get_files().to_vec() {
+        let file = file.to_string();
+        let file = file
+            .strip_suffix(".import")
+            .or_else(|| file.strip_suffix(".remap"))
+            .unwrap_or(&file);
+        files.push(format!("{}/{}", dir, file));
+    }
+
+    for sub_dir in access.get_directories().to_vec() {
+        collect_files(&format!("{}/{}", dir, sub_dir), files);
+    }
+}
+
+// The engine errors and warnings logged since `start` (an offset into
+// captured_stderr), one per line after a leading newline.
+pub(crate) fn engine_errors_since(start: usize) -> String {
+    let stderr = captured_stderr();
+    let messages = parse_engine_messages(stderr.get(start..).unwrap_or(""));
+
+    if messages.is_empty() {
+        return String::new();
+    }
+
+    let texts: Vec<String> = messages.into_iter().map(|message| message.text).collect();
+    format!("\n{}", texts.join("\n"))
+}
+
+// Loads `path` as a `T`, or explains why it doesn't, with the engine errors
+// logged while loading.
+pub fn load_resource<T>(path: &str) -> Result<Gd<T>, String>
+where
+    T: GodotClass + Inherits<Resource>,
+{
+    let mut loader = ResourceLoader::singleton();
+
+    if !loader.exists(path.into()) {
+        return Err(format!("no resource exists at {}", path));
+    }
+
+    let errors_from = captured_stderr().len();
+
+    let Some(resource) = loader.load(path.into()) else {
+        return Err(format!("{} failed to load{}", path, engine_errors_since(errors_from)));
+    };
+
+    let errors = engine_errors_since(errors_from);
+    if !errors.is_empty() {
+        return Err(format!("{} loaded with errors{}", path, errors));
+    }
+
+    resource
+        .try_cast::<T>()
+        .map_err(|resource| format!("{} is a {}, not a {}", path, resource.get_class(), T::class_name()))
+}
+
+#[track_caller]
+pub fn assert_resource_loads<T>(path: &str) -> Gd<T>
+where
+    T: GodotClass + Inherits<Resource>,
+{
+    load_resource::<T>(path).unwrap_or_else(|error| panic!("expected a loadable resource: {}", error))
+}
+
+// Loads every resource under `dir` whose extension is one of `extensions`
+// (or every one the engine can load, when empty) and fails listing all that
+// don't load cleanly.
+#[track_caller]
+pub fn assert_resources_load(dir: &str, extensions: &[&str]) {
+    let mut loader = ResourceLoader::singleton();
+    let recognized: Vec<String> = loader
+        .get_recognized_extensions_for_type("Resource".into())
+        .to_vec()
+        .iter()
+        .map(|extension| extension.to_string())
+        .collect();
+
+    let mut checked = 0;
+    let mut failures = Vec::new();
+
+    for path in files_in(dir) {
+        let extension = path.rsplit('.').next().unwrap_or("").to_string();
+
+        let wanted = if extensions.is_empty() {
+            recognized.contains(&extension)
+        } else {
+            extensions.contains(&extension.as_str())
+        };
+
+        if !wanted {
+            continue;
+        }
+
+        checked += 1;
+        if let Err(error) = load_resource::<Resource>(&path) {
+            failures.push(error);
+        }
+    }
+
+    if !failures.is_empty() {
+        panic!(
+            "{} of {} resources under {} don't load:\n{}",
+            failures.len(),
+            checked,
+            dir,
+            failures.join("\n")
+        );
+    }
+}
+
+// assert_resource_loads!("res://player.tscn") or, checking the type,
+// let texture = assert_resource_loads!("res://icon.svg", Texture2D);
+#[macro_export]
+macro_rules! assert_resource_loads {
+    ($path:expr) => {
+        $crate::resources::assert_resource_loads::<godot::engine::Resource>($path)
+    };
+    ($path:expr, $class:ty) => {
+        $crate::resources::assert_resource_loads::<$class>($path)
+    };
+}
+
+// assert_resources_load!("res://levels") or, for some types only,
+// assert_resources_load!("res://levels", ["tscn", "tres"])
+#[macro_export]
+macro_rules! assert_resources_load {
+    ($dir:expr) => {
+        $crate::resources::assert_resources_load($dir, &[])
+    };
+    ($dir:expr, [$($extension:expr),* $(,)?]) => {
+        $crate::resources::assert_resources_load($dir, &[$($extension),*])
+    };
+}
@@ -0,0 +1,114 @@
+use crate::panics::panic_message;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use tokio::runtime::Runtime;
+use tokio::task::{AbortHandle, JoinHandle};
+
+lazy_static::lazy_static! {
+    // Shared by the whole run. Tasks run on its worker threads, so the engine
+    // keeps processing frames while they're in flight.
+    static ref RUNTIME: Runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_name("godot_rust_specs-tokio")
+        .build()
+        .expect("failed to start the tokio runtime");
+    // Panics of tasks the test never awaited, reported against the test when
+    // it finishes.
+    static ref TASK_PANICS: Mutex<Vec<(u64, String)>> = Mutex::new(Vec::new());
+    // Aborted when the test that spawned them finishes.
+    static ref TEST_TASKS: Mutex<Vec<AbortHandle>> = Mutex::new(Vec::new());
+}
+
+static NEXT_TASK_ID: AtomicU64 = AtomicU64::new(0);
+
+pub fn handle() -> tokio::runtime::Handle {
+    RUNTIME.handle().clone()
+}
+
+// Runs `future` on the runtime. Await the returned Task from an async test
+// (see executor) to get its output across frames without blocking the
+// engine; if the task panicked, awaiting it panics with the same message.
+// Tasks still running when the test finishes are aborted.
+pub fn spawn<F>(future: F) -> Task<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let id = NEXT_TASK_ID.fetch_add(1, Ordering::SeqCst);
+
+    let handle = RUNTIME.spawn(CatchPanic { id, future: Box::pin(future) });
+    TEST_TASKS.lock().unwrap().push(handle.abort_handle());
+
+    Task { id, handle }
+}
+
+struct CatchPanic<F: Future> {
+    id: u64,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for CatchPanic<F> {
+    type Output = Result<F::Output, String>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let id = self.id;
+
+        match panic::catch_unwind(AssertUnwindSafe(|| self.future.as_mut().poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(error) => {
+                let message = panic_message(&error);
+                TASK_PANICS.lock().unwrap().push((id, message.clone()));
+                Poll::Ready(Err(message))
+            }
+        }
+    }
+}
+
+pub struct Task<T> {
+    id: u64,
+    handle: JoinHandle<Result<T, String>>,
+}
+
+impl<T> Task<T> {
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+}
+
+impl<T> Future for Task<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        match Pin::new(&mut self.handle).poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(Ok(output))) => Poll::Ready(output),
+            Poll::Ready(Ok(Err(message))) => {
+                let id = self.id;
+                // Reported here, so not again when the test finishes.
+                TASK_PANICS.lock().unwrap().retain(|(task, _)| *task != id);
+                panic!("task spawned on the tokio runtime panicked: {}", message)
+            }
+            Poll::Ready(Err(error)) => panic!("task spawned on the tokio runtime failed: {}", error),
+        }
+    }
+}
+
+// Called by the runner when a test finishes: aborts what it left running and
+// returns the panic messages of tasks nobody awaited.
+pub(crate) fn finish_test() -> Vec<String> {
+    for task in TEST_TASKS.lock().unwrap().drain(..) {
+        task.abort();
+    }
+
+    TASK_PANICS
+        .lock()
+        .unwrap()
+        .drain(..)
+        .map(|(_, message)| message)
+        .collect()
+}
@@ -0,0 +1,77 @@
+use crate::screenshot::{globalize_path, sanitize_file_name};
+use crate::TestContext;
+use std::path::Path;
+use std::sync::Mutex;
+
+const SANDBOX_ROOT: &str = "user://godot_rust_specs/sandbox";
+
+lazy_static::lazy_static! {
+    // Sandboxes handed out during the current test, deleted when it finishes.
+    static ref CREATED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+}
+
+// A user:// directory of the test's own, so save/load specs don't see each
+// other's files or the developer's real user data. Point the game's save
+// path at `ctx.user_path(..)`; the directory is created on first use and
+// deleted (with everything in it) when the test finishes.
+impl TestContext {
+    // `user://godot_rust_specs/sandbox/<test>`
+    pub fn user_dir(&self) -> String {
+        let dir = format!("{}/{}", SANDBOX_ROOT, sanitize_file_name(self.name()));
+
+        let mut created = CREATED.lock().unwrap();
+        if !created.contains(&dir) {
+            // Leftovers of an earlier run that didn't get to clean up.
+            let _ = std::fs::remove_dir_all(globalize_path(&dir));
+            created.push(dir.clone());
+        }
+        drop(created);
+
+        std::fs::create_dir_all(globalize_path(&dir))
+            .unwrap_or_else(|error| panic!("failed to create the test's user dir {}: {}", dir, error));
+
+        dir
+    }
+
+    // A user:// path inside the test's sandbox. Parent directories are
+    // created so the game can write to it straight away.
+    pub fn user_path(&self, relative: &str) -> String {
+        let path = format!("{}/{}", self.user_dir(), relative.trim_start_matches('/'));
+
+        if let Some(parent) = Path::new(&globalize_path(&path)).parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+
+        path
+    }
+
+    // Pre-populates a file in the sandbox and returns its user:// path.
+    pub fn write_user_file(&self, relative: &str, contents: impl AsRef<[u8]>) -> String {
+        let path = self.user_path(relative);
+
+        std::fs::write(globalize_path(&path), contents)
+            .unwrap_or_else(|error| panic!("failed to write {}: {}", path, error));
+
+        path
+    }
+
+    // Copies a fixture (usually a res:// file) into the sandbox and returns
+    // its user:// path.
+    pub fn copy_fixture(&self, from: &str, relative: &str) -> String {
+        let path = self.user_path(relative);
+
+        std::fs::copy(globalize_path(from), globalize_path(&path))
+            .unwrap_or_else(|error| panic!("failed to copy {} to {}: {}", from, path, error));
+
+        path
+    }
+}
+
+// Called by the runner once a test has finished.
+pub(crate) fn reset() {
+    let created: Vec<String> = CREATED.lock().unwrap().drain(..).collect();
+
+    for dir in created {
+        let _ = std::fs::remove_dir_all(globalize_path(&dir));
+    }
+}
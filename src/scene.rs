@@ -0,0 +1,142 @@
+use godot::engine::global::Error;
+use godot::engine::{PackedScene, ResourceLoader, ResourceSaver};
+use godot::prelude::*;
+
+pub trait SceneFixtures {
+    // Loads the PackedScene at `path`, instantiates it as `T` and adds it as
+    // a child of the test root, panicking with a specific message at
+    // whichever step fails.
+    fn load_scene<T>(&self, path: &str) -> Gd<T>
+    where
+        T: GodotClass + Inherits<Node>;
+}
+
+impl SceneFixtures for Gd<Node> {
+    fn load_scene<T>(&self, path: &str) -> Gd<T>
+    where
+        T: GodotClass + Inherits<Node>,
+    {
+        let mut loader = ResourceLoader::singleton();
+
+        if !loader.exists(path.into()) {
+            panic!("load_scene: no resource exists at {}", path);
+        }
+
+        let resource = loader.load(path.into()).unwrap_or_else(|| {
+            panic!(
+                "load_scene: {} exists but failed to load (see the engine errors above)",
+                path
+            )
+        });
+
+        let scene = resource.try_cast::<PackedScene>().unwrap_or_else(|resource| {
+            panic!(
+                "load_scene: {} is a {}, not a PackedScene",
+                path,
+                resource.get_class()
+            )
+        });
+
+        let node = instantiate_as::<T>(&scene);
+
+        self.clone().add_child(node.clone().upcast());
+
+        node
+    }
+}
+
+// `PackedScene::instantiate` + `try_cast`, panicking with the scene path, the
+// expected Rust type and the actual Godot class instead of an opaque cast
+// failure.
+pub fn instantiate_as<T>(scene: &Gd<PackedScene>) -> Gd<T>
+where
+    T: GodotClass + Inherits<Node>,
+{
+    let path = scene_path(scene);
+
+    let node = scene
+        .instantiate()
+        .unwrap_or_else(|| panic!("instantiate_as: {} failed to instantiate", path));
+
+    node.try_cast::<T>().unwrap_or_else(|node| {
+        let class = node.get_class();
+        node.free();
+        panic!(
+            "instantiate_as: the root of {} is a {}, expected {} ({})",
+            path,
+            class,
+            T::class_name(),
+            std::any::type_name::<T>()
+        )
+    })
+}
+
+fn scene_path(scene: &Gd<PackedScene>) -> String {
+    let path = scene.get_path().to_string();
+
+    if path.is_empty() {
+        "<unsaved PackedScene>".to_string()
+    } else {
+        path
+    }
+}
+
+// Packs `root` and everything under it into a scene file. Packing only keeps
+// nodes owned by the root (or by an instanced scene inside it), so nodes
+// added from code are given the root as owner for the duration.
+pub fn save_subtree(root: &Gd<Node>, path: &str) -> Result<(), String> {
+    let mut reowned = Vec::new();
+    reown(root, root, &mut reowned);
+
+    let mut scene = PackedScene::new_gd();
+    let packed = scene.pack(root.clone());
+
+    // Nodes that had no owner keep the root, which only matters for packing.
+    for (mut node, owner) in reowned {
+        if let Some(owner) = owner {
+            node.set_owner(owner);
+        }
+    }
+
+    if packed != Error::OK {
+        return Err(format!("packing {} failed with {:?}", root.get_path(), packed));
+    }
+
+    let saved = ResourceSaver::singleton().save(scene.upcast(), path.into());
+    if saved != Error::OK {
+        return Err(format!("saving {} failed with {:?}", path, saved));
+    }
+
+    Ok(())
+}
+
+fn reown(root: &Gd<Node>, node: &Gd<Node>, reowned: &mut Vec<(Gd<Node>, Option<Gd<Node>>)>) {
+    for mut child in node.get_children().iter_shared() {
+        let owner = child.get_owner();
+        let owned_inside = owner
+            .as_ref()
+            .map_or(false, |owner| owner == root || root.is_ancestor_of(owner.clone()));
+
+        if !owned_inside {
+            child.set_owner(root.clone());
+            reowned.push((child.clone(), owner));
+        }
+
+        reown(root, &child, reowned);
+    }
+}
+
+// Called by the runner for a failed test, before its root is freed. Saves
+// the test's tree as `<artifacts_dir>/<test>/scene.tscn`, to open in the
+// editor.
+pub(crate) fn save_failure_scene(root: &Gd<Node>, test_name: &str) -> Option<String> {
+    let path = crate::artifacts::path_for(test_name, "scene.tscn");
+
+    match save_subtree(root, &path) {
+        Ok(()) => Some(path),
+        Err(error) => {
+            crate::output::print_plain(&format!("No scene saved for {}: {}", test_name, error), true);
+            None
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::TestCase;
+use std::collections::{HashSet, VecDeque};
+
+// Adds back (at their registration position) the tests that `selected`
+// depends on, directly or not, when filtering left them out.
+pub(crate) fn with_prerequisites(
+    registered: &[TestCase],
+    selected: VecDeque<TestCase>,
+) -> VecDeque<TestCase> {
+    let mut wanted: HashSet<String> = selected.iter().map(|test| test.name.clone()).collect();
+    let mut pending: Vec<String> = selected.iter().flat_map(|test| test.depends_on.clone()).collect();
+    let mut added = false;
+
+    while let Some(name) = pending.pop() {
+        if wanted.contains(&name) {
+            continue;
+        }
+        if let Some(test) = registered.iter().find(|test| test.name == name) {
+            pending.extend(test.depends_on.iter().cloned());
+            wanted.insert(name);
+            added = true;
+        }
+    }
+
+    if !added {
+        return selected;
+    }
+
+    registered.iter().filter(|test| wanted.contains(&test.name)).cloned().collect()
+}
+
+// Higher priorities first, registration order among equals, but never a
+// test before the tests it depends on. Dependencies that aren't part of the
+// run (e.g. in another shard) are ignored. Fails on a dependency cycle or an
+// unknown test name.
+pub(crate) fn order(
+    registered: &[TestCase],
+    tests: VecDeque<TestCase>,
+) -> Result<VecDeque<TestCase>, String> {
+    for test in &tests {
+        for dependency in &test.depends_on {
+            if !registered.iter().any(|registered| registered.name == *dependency) {
+                return Err(format!("{} depends on {:?}, but no test has that name", test.name, dependency));
+            }
+        }
+    }
+
+    if tests.iter().all(|test| test.priority == 0 && test.depends_on.is_empty()) {
+        return Ok(tests);
+    }
+
+    let mut remaining: Vec<TestCase> = tests.into_iter().collect();
+    // Stable, so registration order is kept among equal priorities.
+    remaining.sort_by(|a, b| b.priority.cmp(&a.priority));
+
+    let in_run: HashSet<String> = remaining.iter().map(|test| test.name.clone()).collect();
+    let mut done: HashSet<String> = HashSet::new();
+    let mut ordered = VecDeque::with_capacity(remaining.len());
+
+    while !remaining.is_empty() {
+        let ready = remaining.iter().position(|test| {
+            test.depends_on
+                .iter()
+                .all(|dependency| done.contains(dependency) || !in_run.contains(dependency))
+        });
+
+        let Some(ready) = ready else {
+            let cycle = cycle(&remaining);
+            return Err(format!("these tests depend on each other in a cycle: {}", cycle.join(" -> ")));
+        };
+
+        let test = remaining.remove(ready);
+        done.insert(test.name.clone());
+        ordered.push_back(test);
+    }
+
+    Ok(ordered)
+}
+
+// One cycle among `remaining`, every one of which still waits for another
+// of them, e.g. ["a", "b", "a"].
+fn cycle(remaining: &[TestCase]) -> Vec<String> {
+    let mut path: Vec<&str> = Vec::new();
+    let mut current = &remaining[0];
+
+    loop {
+        if let Some(start) = path.iter().position(|name| *name == current.name) {
+            let mut cycle: Vec<String> = path[start..].iter().map(|name| name.to_string()).collect();
+            cycle.push(current.name.clone());
+            return cycle;
+        }
+
+        path.push(&current.name);
+        current = current
+            .depends_on
+            .iter()
+            .find_map(|dependency| remaining.iter().find(|test| test.name == *dependency))
+            .expect("every remaining test waits for another remaining one");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(name: &str) -> TestCase {
+        TestCase::new(name, |_| {})
+    }
+
+    fn names(tests: VecDeque<TestCase>) -> Vec<String> {
+        tests.into_iter().map(|test| test.name).collect()
+    }
+
+    fn ordered(tests: &[TestCase]) -> Result<Vec<String>, String> {
+        order(tests, tests.iter().cloned().collect()).map(names)
+    }
+
+    #[test]
+    fn keeps_registration_order_among_equal_priorities() {
+        let tests = [case("a"), case("b").priority(1), case("c"), case("d").priority(1), case("e").priority(-1)];
+
+        assert_eq!(ordered(&tests).unwrap(), vec!["b", "d", "a", "c", "e"]);
+    }
+
+    #[test]
+    fn runs_dependencies_first() {
+        let tests = [case("a").depends_on("b"), case("b").depends_on("c"), case("c")];
+        assert_eq!(ordered(&tests).unwrap(), vec!["c", "b", "a"]);
+
+        let tests = [case("a").priority(5).depends_on("c"), case("b"), case("c")];
+        assert_eq!(ordered(&tests).unwrap(), vec!["b", "c", "a"]);
+    }
+
+    #[test]
+    fn ignores_dependencies_outside_the_run() {
+        let registered = [case("a"), case("b").depends_on("a")];
+
+        assert_eq!(names(order(&registered, [registered[1].clone()].into()).unwrap()), vec!["b"]);
+    }
+
+    #[test]
+    fn rejects_unknown_dependencies() {
+        assert_eq!(
+            ordered(&[case("a"), case("b").depends_on("missing")]),
+            Err("b depends on \"missing\", but no test has that name".to_string())
+        );
+    }
+
+    #[test]
+    fn names_the_cycle() {
+        let tests = [case("waits").depends_on("a"), case("a").depends_on("b"), case("b").depends_on("a"), case("c")];
+        assert_eq!(ordered(&tests), Err("these tests depend on each other in a cycle: a -> b -> a".to_string()));
+
+        let tests = [case("a").depends_on("a")];
+        assert_eq!(ordered(&tests), Err("these tests depend on each other in a cycle: a -> a".to_string()));
+    }
+
+    #[test]
+    fn adds_back_prerequisites() {
+        let registered = [case("a"), case("b").depends_on("a"), case("c").depends_on("b"), case("d")];
+
+        assert_eq!(names(with_prerequisites(&registered, [registered[2].clone()].into())), vec!["a", "b", "c"]);
+        assert_eq!(names(with_prerequisites(&registered, [registered[3].clone()].into())), vec!["d"]);
+    }
+}
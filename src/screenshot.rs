@@ -0,0 +1,181 @@
+use godot::engine::global::Error;
+use godot::engine::image::Format;
+use godot::engine::{Engine, Image, ProjectSettings, Viewport};
+use godot::prelude::*;
+
+// Turns a test name into something safe to use as a file name.
+pub fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
+
+// Resolves res:// and user:// paths to a real filesystem path.
+pub fn globalize_path(path: &str) -> String {
+    ProjectSettings::singleton()
+        .globalize_path(path.into())
+        .to_string()
+}
+
+// Saves the viewport's last rendered frame as
+// `<artifacts_dir>/<test>/screenshot.png`.
+// Returns None (after saying why) when there is nothing to capture, e.g.
+// when running headless.
+pub fn capture(viewport: Gd<Viewport>, test_name: &str) -> Option<String> {
+    let Some(image) = viewport.get_texture().and_then(|texture| texture.get_image()) else {
+        crate::output::print_plain("No screenshot taken: the viewport has no rendered image (headless?)", true);
+        return None;
+    };
+
+    let path = crate::artifacts::path_for(test_name, "screenshot.png");
+    let result = image.save_png(path.clone().into());
+
+    if result != Error::OK {
+        crate::output::print_plain(
+            &format!("No screenshot taken: saving {} failed with {:?}", path, result),
+            true,
+        );
+        return None;
+    }
+
+    Some(path)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScreenshotTolerance {
+    // How far (0-255) any channel of a pixel may be off before the pixel
+    // counts as different.
+    pub per_channel: u8,
+    // Fraction (0.0-1.0) of pixels that may differ before the comparison
+    // fails.
+    pub max_differing_ratio: f64,
+}
+
+impl Default for ScreenshotTolerance {
+    fn default() -> Self {
+        Self {
+            per_channel: 4,
+            max_differing_ratio: 0.001,
+        }
+    }
+}
+
+fn root_viewport() -> Gd<Viewport> {
+    let tree = Engine::singleton()
+        .get_main_loop()
+        .and_then(|main_loop| main_loop.try_cast::<SceneTree>().ok())
+        .expect("assert_matches_screenshot: no SceneTree is running");
+
+    tree.get_root()
+        .expect("assert_matches_screenshot: the SceneTree has no root window")
+        .upcast()
+}
+
+fn rgba_image(image: &mut Gd<Image>) -> (i32, i32, Vec<u8>) {
+    image.convert(Format::RGBA8);
+    (image.get_width(), image.get_height(), image.get_data().to_vec())
+}
+
+fn save_artifact(image: &Gd<Image>, name: &str, suffix: &str) -> String {
+    let path = crate::artifacts::current_path(&format!("{}.{}.png", sanitize_file_name(name), suffix));
+    image.save_png(path.clone().into());
+    path
+}
+
+// Compares the current frame against `<screenshot_baseline_dir>/<name>.png`.
+// On mismatch the actual frame and a diff image (differing pixels in red)
+// are written to the artifacts directory. With update_snapshots set the
+// baseline is (re)written instead.
+#[track_caller]
+pub fn assert_matches_screenshot(name: &str) {
+    let mut actual = root_viewport()
+        .get_texture()
+        .and_then(|texture| texture.get_image())
+        .unwrap_or_else(|| {
+            panic!(
+                "assert_matches_screenshot: no rendered image to compare for {} (headless?)",
+                name
+            )
+        });
+
+    let baseline_dir =
+        globalize_path(&crate::config::with(|config| config.screenshot_baseline_dir.clone()));
+    let baseline_path = format!("{}/{}.png", baseline_dir, sanitize_file_name(name));
+
+    if crate::config::with(|config| config.update_snapshots) {
+        let _ = std::fs::create_dir_all(&baseline_dir);
+        actual.save_png(baseline_path.into());
+        return;
+    }
+
+    let Some(mut expected) = Image::load_from_file(baseline_path.clone().into()) else {
+        let actual_path = save_artifact(&actual, name, "actual");
+        panic!(
+            "assert_matches_screenshot: no baseline at {} (actual frame saved to {}, enable update_snapshots to record it)",
+            baseline_path, actual_path
+        );
+    };
+
+    let (width, height, actual_data) = rgba_image(&mut actual);
+    let (expected_width, expected_height, expected_data) = rgba_image(&mut expected);
+
+    if (width, height) != (expected_width, expected_height) {
+        let actual_path = save_artifact(&actual, name, "actual");
+        panic!(
+            "assert_matches_screenshot: {} is {}x{} but the baseline is {}x{} (actual frame saved to {})",
+            name, width, height, expected_width, expected_height, actual_path
+        );
+    }
+
+    let tolerance = crate::config::with(|config| config.screenshot_tolerance);
+    let mut diff_data = Vec::with_capacity(actual_data.len());
+    let mut differing = 0;
+
+    for (actual_pixel, expected_pixel) in actual_data.chunks(4).zip(expected_data.chunks(4)) {
+        let differs = actual_pixel
+            .iter()
+            .zip(expected_pixel)
+            .any(|(a, e)| a.abs_diff(*e) > tolerance.per_channel);
+
+        if differs {
+            differing += 1;
+            diff_data.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            diff_data.extend(actual_pixel[..3].iter().map(|channel| channel / 3));
+            diff_data.push(255);
+        }
+    }
+
+    let total = (width as usize * height as usize).max(1);
+    let ratio = differing as f64 / total as f64;
+
+    if ratio > tolerance.max_differing_ratio {
+        let actual_path = save_artifact(&actual, name, "actual");
+        let diff = Image::create_from_data(
+            width,
+            height,
+            false,
+            Format::RGBA8,
+            PackedByteArray::from(&diff_data[..]),
+        );
+        let diff_path = diff.map(|diff| save_artifact(&diff, name, "diff"));
+
+        panic!(
+            "assert_matches_screenshot: {} differs from its baseline in {} pixels ({:.3}%, allowed {:.3}%)\n  baseline: {}\n  actual: {}\n  diff: {}",
+            name,
+            differing,
+            ratio * 100.0,
+            tolerance.max_differing_ratio * 100.0,
+            baseline_path,
+            actual_path,
+            diff_path.unwrap_or_else(|| "<could not be created>".to_string())
+        );
+    }
+}
+
+#[macro_export]
+macro_rules! assert_matches_screenshot {
+    ($name:expr) => {
+        $crate::screenshot::assert_matches_screenshot($name)
+    };
+}
@@ -0,0 +1,78 @@
+use crate::errors::captured_stderr;
+use crate::resources::{engine_errors_since, files_in};
+use godot::engine::{ResourceLoader, Shader, ShaderMaterial};
+use godot::prelude::*;
+
+// Loads every shader (.gdshader, and Shader or ShaderMaterial .tres/.res/
+// .material files) under `dir` and fails listing the ones that don't
+// compile, with the compiler's errors.
+//
+// Shaders are compiled by the renderer, so this skips the test when running
+// headless, where the dummy renderer accepts anything.
+#[track_caller]
+pub fn assert_shaders_compile(dir: &str) {
+    if crate::skip::is_headless() {
+        crate::skip::skip("compiling shaders needs a renderer, but running headless");
+    }
+
+    let mut loader = ResourceLoader::singleton();
+    let mut checked = 0;
+    let mut failures = Vec::new();
+
+    for path in files_in(dir) {
+        let extension = path.rsplit('.').next().unwrap_or("");
+        if !["gdshader", "tres", "res", "material"].contains(&extension) {
+            continue;
+        }
+
+        let errors_from = captured_stderr().len();
+
+        let Some(resource) = loader.load(path.as_str().into()) else {
+            if extension == "gdshader" {
+                checked += 1;
+                failures.push(format!("{} failed to load{}", path, engine_errors_since(errors_from)));
+            }
+            continue;
+        };
+
+        let shader = match resource.try_cast::<Shader>() {
+            Ok(shader) => Some(shader),
+            Err(resource) => resource
+                .try_cast::<ShaderMaterial>()
+                .ok()
+                .and_then(|material| material.get_shader()),
+        };
+
+        let Some(shader) = shader else {
+            continue;
+        };
+
+        checked += 1;
+
+        // Asking for the uniforms makes the renderer compile the code, if
+        // loading it didn't already.
+        let _ = shader.get_shader_uniform_list();
+
+        let errors = engine_errors_since(errors_from);
+        if !errors.is_empty() {
+            failures.push(format!("{}:{}", path, errors));
+        }
+    }
+
+    if !failures.is_empty() {
+        panic!(
+            "{} of {} shaders under {} don't compile:\n{}",
+            failures.len(),
+            checked,
+            dir,
+            failures.join("\n")
+        );
+    }
+}
+
+#[macro_export]
+macro_rules! assert_shaders_compile {
+    ($dir:expr) => {
+        $crate::shaders::assert_shaders_compile($dir)
+    };
+}
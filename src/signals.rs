@@ -0,0 +1,182 @@
+use crate::TestContext;
+use godot::prelude::*;
+use std::cell::RefCell;
+
+struct Connection {
+    source: Gd<Object>,
+    signal: StringName,
+    callable: Callable,
+}
+
+thread_local! {
+    // Connections made through connect() (and the framework's own helpers)
+    // during the current test.
+    static CONNECTIONS: RefCell<Vec<Connection>> = RefCell::new(Vec::new());
+}
+
+// What a signal is expected to be connected to.
+pub enum Handler {
+    Method(Gd<Object>, String),
+    Callable(Callable),
+}
+
+impl Handler {
+    fn matches(&self, callable: &Callable) -> bool {
+        match self {
+            Handler::Method(target, method) => {
+                callable.object().map(|object| object.instance_id()) == Some(target.instance_id())
+                    && callable.method_name().map(|name| name.to_string()).as_deref() == Some(method.as_str())
+            }
+            Handler::Callable(expected) => callable == expected,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Handler::Method(target, method) => format!("{}.{}", describe_object(target), method),
+            Handler::Callable(callable) => describe_callable(callable),
+        }
+    }
+}
+
+fn describe_object(object: &Gd<Object>) -> String {
+    match object.clone().try_cast::<Node>() {
+        Ok(node) if node.is_inside_tree() => format!("{} ({})", node.get_path(), node.get_class()),
+        Ok(node) => format!("{} ({})", node.get_name(), node.get_class()),
+        Err(object) => format!("{}#{}", object.get_class(), object.instance_id()),
+    }
+}
+
+fn describe_callable(callable: &Callable) -> String {
+    match (callable.object(), callable.method_name()) {
+        (Some(object), Some(method)) => format!("{}.{}", describe_object(&object), method),
+        _ => callable.to_string(),
+    }
+}
+
+fn connections(object: &Gd<Object>, signal: &str) -> Vec<Callable> {
+    if !object.has_signal(signal.into()) {
+        panic!("{} has no signal named {:?}", describe_object(object), signal);
+    }
+
+    object
+        .get_signal_connection_list(signal.into())
+        .iter_shared()
+        .filter_map(|connection| connection.get("callable"))
+        .filter_map(|callable| callable.try_to::<Callable>().ok())
+        .collect()
+}
+
+#[track_caller]
+pub fn assert_connected(object: &Gd<Object>, signal: &str, handler: Handler, expected: bool) {
+    let connections = connections(object, signal);
+    let connected = connections.iter().any(|callable| handler.matches(callable));
+
+    if connected != expected {
+        let actual: Vec<String> = connections.iter().map(describe_callable).collect();
+
+        panic!(
+            "expected {}'s {:?} signal {}to be connected to {}. Its connections: {}",
+            describe_object(object),
+            signal,
+            if expected { "" } else { "not " },
+            handler.describe(),
+            if actual.is_empty() { "(none)".to_string() } else { actual.join(", ") }
+        );
+    }
+}
+
+// assert_connected!(button, "pressed", menu, "on_start_pressed")
+// or, with a Callable, assert_connected!(button, "pressed", callable)
+#[macro_export]
+macro_rules! assert_connected {
+    ($object:expr, $signal:expr, $target:expr, $method:expr) => {
+        $crate::signals::assert_connected(
+            &$object.clone().upcast(),
+            $signal,
+            $crate::signals::Handler::Method($target.clone().upcast(), ($method).to_string()),
+            true,
+        )
+    };
+    ($object:expr, $signal:expr, $callable:expr) => {
+        $crate::signals::assert_connected(
+            &$object.clone().upcast(),
+            $signal,
+            $crate::signals::Handler::Callable($callable.clone()),
+            true,
+        )
+    };
+}
+
+#[macro_export]
+macro_rules! assert_not_connected {
+    ($object:expr, $signal:expr, $target:expr, $method:expr) => {
+        $crate::signals::assert_connected(
+            &$object.clone().upcast(),
+            $signal,
+            $crate::signals::Handler::Method($target.clone().upcast(), ($method).to_string()),
+            false,
+        )
+    };
+    ($object:expr, $signal:expr, $callable:expr) => {
+        $crate::signals::assert_connected(
+            &$object.clone().upcast(),
+            $signal,
+            $crate::signals::Handler::Callable($callable.clone()),
+            false,
+        )
+    };
+}
+
+// Connects `callable` to `signal` for the rest of the test only. Unlike a
+// plain connect, this doesn't stay behind when the source outlives the test,
+// e.g. an autoload's signal, where the next test would otherwise get calls
+// from a handler whose test is gone.
+pub fn connect(source: &Gd<Object>, signal: &str, callable: Callable) {
+    if !source.has_signal(signal.into()) {
+        panic!("{} has no signal named {:?}", describe_object(source), signal);
+    }
+
+    source.clone().connect(signal.into(), callable.clone());
+    track(source, signal, callable);
+}
+
+impl TestContext {
+    pub fn connect(&self, source: &Gd<Object>, signal: &str, callable: Callable) {
+        connect(source, signal, callable);
+    }
+}
+
+// For connections the framework makes itself, connected by the caller.
+pub(crate) fn track(source: &Gd<Object>, signal: &str, callable: Callable) {
+    CONNECTIONS.with(|connections| {
+        connections.borrow_mut().push(Connection { source: source.clone(), signal: signal.into(), callable })
+    });
+}
+
+// Called by the runner once a test has finished, before its root is freed.
+// Connections on nodes under the root go with them, the rest are
+// disconnected here. Returns how many were.
+pub(crate) fn reset(root: Option<&Gd<Node>>) -> usize {
+    let connections = CONNECTIONS.with(|connections| std::mem::take(&mut *connections.borrow_mut()));
+    let mut disconnected = 0;
+
+    for Connection { mut source, signal, callable } in connections {
+        if !source.is_instance_valid() {
+            continue;
+        }
+
+        let in_test = match (source.clone().try_cast::<Node>(), root) {
+            (Ok(node), Some(root)) => *root == node || root.is_ancestor_of(node),
+            _ => false,
+        };
+
+        // One shot connections are already gone once they've fired.
+        if !in_test && source.is_connected(signal.clone(), callable.clone()) {
+            source.disconnect(signal, callable);
+            disconnected += 1;
+        }
+    }
+
+    disconnected
+}
@@ -0,0 +1,79 @@
+use godot::engine::{DisplayServer, Engine, Os};
+
+// Panic payload that ends a test early and reports it as skipped rather
+// than failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Skip {
+    pub reason: String,
+}
+
+// Stops the current test and reports it as skipped with `reason`.
+pub fn skip(reason: &str) -> ! {
+    std::panic::panic_any(Skip { reason: reason.to_string() })
+}
+
+pub fn is_headless() -> bool {
+    DisplayServer::singleton().get_name() == "headless".into()
+}
+
+// Whether frames are actually rendered, i.e. viewports have textures to read
+// back and screenshots are meaningful.
+pub fn rendering_available() -> bool {
+    !is_headless()
+}
+
+// "Windows", "macOS", "Linux", "Android", "iOS", "Web" ...
+pub fn platform() -> String {
+    Os::singleton().get_name().to_string()
+}
+
+pub fn is_platform(name: &str) -> bool {
+    platform().eq_ignore_ascii_case(name)
+}
+
+// Godot feature tags, e.g. "mobile", "debug", "editor" or custom export
+// features.
+pub fn has_feature(feature: &str) -> bool {
+    Os::singleton().has_feature(feature.into())
+}
+
+pub fn has_display() -> bool {
+    !is_headless()
+}
+
+pub fn godot_version() -> (i64, i64, i64) {
+    let info = Engine::singleton().get_version_info();
+    let part = |key: &str| info.get(key).map_or(0, |value| value.to::<i64>());
+    (part("major"), part("minor"), part("patch"))
+}
+
+pub fn godot_version_at_least(major: i64, minor: i64) -> bool {
+    let (actual_major, actual_minor, _) = godot_version();
+    (actual_major, actual_minor) >= (major, minor)
+}
+
+// Skips the current test when nothing is rendered (e.g. --headless CI).
+pub fn require_rendering() {
+    if !rendering_available() {
+        skip("requires rendering, but running headless");
+    }
+}
+
+// Call at the start of a test that reads back rendered frames.
+#[macro_export]
+macro_rules! requires_rendering {
+    () => {
+        $crate::skip::require_rendering()
+    };
+}
+
+// skip_if!(skip::is_platform("Web"), "no threads on the web")
+// skip_if!(!skip::godot_version_at_least(4, 3), "needs Godot {}", "4.3")
+#[macro_export]
+macro_rules! skip_if {
+    ($condition:expr, $($reason:tt)+) => {
+        if $condition {
+            $crate::skip::skip(&format!($($reason)+));
+        }
+    };
+}
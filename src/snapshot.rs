@@ -0,0 +1,117 @@
+use crate::screenshot::{globalize_path, sanitize_file_name};
+use godot::prelude::*;
+
+// A stable, indented text representation of a node and its descendants:
+//
+//     Player (CharacterBody2D) position=(10, 20)
+//       Sprite (Sprite2D)
+//
+// Only the listed properties are included, and only when the node has them.
+pub fn tree_to_string(node: &Gd<Node>, properties: &[&str]) -> String {
+    let mut output = String::new();
+    write_node(&mut output, node, properties, 0);
+    output
+}
+
+fn write_node(output: &mut String, node: &Gd<Node>, properties: &[&str], depth: usize) {
+    output.push_str(&"  ".repeat(depth));
+    output.push_str(&format!("{} ({})", node.get_name(), node.get_class()));
+
+    for property in properties {
+        let value = node.get(StringName::from(*property));
+        if !value.is_nil() {
+            output.push_str(&format!(" {}={}", property, value));
+        }
+    }
+
+    output.push('\n');
+
+    for child in node.get_children().iter_shared() {
+        write_node(output, &child, properties, depth + 1);
+    }
+}
+
+// Compares `actual` to `<snapshot_dir>/<name>.txt`, or (re)writes that file
+// when update_snapshots is set.
+#[track_caller]
+pub fn assert_matches_snapshot(name: &str, actual: &str) {
+    let dir = globalize_path(&crate::config::with(|config| config.snapshot_dir.clone()));
+    let path = format!("{}/{}.txt", dir, sanitize_file_name(name));
+
+    if crate::config::with(|config| config.update_snapshots) {
+        std::fs::create_dir_all(&dir)
+            .and_then(|_| std::fs::write(&path, actual))
+            .unwrap_or_else(|error| panic!("could not write snapshot {}: {}", path, error));
+        return;
+    }
+
+    let expected = match std::fs::read_to_string(&path) {
+        Ok(expected) => expected,
+        Err(_) => panic!(
+            "no snapshot at {} (enable update_snapshots to record it). Actual:\n{}",
+            path, actual
+        ),
+    };
+
+    if expected != actual {
+        let actual_path = crate::artifacts::current_path(&format!("{}.actual.txt", sanitize_file_name(name)));
+        let _ = std::fs::write(&actual_path, actual);
+
+        panic!(
+            "snapshot {} does not match {} (actual saved to {}):\n{}",
+            name,
+            path,
+            actual_path,
+            crate::diff::format_unified_diff(&expected, actual, 3)
+        );
+    }
+}
+
+#[macro_export]
+macro_rules! assert_tree_snapshot {
+    ($node:expr, $name:expr) => {
+        $crate::snapshot::assert_matches_snapshot(
+            $name,
+            &$crate::snapshot::tree_to_string(&$node.clone().upcast(), &[]),
+        )
+    };
+    ($node:expr, $name:expr, [$($property:expr),* $(,)?]) => {
+        $crate::snapshot::assert_matches_snapshot(
+            $name,
+            &$crate::snapshot::tree_to_string(&$node.clone().upcast(), &[$($property),*]),
+        )
+    };
+}
+
+#[track_caller]
+pub fn assert_tree_eq(left: &Gd<Node>, right: &Gd<Node>, properties: &[&str]) {
+    let left_tree = tree_to_string(left, properties);
+    let right_tree = tree_to_string(right, properties);
+
+    if left_tree != right_tree {
+        panic!(
+            "trees under {} and {} differ (- left, + right):\n{}",
+            left.get_path(),
+            right.get_path(),
+            crate::diff::format_unified_diff(&left_tree, &right_tree, 3)
+        );
+    }
+}
+
+#[macro_export]
+macro_rules! assert_tree_eq {
+    ($left:expr, $right:expr) => {
+        $crate::snapshot::assert_tree_eq(
+            &$left.clone().upcast(),
+            &$right.clone().upcast(),
+            &[],
+        )
+    };
+    ($left:expr, $right:expr, [$($property:expr),* $(,)?]) => {
+        $crate::snapshot::assert_tree_eq(
+            &$left.clone().upcast(),
+            &$right.clone().upcast(),
+            &[$($property),*],
+        )
+    };
+}
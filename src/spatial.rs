@@ -0,0 +1,179 @@
+use crate::approx::{ApproxEq, Tolerance};
+use godot::prelude::*;
+use std::f64::consts::{PI, TAU};
+use std::fmt::Debug;
+
+// What the assertions below need from Node2D and Node3D, so they work with
+// either. Rotations are radians, Euler angles (in Godot's YXZ order) in 3D.
+pub trait Spatial: GodotClass + Inherits<Node> {
+    type Vector: ApproxEq + Debug + Copy;
+    type Rotation: Debug + Copy;
+
+    fn global_position(node: &Gd<Self>) -> Self::Vector;
+    fn global_rotation(node: &Gd<Self>) -> Self::Rotation;
+    fn global_scale(node: &Gd<Self>) -> Self::Vector;
+    // How far apart two rotations are, in radians, whichever way round.
+    fn rotation_difference(a: Self::Rotation, b: Self::Rotation) -> f64;
+    fn describe_transforms(node: &Gd<Self>) -> String;
+}
+
+impl Spatial for Node2D {
+    type Vector = Vector2;
+    type Rotation = f32;
+
+    fn global_position(node: &Gd<Self>) -> Vector2 {
+        node.get_global_position()
+    }
+
+    fn global_rotation(node: &Gd<Self>) -> f32 {
+        node.get_global_rotation()
+    }
+
+    fn global_scale(node: &Gd<Self>) -> Vector2 {
+        node.get_global_scale()
+    }
+
+    fn rotation_difference(a: f32, b: f32) -> f64 {
+        ((a as f64 - b as f64 + PI).rem_euclid(TAU) - PI).abs()
+    }
+
+    fn describe_transforms(node: &Gd<Self>) -> String {
+        format!(
+            "  local:  position {:?}, rotation {:?}, scale {:?}\n  global: position {:?}, rotation {:?}, scale {:?}",
+            node.get_position(),
+            node.get_rotation(),
+            node.get_scale(),
+            node.get_global_position(),
+            node.get_global_rotation(),
+            node.get_global_scale()
+        )
+    }
+}
+
+impl Spatial for Node3D {
+    type Vector = Vector3;
+    type Rotation = Vector3;
+
+    fn global_position(node: &Gd<Self>) -> Vector3 {
+        node.get_global_position()
+    }
+
+    fn global_rotation(node: &Gd<Self>) -> Vector3 {
+        node.get_global_rotation()
+    }
+
+    fn global_scale(node: &Gd<Self>) -> Vector3 {
+        node.get_global_transform().basis.scale()
+    }
+
+    // Compared as orientations, as different Euler angles can describe the
+    // same one.
+    fn rotation_difference(a: Vector3, b: Vector3) -> f64 {
+        let a = Basis::from_euler(EulerOrder::YXZ, a).to_quat();
+        let b = Basis::from_euler(EulerOrder::YXZ, b).to_quat();
+        let dot = (a.x * b.x + a.y * b.y + a.z * b.z + a.w * b.w).abs().min(1.0);
+
+        2.0 * (dot as f64).acos()
+    }
+
+    fn describe_transforms(node: &Gd<Self>) -> String {
+        format!(
+            "  local:  position {:?}, rotation {:?}, scale {:?}\n  global: position {:?}, rotation {:?}, scale {:?}",
+            node.get_position(),
+            node.get_rotation(),
+            node.get_scale(),
+            node.get_global_position(),
+            node.get_global_rotation(),
+            Self::global_scale(node)
+        )
+    }
+}
+
+fn path<N: Spatial>(node: &Gd<N>) -> NodePath {
+    node.clone().upcast::<Node>().get_path()
+}
+
+#[track_caller]
+pub fn assert_global_position<N: Spatial>(node: &Gd<N>, expected: N::Vector, epsilon: f64) {
+    let actual = N::global_position(node);
+
+    if let Some(component) = actual.diverging_component(&expected, Tolerance::Absolute(epsilon)) {
+        panic!(
+            "expected {} to be at {:?} (epsilon {}), but it's at {:?}, diverged at {}\n{}",
+            path(node),
+            expected,
+            epsilon,
+            actual,
+            component,
+            N::describe_transforms(node)
+        );
+    }
+}
+
+#[track_caller]
+pub fn assert_global_rotation<N: Spatial>(node: &Gd<N>, expected: N::Rotation, epsilon: f64) {
+    let actual = N::global_rotation(node);
+    let difference = N::rotation_difference(actual, expected);
+
+    if difference > epsilon {
+        panic!(
+            "expected {} to be rotated {:?} (epsilon {}), but it's {:?}, {:.4} radians off\n{}",
+            path(node),
+            expected,
+            epsilon,
+            actual,
+            difference,
+            N::describe_transforms(node)
+        );
+    }
+}
+
+#[track_caller]
+pub fn assert_global_scale<N: Spatial>(node: &Gd<N>, expected: N::Vector, epsilon: f64) {
+    let actual = N::global_scale(node);
+
+    if let Some(component) = actual.diverging_component(&expected, Tolerance::Absolute(epsilon)) {
+        panic!(
+            "expected {} to be scaled {:?} (epsilon {}), but it's {:?}, diverged at {}\n{}",
+            path(node),
+            expected,
+            epsilon,
+            actual,
+            component,
+            N::describe_transforms(node)
+        );
+    }
+}
+
+// assert_position_approx!(player, Vector2::new(100.0, 0.0)) or with an
+// epsilon, assert_position_approx!(player, Vector2::new(100.0, 0.0), 0.5).
+// All of these compare global values.
+#[macro_export]
+macro_rules! assert_position_approx {
+    ($node:expr, $expected:expr) => {
+        $crate::spatial::assert_global_position(&$node, $expected, 0.001)
+    };
+    ($node:expr, $expected:expr, $epsilon:expr) => {
+        $crate::spatial::assert_global_position(&$node, $expected, ($epsilon) as f64)
+    };
+}
+
+#[macro_export]
+macro_rules! assert_rotation_approx {
+    ($node:expr, $expected:expr) => {
+        $crate::spatial::assert_global_rotation(&$node, $expected, 0.001)
+    };
+    ($node:expr, $expected:expr, $epsilon:expr) => {
+        $crate::spatial::assert_global_rotation(&$node, $expected, ($epsilon) as f64)
+    };
+}
+
+#[macro_export]
+macro_rules! assert_scale_approx {
+    ($node:expr, $expected:expr) => {
+        $crate::spatial::assert_global_scale(&$node, $expected, 0.001)
+    };
+    ($node:expr, $expected:expr, $epsilon:expr) => {
+        $crate::spatial::assert_global_scale(&$node, $expected, ($epsilon) as f64)
+    };
+}
@@ -0,0 +1,167 @@
+use godot::prelude::*;
+
+#[derive(Clone, Debug)]
+pub struct SpyCall {
+    pub method: String,
+    pub args: Vec<Variant>,
+}
+
+// The calls a test double received, in order.
+#[derive(Default, Debug)]
+pub struct SpyLog {
+    calls: Vec<SpyCall>,
+}
+
+impl SpyLog {
+    pub fn record(&mut self, method: &str, args: Vec<Variant>) {
+        self.calls.push(SpyCall { method: method.to_string(), args });
+    }
+
+    pub fn calls(&self) -> &[SpyCall] {
+        &self.calls
+    }
+
+    pub fn calls_to(&self, method: &str) -> Vec<&SpyCall> {
+        self.calls.iter().filter(|call| call.method == method).collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.calls.clear();
+    }
+
+    #[track_caller]
+    pub fn assert_called(&self, method: &str, times: usize) {
+        let count = self.calls_to(method).len();
+        if count != times {
+            panic!(
+                "expected {} to be called {} time(s), but it was called {} time(s). Calls:\n{}",
+                method,
+                times,
+                count,
+                self.describe()
+            );
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_not_called(&self, method: &str) {
+        self.assert_called(method, 0);
+    }
+
+    // Passes if any call to `method` had exactly these arguments.
+    #[track_caller]
+    pub fn assert_called_with(&self, method: &str, args: &[Variant]) {
+        if !self.calls_to(method).iter().any(|call| call.args == args) {
+            panic!(
+                "expected {}({}) to be called, but it wasn't. Calls:\n{}",
+                method,
+                format_args(args),
+                self.describe()
+            );
+        }
+    }
+
+    fn describe(&self) -> String {
+        if self.calls.is_empty() {
+            return "  (none)".to_string();
+        }
+
+        self.calls
+            .iter()
+            .map(|call| format!("  {}({})", call.method, format_args(&call.args)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn format_args(args: &[Variant]) -> String {
+    args.iter().map(|arg| arg.stringify().to_string()).collect::<Vec<_>>().join(", ")
+}
+
+// Implemented by spy_class! doubles (and SpyNode).
+pub trait Spy {
+    fn spy_log(&self) -> &SpyLog;
+    fn spy_log_mut(&mut self) -> &mut SpyLog;
+}
+
+// A plain node that records whatever is passed to `record_call`, e.g. as the
+// target of a signal connection or from GDScript:
+//
+//     spy.call("record_call".into(), &["died".to_variant(), varray![].to_variant()]);
+#[derive(GodotClass)]
+#[class(init, base=Node)]
+pub struct SpyNode {
+    #[base]
+    base: Base<Node>,
+    calls: SpyLog,
+}
+
+#[godot_api]
+impl SpyNode {
+    #[func]
+    fn record_call(&mut self, method: StringName, args: VariantArray) {
+        self.calls.record(&method.to_string(), args.iter_shared().collect());
+    }
+}
+
+impl Spy for SpyNode {
+    fn spy_log(&self) -> &SpyLog {
+        &self.calls
+    }
+
+    fn spy_log_mut(&mut self) -> &mut SpyLog {
+        &mut self.calls
+    }
+}
+
+// Declares a Godot class whose methods record their calls and return the
+// default value, to stand in for a real class:
+//
+//     spy_class! {
+//         EnemySpy: Node {
+//             fn take_damage(amount: i64);
+//             fn is_alive() -> bool;
+//         }
+//     }
+//
+//     let enemy = EnemySpy::new_alloc();
+//     bullet.bind_mut().hit(enemy.clone().upcast());
+//     enemy.bind().spy_log().assert_called_with("take_damage", &[10.to_variant()]);
+//
+// The base class has to be in scope.
+#[macro_export]
+macro_rules! spy_class {
+    ($name:ident : $base:ident {
+        $(fn $method:ident($($arg:ident: $type:ty),* $(,)?) $(-> $return:ty)?;)*
+    }) => {
+        #[derive(godot::prelude::GodotClass)]
+        #[class(init, base=$base)]
+        pub struct $name {
+            #[base]
+            base: godot::prelude::Base<$base>,
+            calls: $crate::spy::SpyLog,
+        }
+
+        #[godot::prelude::godot_api]
+        impl $name {
+            $(
+                #[func]
+                pub fn $method(&mut self, $($arg: $type),*) $(-> $return)? {
+                    use godot::prelude::ToGodot;
+                    self.calls.record(stringify!($method), vec![$($arg.to_variant()),*]);
+                    Default::default()
+                }
+            )*
+        }
+
+        impl $crate::spy::Spy for $name {
+            fn spy_log(&self) -> &$crate::spy::SpyLog {
+                &self.calls
+            }
+
+            fn spy_log_mut(&mut self) -> &mut $crate::spy::SpyLog {
+                &mut self.calls
+            }
+        }
+    };
+}
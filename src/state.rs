@@ -0,0 +1,75 @@
+use crate::TestContext;
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+thread_local! {
+    // Values that live for one test, across all of its `wait!` replays. Thread
+    // local rather than a Mutex static so it can hold Gd pointers, which
+    // aren't Send; tests only ever run on the main thread anyway.
+    static TEST_STATE: RefCell<HashMap<TypeId, Box<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+// Gives the current test a `T` that survives `wait!`. It is created with
+// `Default` the first time it's asked for and dropped when the test finishes.
+//
+//     #[derive(Default)]
+//     struct Frames(usize);
+//
+//     fn counts_frames(root: &Gd<Node>) {
+//         let seen = test_state(|frames: &mut Frames| {
+//             frames.0 += 1;
+//             frames.0
+//         });
+//         if seen < 3 {
+//             wait!(0.0);
+//         }
+//     }
+pub fn test_state<T, R>(f: impl FnOnce(&mut T) -> R) -> R
+where
+    T: Default + 'static,
+{
+    // Taken out while `f` runs so that `f` can use other state types.
+    let mut value = TEST_STATE
+        .with(|state| state.borrow_mut().remove(&TypeId::of::<T>()))
+        .unwrap_or_else(|| Box::new(T::default()));
+
+    let result = f(value.downcast_mut::<T>().unwrap());
+
+    TEST_STATE.with(|state| state.borrow_mut().insert(TypeId::of::<T>(), value));
+
+    result
+}
+
+// Called by the runner once a test has finished.
+pub(crate) fn reset() {
+    let state = TEST_STATE.with(|state| std::mem::take(&mut *state.borrow_mut()));
+    drop(state);
+}
+
+// A test that carries its own state between frames. A fresh instance is
+// created when the test starts and `run` is called on it every frame, so
+// fields keep their values across `wait!`:
+//
+//     #[derive(Default)]
+//     struct PlayerFallsToTheGround {
+//         player: Option<Gd<Player>>,
+//     }
+//
+//     impl SpecCase for PlayerFallsToTheGround {
+//         fn run(&mut self, ctx: &TestContext) {
+//             let player = self.player.get_or_insert_with(|| ctx.load_scene("res://player.tscn"));
+//             if !player.bind().is_on_floor() {
+//                 wait!(0.1);
+//             }
+//         }
+//     }
+//
+//     spec!(PlayerFallsToTheGround);
+pub trait SpecCase: Default + 'static {
+    fn run(&mut self, ctx: &TestContext);
+}
+
+pub fn run_spec<T: SpecCase>(ctx: &TestContext) {
+    test_state(|spec: &mut T| spec.run(ctx));
+}
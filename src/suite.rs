@@ -0,0 +1,95 @@
+use crate::TestCase;
+use std::cell::RefCell;
+
+thread_local! {
+    // The suite TestSuite::collect is filling in, if any. Registering goes
+    // there instead of to the runner while it's set.
+    static COLLECTING: RefCell<Option<TestSuite>> = RefCell::new(None);
+}
+
+// A set of tests that can be built up separately from the runner, e.g. by a
+// library crate of a multi-crate game, and handed to it later:
+//
+//     // In the `inventory` crate:
+//     pub fn specs() -> TestSuite {
+//         TestSuite::collect(|| {
+//             test!(stacks_items);
+//             test!(drops_on_death);
+//         })
+//     }
+//
+//     // In the project's registration function:
+//     inventory::specs().merge(combat::specs()).register();
+#[derive(Clone, Default)]
+pub struct TestSuite {
+    // With whether each was registered with focus!.
+    tests: Vec<(TestCase, bool)>,
+}
+
+impl TestSuite {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Runs `f` and returns what the registration macros in it (test!, spec!,
+    // bench! and so on) registered, instead of giving it to the runner.
+    pub fn collect(f: impl FnOnce()) -> Self {
+        let outer = COLLECTING.with(|collecting| collecting.borrow_mut().replace(Self::new()));
+
+        f();
+
+        let suite = COLLECTING.with(|collecting| std::mem::replace(&mut *collecting.borrow_mut(), outer));
+        suite.unwrap_or_default()
+    }
+
+    pub fn add(mut self, test: TestCase) -> Self {
+        self.tests.push((test, false));
+        self
+    }
+
+    pub fn focus(mut self, test: TestCase) -> Self {
+        self.tests.push((test, true));
+        self
+    }
+
+    pub fn merge(mut self, other: TestSuite) -> Self {
+        self.tests.extend(other.tests);
+        self
+    }
+
+    pub fn tests(&self) -> impl Iterator<Item = &TestCase> {
+        self.tests.iter().map(|(test, _)| test)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tests.is_empty()
+    }
+
+    // Hands the tests to the runner, after those registered so far. Inside
+    // another TestSuite::collect they go to that suite instead.
+    pub fn register(self) {
+        for (test, focused) in self.tests {
+            if focused {
+                crate::register_focused(test);
+            } else {
+                crate::register(test);
+            }
+        }
+    }
+}
+
+// Called by register: keeps the test when a suite is being collected, or
+// gives it back to go to the runner.
+pub(crate) fn collect(test: TestCase, focused: bool) -> Option<TestCase> {
+    COLLECTING.with(|collecting| match collecting.borrow_mut().as_mut() {
+        Some(suite) => {
+            suite.tests.push((test, focused));
+            None
+        }
+        None => Some(test),
+    })
+}
@@ -0,0 +1,136 @@
+use crate::panics::panic_message;
+use crate::TestContext;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+// How long a finished test waits for the threads it spawned to end before
+// failing them as leaked.
+pub(crate) const LEAK_GRACE: Duration = Duration::from_millis(200);
+
+struct Spawned {
+    id: u64,
+    name: String,
+    finished: Arc<AtomicBool>,
+}
+
+lazy_static::lazy_static! {
+    // Panics of threads the test never joined, reported against the test
+    // when it finishes.
+    static ref THREAD_PANICS: Mutex<Vec<(u64, String)>> = Mutex::new(Vec::new());
+    // Threads spawned by the current test.
+    static ref TEST_THREADS: Mutex<Vec<Spawned>> = Mutex::new(Vec::new());
+}
+
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(0);
+
+// Runs `f` on a new thread owned by the test. A panic on it fails the test
+// (when joined, or when the test finishes), instead of going unnoticed, and
+// the test fails if the thread is still running once it's done.
+pub fn spawn<F, T>(test_name: &str, f: F) -> TestThread<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let id = NEXT_THREAD_ID.fetch_add(1, Ordering::SeqCst);
+    let name = format!("{} (thread {})", test_name, id);
+    let finished = Arc::new(AtomicBool::new(false));
+
+    TEST_THREADS.lock().unwrap().push(Spawned { id, name: name.clone(), finished: finished.clone() });
+
+    let handle = std::thread::Builder::new()
+        .name(name.clone())
+        .spawn(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(f)).map_err(|error| {
+                let message = panic_message(&error);
+                THREAD_PANICS.lock().unwrap().push((id, message.clone()));
+                message
+            });
+
+            finished.store(true, Ordering::SeqCst);
+            result
+        })
+        .expect("failed to spawn a test thread");
+
+    TestThread { id, name, handle }
+}
+
+impl TestContext {
+    pub fn spawn<F, T>(&self, f: F) -> TestThread<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        spawn(self.name(), f)
+    }
+}
+
+pub struct TestThread<T> {
+    id: u64,
+    name: String,
+    handle: JoinHandle<Result<T, String>>,
+}
+
+impl<T> TestThread<T> {
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    // Blocks until the thread is done and returns what it returned. If it
+    // panicked, this panics with the same message. In a test that waits
+    // across frames, check is_finished first so the engine keeps running.
+    #[track_caller]
+    pub fn join(self) -> T {
+        let id = self.id;
+
+        match self.handle.join() {
+            Ok(Ok(output)) => output,
+            Ok(Err(message)) => {
+                // Reported here, so not again when the test finishes.
+                THREAD_PANICS.lock().unwrap().retain(|(thread, _)| *thread != id);
+                panic!("{} panicked: {}", self.name, message)
+            }
+            Err(error) => panic!("{} failed: {}", self.name, panic_message(&error)),
+        }
+    }
+}
+
+// Whether a thread the current test spawned is still running. The runner
+// keeps checking, a frame at a time, for up to LEAK_GRACE before calling
+// finish_test.
+pub(crate) fn still_running() -> bool {
+    TEST_THREADS.lock().unwrap().iter().any(|thread| !thread.finished.load(Ordering::SeqCst))
+}
+
+// Called by the runner once a test's threads are done, or LEAK_GRACE has
+// passed: returns the panics of its threads nobody joined, and a failure for
+// each thread still running. Those are left to finish on their own, as
+// threads can't be stopped, and anything they do afterwards isn't attributed
+// to later tests.
+pub(crate) fn finish_test() -> Vec<String> {
+    let threads: Vec<Spawned> = TEST_THREADS.lock().unwrap().drain(..).collect();
+
+    let panics: Vec<(u64, String)> = THREAD_PANICS.lock().unwrap().drain(..).collect();
+
+    let mut failures: Vec<String> = panics
+        .into_iter()
+        .filter_map(|(id, message)| {
+            let thread = threads.iter().find(|thread| thread.id == id)?;
+            Some(format!("{} panicked: {}", thread.name, message))
+        })
+        .collect();
+
+    for thread in &threads {
+        if !thread.finished.load(Ordering::SeqCst) {
+            failures.push(format!(
+                "{} was still running {}ms after the test finished (join it before the test ends)",
+                thread.name,
+                LEAK_GRACE.as_millis()
+            ));
+        }
+    }
+
+    failures
+}
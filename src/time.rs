@@ -0,0 +1,172 @@
+use crate::TestContext;
+use godot::engine::{AnimationPlayer, Engine, Time, Timer};
+use godot::prelude::*;
+use std::sync::{Arc, Mutex};
+
+// Changes Engine.time_scale for the rest of the test. It is put back when
+// the test finishes (with the rest of globals::restore), so a test can't slow
+// down (or speed up) the rest of the suite.
+pub fn set_time_scale(scale: f64) {
+    Engine::singleton().set_time_scale(scale);
+}
+
+impl TestContext {
+    pub fn set_time_scale(&self, scale: f64) {
+        set_time_scale(scale);
+    }
+}
+
+// Sets Engine.time_scale until dropped. For a scale that has to survive
+// `wait!`, use `set_time_scale` instead.
+pub struct TimeScaleGuard {
+    previous: f64,
+}
+
+impl TimeScaleGuard {
+    pub fn new(scale: f64) -> Self {
+        let previous = Engine::singleton().get_time_scale();
+        set_time_scale(scale);
+        Self { previous }
+    }
+}
+
+impl Drop for TimeScaleGuard {
+    fn drop(&mut self) {
+        Engine::singleton().set_time_scale(self.previous);
+    }
+}
+
+// Where game code gets "now" from. Production code uses EngineClock; specs
+// pass a VirtualClock and move it forward by hand.
+pub trait Clock {
+    // Seconds since some fixed point.
+    fn now(&self) -> f64;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EngineClock;
+
+impl Clock for EngineClock {
+    fn now(&self) -> f64 {
+        Time::singleton().get_ticks_usec() as f64 / 1_000_000.0
+    }
+}
+
+// A clock that only moves when told to. Clones share the same time.
+#[derive(Clone, Debug, Default)]
+pub struct VirtualClock {
+    now: Arc<Mutex<f64>>,
+}
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, seconds: f64) {
+        assert!(seconds >= 0.0, "VirtualClock can't go back in time ({}s)", seconds);
+        *self.now.lock().unwrap() += seconds;
+    }
+
+    pub fn set(&self, seconds: f64) {
+        *self.now.lock().unwrap() = seconds;
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> f64 {
+        self.now.lock().unwrap().clone()
+    }
+}
+
+// Moves every running Timer and playing AnimationPlayer under `root`, and
+// every running Tween, `seconds` forward within the current frame. Timers
+// that run out emit `timeout` (repeating ones once per period) and are
+// restarted or stopped like the engine would. Returns how many timeouts were
+// emitted.
+pub fn fast_forward(root: &Gd<Node>, seconds: f64) -> usize {
+    assert!(seconds >= 0.0, "fast_forward: can't go back in time ({}s)", seconds);
+
+    let mut nodes = Vec::new();
+    collect_nodes(root.clone(), &mut nodes);
+
+    let mut timeouts = 0;
+
+    for node in &nodes {
+        if let Ok(timer) = node.clone().try_cast::<Timer>() {
+            timeouts += fast_forward_timer(timer, seconds);
+        }
+    }
+
+    for node in nodes {
+        if let Ok(mut player) = node.try_cast::<AnimationPlayer>() {
+            if player.is_playing() {
+                player.advance(seconds);
+            }
+        }
+    }
+
+    if let Some(tree) = root.get_tree() {
+        for mut tween in tree.get_processed_tweens().iter_shared() {
+            if tween.is_valid() && tween.is_running() {
+                tween.custom_step(seconds);
+            }
+        }
+    }
+
+    timeouts
+}
+
+fn collect_nodes(node: Gd<Node>, nodes: &mut Vec<Gd<Node>>) {
+    for child in node.get_children().iter_shared() {
+        collect_nodes(child, nodes);
+    }
+    nodes.push(node);
+}
+
+fn fast_forward_timer(mut timer: Gd<Timer>, seconds: f64) -> usize {
+    if timer.is_stopped() || timer.is_paused() {
+        return 0;
+    }
+
+    let time_left = timer.get_time_left();
+    let wait_time = timer.get_wait_time();
+
+    if seconds < time_left {
+        restart(&mut timer, time_left - seconds, wait_time);
+        return 0;
+    }
+
+    if timer.is_one_shot() {
+        timer.stop();
+        timer.emit_signal("timeout".into(), &[]);
+        return 1;
+    }
+
+    let overshoot = seconds - time_left;
+    let extra = (overshoot / wait_time).floor() as usize;
+    let remaining = wait_time - (overshoot - extra as f64 * wait_time);
+
+    // Restarted first, so a timeout handler that stops the timer wins.
+    restart(&mut timer, remaining, wait_time);
+
+    for _ in 0..=extra {
+        timer.emit_signal("timeout".into(), &[]);
+    }
+
+    extra + 1
+}
+
+// Timer.start(time_sec) also overwrites wait_time, which has to survive for
+// the following periods.
+fn restart(timer: &mut Gd<Timer>, time_left: f64, wait_time: f64) {
+    timer.start_ex().time_sec(time_left).done();
+    timer.set_wait_time(wait_time);
+}
+
+impl TestContext {
+    // `fast_forward` on the test's root.
+    pub fn fast_forward(&self, seconds: f64) -> usize {
+        fast_forward(self.root(), seconds)
+    }
+}
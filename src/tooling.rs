@@ -0,0 +1,413 @@
+// Glue for driving a suite from outside Godot: building the GDExtension,
+// launching the headless test scene and watching sources. Used by the
+// binaries in src/bin.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::time::SystemTime;
+
+#[derive(Clone, Debug)]
+pub struct LaunchOptions {
+    pub godot: PathBuf,
+    pub project: PathBuf,
+    pub scene: String,
+    pub headless: bool,
+    // Passed to Godot as --fixed-fps and to the runner, see
+    // TestConfig::fixed_fps.
+    pub fixed_fps: Option<u32>,
+    pub user_args: Vec<String>,
+}
+
+impl Default for LaunchOptions {
+    fn default() -> Self {
+        Self {
+            godot: PathBuf::from("godot"),
+            project: PathBuf::from("godot"),
+            scene: "res://tests.tscn".to_string(),
+            headless: true,
+            fixed_fps: None,
+            user_args: Vec::new(),
+        }
+    }
+}
+
+pub fn cargo_build(extra_args: &[String]) -> bool {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+
+    Command::new(cargo)
+        .arg("build")
+        .args(extra_args)
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+pub fn godot_command(options: &LaunchOptions) -> Command {
+    let mut command = Command::new(&options.godot);
+
+    if options.headless {
+        command.arg("--headless");
+    }
+
+    if let Some(fps) = options.fixed_fps {
+        command.arg("--fixed-fps").arg(fps.to_string());
+    }
+
+    command.arg("--path").arg(&options.project).arg(&options.scene);
+
+    let mut user_args = options.user_args.clone();
+    if let Some(fps) = options.fixed_fps {
+        user_args.push("--fixed-fps".to_string());
+        user_args.push(fps.to_string());
+    }
+
+    if !user_args.is_empty() {
+        command.arg("--").args(&user_args);
+    }
+
+    command
+}
+
+pub fn spawn_godot(options: &LaunchOptions) -> std::io::Result<Child> {
+    godot_command(options).spawn()
+}
+
+pub fn run_godot(options: &LaunchOptions) -> std::io::Result<ExitStatus> {
+    godot_command(options).status()
+}
+
+// Runs the suite as `shards` Godot processes side by side (each with
+// `--shard i/N`), then merges their results into `results_file`. Returns the
+// exit code to finish with: the first non-zero one of any shard.
+pub fn run_sharded(options: &LaunchOptions, shards: u32, results_file: &Path) -> Result<i32, String> {
+    let results = results_file.to_string_lossy().to_string();
+
+    let mut children = Vec::new();
+    for index in 1..=shards {
+        let mut shard = options.clone();
+        shard.user_args.push("--shard".to_string());
+        shard.user_args.push(format!("{}/{}", index, shards));
+        shard.user_args.push("--results-file".to_string());
+        shard.user_args.push(results.clone());
+
+        let child = spawn_godot(&shard)
+            .map_err(|error| format!("could not launch shard {}/{}: {}", index, shards, error))?;
+        children.push((index, child));
+    }
+
+    let mut exit_code = 0;
+    let mut shard_results = Vec::new();
+
+    for (index, mut child) in children {
+        let code = child.wait().map(|status| status.code().unwrap_or(1)).unwrap_or(1);
+        if exit_code == 0 {
+            exit_code = code;
+        }
+
+        let path = crate::report::shard_results_path(&results, crate::config::Shard { index, total: shards });
+        match std::fs::read_to_string(&path).ok().and_then(|json| serde_json::from_str(&json).ok()) {
+            Some(json) => shard_results.push(json),
+            None => eprintln!("shard {}/{} wrote no results to {}", index, shards, path),
+        }
+    }
+
+    let merged = crate::report::merge_results(&shard_results);
+    if let Some(parent) = results_file.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    std::fs::write(results_file, serde_json::to_string_pretty(&merged).unwrap_or_default())
+        .map_err(|error| format!("could not write {}: {}", results_file.display(), error))?;
+
+    Ok(exit_code)
+}
+
+// Modification times of every .rs file under the given directories.
+pub fn rust_sources(dirs: &[PathBuf]) -> HashMap<PathBuf, SystemTime> {
+    let mut sources = HashMap::new();
+
+    for dir in dirs {
+        collect_rust_sources(dir, &mut sources);
+    }
+
+    sources
+}
+
+fn collect_rust_sources(dir: &Path, sources: &mut HashMap<PathBuf, SystemTime>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.is_dir() {
+            if path.file_name().map_or(false, |name| name == "target") {
+                continue;
+            }
+            collect_rust_sources(&path, sources);
+        } else if path.extension().map_or(false, |extension| extension == "rs") {
+            if let Ok(modified) = entry.metadata().and_then(|metadata| metadata.modified()) {
+                sources.insert(path, modified);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ScaffoldOptions {
+    pub project: PathBuf,
+    // Name of the cdylib crate containing the specs, as it appears in the
+    // built file name (lib<name>.so, <name>.dll, ...).
+    pub lib_name: String,
+    pub scene: String,
+    pub force: bool,
+}
+
+// The crate's library name from ./Cargo.toml: [lib] name if set, otherwise
+// [package] name, with dashes turned into underscores like cargo does.
+pub fn lib_name_from_manifest(manifest: &Path) -> Option<String> {
+    let contents = std::fs::read_to_string(manifest).ok()?;
+    let mut section = String::new();
+    let mut package_name = None;
+    let mut lib_name = None;
+
+    for line in contents.lines() {
+        let line = line.trim();
+
+        if line.starts_with('[') {
+            section = line.to_string();
+            continue;
+        }
+
+        let Some(value) = line.strip_prefix("name") else {
+            continue;
+        };
+        let Some(value) = value.trim_start().strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"').to_string();
+
+        match section.as_str() {
+            "[lib]" => lib_name = Some(value),
+            "[package]" => package_name = Some(value),
+            _ => {}
+        }
+    }
+
+    lib_name.or(package_name).map(|name| name.replace('-', "_"))
+}
+
+// res:// path of the crate's target directory as seen from the project.
+fn target_dir_from_project(project: &Path) -> String {
+    let depth = project
+        .components()
+        .filter(|component| matches!(component, std::path::Component::Normal(_)))
+        .count();
+
+    format!("res://{}target", "../".repeat(depth))
+}
+
+fn project_godot(scene: &str) -> String {
+    format!(
+        "config_version=5\n\n[application]\n\nconfig/name=\"Specs\"\nrun/main_scene=\"{}\"\nconfig/features=PackedStringArray(\"4.2\")\n",
+        scene
+    )
+}
+
+fn gdextension(lib_name: &str, target: &str) -> String {
+    let mut contents = String::from(
+        "[configuration]\nentry_symbol = \"gdext_rust_init\"\ncompatibility_minimum = 4.1\nreloadable = true\n\n[libraries]\n",
+    );
+
+    for profile in ["debug", "release"] {
+        contents.push_str(&format!(
+            "linux.{profile}.x86_64 = \"{target}/{profile}/lib{lib}.so\"\n\
+             windows.{profile}.x86_64 = \"{target}/{profile}/{lib}.dll\"\n\
+             macos.{profile} = \"{target}/{profile}/lib{lib}.dylib\"\n\
+             macos.{profile}.arm64 = \"{target}/{profile}/lib{lib}.dylib\"\n",
+            profile = profile,
+            target = target,
+            lib = lib_name
+        ));
+    }
+
+    contents
+}
+
+fn test_scene() -> String {
+    "[gd_scene format=3]\n\n[node name=\"TestRunner\" type=\"TestRunner\"]\n".to_string()
+}
+
+// Writes the minimal Godot project needed to run the suite. Returns the
+// files it created; existing files are left alone unless `force` is set.
+pub fn scaffold(options: &ScaffoldOptions) -> std::io::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(&options.project)?;
+
+    let scene_file = options
+        .scene
+        .strip_prefix("res://")
+        .unwrap_or(&options.scene)
+        .to_string();
+
+    let files = [
+        (PathBuf::from("project.godot"), project_godot(&options.scene)),
+        (
+            PathBuf::from(format!("{}.gdextension", options.lib_name)),
+            gdextension(&options.lib_name, &target_dir_from_project(&options.project)),
+        ),
+        (PathBuf::from(scene_file), test_scene()),
+    ];
+
+    let mut written = Vec::new();
+
+    for (file, contents) in files {
+        let path = options.project.join(file);
+
+        if path.exists() && !options.force {
+            continue;
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, contents)?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+const GODOT_NAMES: &[&str] = &["godot", "godot4", "Godot", "Godot4", "godot.linuxbsd.editor.x86_64"];
+
+fn common_godot_locations() -> Vec<PathBuf> {
+    let mut locations = vec![
+        PathBuf::from("/Applications/Godot.app/Contents/MacOS/Godot"),
+        PathBuf::from("/usr/local/bin/godot"),
+        PathBuf::from("/usr/bin/godot"),
+        PathBuf::from("/var/lib/flatpak/exports/bin/org.godotengine.Godot"),
+        PathBuf::from("C:\\Program Files\\Godot\\godot.exe"),
+    ];
+
+    if let Some(home) = std::env::var_os("HOME") {
+        let home = PathBuf::from(home);
+        locations.push(home.join("Applications/Godot.app/Contents/MacOS/Godot"));
+        locations.push(home.join(".local/bin/godot"));
+        locations.push(home.join(".local/share/flatpak/exports/bin/org.godotengine.Godot"));
+    }
+
+    locations
+}
+
+// Finds the Godot executable: the explicit path if given, then $GODOT, then
+// PATH, then common install locations.
+pub fn find_godot(explicit: Option<&Path>) -> Result<PathBuf, String> {
+    if let Some(path) = explicit {
+        return if path.exists() || which(path).is_some() {
+            Ok(path.to_path_buf())
+        } else {
+            Err(format!("the Godot executable {} does not exist", path.display()))
+        };
+    }
+
+    if let Some(path) = std::env::var_os("GODOT") {
+        let path = PathBuf::from(path);
+        return if path.exists() || which(&path).is_some() {
+            Ok(path)
+        } else {
+            Err(format!("GODOT is set to {}, which does not exist", path.display()))
+        };
+    }
+
+    for name in GODOT_NAMES {
+        if let Some(path) = which(Path::new(name)) {
+            return Ok(path);
+        }
+    }
+
+    for path in common_godot_locations() {
+        if path.is_file() {
+            return Ok(path);
+        }
+    }
+
+    Err("could not find a Godot executable; put it on PATH, set GODOT, or pass --godot".to_string())
+}
+
+fn which(name: &Path) -> Option<PathBuf> {
+    if name.components().count() > 1 {
+        return None;
+    }
+
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).find_map(|dir| {
+        let candidate = dir.join(format!("{}{}", name.display(), std::env::consts::EXE_SUFFIX));
+        if candidate.is_file() {
+            Some(candidate)
+        } else {
+            None
+        }
+    })
+}
+
+// Parses the output of `godot --version`, e.g. "4.2.1.stable.official.b09f793f5".
+pub fn parse_godot_version(output: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = output.trim().split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    let patch = parts.next().and_then(|patch| patch.parse().ok()).unwrap_or(0);
+    Some((major, minor, patch))
+}
+
+pub fn godot_version(godot: &Path) -> Result<(u32, u32, u32), String> {
+    let output = Command::new(godot)
+        .arg("--version")
+        .output()
+        .map_err(|error| format!("could not run {} --version: {}", godot.display(), error))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_godot_version(&stdout).ok_or_else(|| {
+        format!(
+            "could not understand the version reported by {}: {:?}",
+            godot.display(),
+            stdout.trim()
+        )
+    })
+}
+
+// GDExtensions only load in the Godot version their bindings were generated
+// for, or a newer one.
+pub fn check_godot_version(godot: &Path) -> Result<(u32, u32, u32), String> {
+    let version = godot_version(godot)?;
+    let (major, minor, patch) = godot::sys::GdextBuild::godot_static_version_triple();
+    let required = (major as u32, minor as u32, patch as u32);
+
+    if version.0 != required.0 || (version.0, version.1) < (required.0, required.1) {
+        return Err(format!(
+            "{} is Godot {}.{}.{}, but the bindings were built for Godot {}.{} or newer (4.x)",
+            godot.display(),
+            version.0,
+            version.1,
+            version.2,
+            required.0,
+            required.1
+        ));
+    }
+
+    Ok(version)
+}
+
+// find_godot + check_godot_version, exiting with a clear message on failure.
+// Meant for the command line tools.
+pub fn resolve_godot_or_exit(explicit: Option<&Path>) -> PathBuf {
+    let godot = find_godot(explicit).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(127);
+    });
+
+    if let Err(error) = check_godot_version(&godot) {
+        eprintln!("{}", error);
+        std::process::exit(127);
+    }
+
+    godot
+}
@@ -0,0 +1,79 @@
+use crate::TestContext;
+use godot::engine::sub_viewport::UpdateMode;
+use godot::engine::{Engine, Image, SceneTree, SubViewport, Window, World2D, World3D};
+use godot::prelude::*;
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+    // The root window's size before the current test changed it.
+    static ref ORIGINAL_SIZE: Mutex<Option<Vector2i>> = Mutex::new(None);
+}
+
+pub fn root_window() -> Gd<Window> {
+    Engine::singleton()
+        .get_main_loop()
+        .and_then(|main_loop| main_loop.try_cast::<SceneTree>().ok())
+        .and_then(|tree| tree.get_root())
+        .expect("there is no SceneTree root window")
+}
+
+// Resizes the root window (and with it the root viewport) for the rest of
+// the test. The previous size is restored when the test finishes.
+pub fn set_viewport_size(size: Vector2i) {
+    let mut window = root_window();
+
+    let mut original = ORIGINAL_SIZE.lock().unwrap();
+    if original.is_none() {
+        *original = Some(window.get_size());
+    }
+
+    window.set_size(size);
+}
+
+impl TestContext {
+    pub fn set_viewport_size(&self, width: i32, height: i32) {
+        set_viewport_size(Vector2i::new(width, height));
+    }
+}
+
+// With TestConfig::isolate_viewports, each test's root is a SubViewport
+// with its own 2D and 3D worlds, so cameras, environments and canvas layers
+// can't leak between tests. With isolate_physics, those are brand new
+// worlds, with physics spaces of their own.
+pub(crate) fn isolated_root(own_physics: bool) -> Gd<Node> {
+    let mut viewport = SubViewport::new_alloc();
+    viewport.set_size(root_window().get_size());
+    if own_physics {
+        viewport.set_world_2d(World2D::new_gd());
+        viewport.set_world_3d(World3D::new_gd());
+    } else {
+        viewport.set_use_own_world_3d(true);
+    }
+    viewport.set_update_mode(UpdateMode::ALWAYS);
+    viewport.upcast()
+}
+
+impl TestContext {
+    // The test's own SubViewport when running with isolate_viewports.
+    pub fn viewport(&self) -> Option<Gd<SubViewport>> {
+        self.root().clone().try_cast::<SubViewport>().ok()
+    }
+
+    // The last frame rendered in the test's SubViewport. None when not
+    // isolated or when nothing is rendered (headless).
+    pub fn viewport_image(&self) -> Option<Gd<Image>> {
+        self.viewport()?.get_texture()?.get_image()
+    }
+}
+
+// Called by the runner when the suite starts, for TestConfig::viewport_size.
+pub(crate) fn apply_suite_size(size: Vector2i) {
+    root_window().set_size(size);
+}
+
+// Called by the runner once a test has finished.
+pub(crate) fn reset() {
+    if let Some(size) = ORIGINAL_SIZE.lock().unwrap().take() {
+        root_window().set_size(size);
+    }
+}
@@ -0,0 +1,118 @@
+use godot::engine::{
+    CanvasItem, Control, Node2D, Node3D, VisibleOnScreenNotifier2D, VisibleOnScreenNotifier3D,
+};
+use godot::prelude::*;
+
+struct OnScreen {
+    visible: bool,
+    why: String,
+}
+
+fn notifier_state(node: &Gd<Node>) -> Option<String> {
+    node.get_children().iter_shared().find_map(|child| {
+        if let Ok(notifier) = child.clone().try_cast::<VisibleOnScreenNotifier2D>() {
+            Some(format!("{} says on_screen={}", notifier.get_name(), notifier.is_on_screen()))
+        } else if let Ok(notifier) = child.try_cast::<VisibleOnScreenNotifier3D>() {
+            Some(format!("{} says on_screen={}", notifier.get_name(), notifier.is_on_screen()))
+        } else {
+            None
+        }
+    })
+}
+
+// Whether the node's origin (or, for a Control, any of its rect) is inside
+// its viewport's visible rect, as seen through the current camera.
+fn on_screen(node: &Gd<Node>) -> OnScreen {
+    let Some(viewport) = node.get_viewport() else {
+        return OnScreen { visible: false, why: "it isn't in the tree".to_string() };
+    };
+    let rect = viewport.get_visible_rect();
+
+    if let Ok(item) = node.clone().try_cast::<CanvasItem>() {
+        if !item.is_visible_in_tree() {
+            return OnScreen { visible: false, why: "it (or a parent) is hidden".to_string() };
+        }
+    }
+
+    if let Ok(control) = node.clone().try_cast::<Control>() {
+        let global = control.get_global_rect();
+        return OnScreen {
+            visible: rect.intersects(global),
+            why: format!("its rect is {:?}, the viewport shows {:?}", global, rect),
+        };
+    }
+
+    if let Ok(node) = node.clone().try_cast::<Node2D>() {
+        // Includes the canvas transform, i.e. the active Camera2D.
+        let position = node.get_global_transform_with_canvas().origin;
+        return OnScreen {
+            visible: rect.contains_point(position),
+            why: format!("it's at {:?} on screen, the viewport shows {:?}", position, rect),
+        };
+    }
+
+    if let Ok(node) = node.clone().try_cast::<Node3D>() {
+        if !node.is_visible_in_tree() {
+            return OnScreen { visible: false, why: "it (or a parent) is hidden".to_string() };
+        }
+
+        let Some(camera) = viewport.get_camera_3d() else {
+            return OnScreen { visible: false, why: "the viewport has no current Camera3D".to_string() };
+        };
+
+        let position = node.get_global_position();
+        if camera.is_position_behind(position) {
+            return OnScreen {
+                visible: false,
+                why: format!("{:?} is behind {}", position, camera.get_path()),
+            };
+        }
+
+        let screen = camera.unproject_position(position);
+        return OnScreen {
+            visible: rect.contains_point(screen),
+            why: format!(
+                "{:?} is at {:?} on screen through {}, the viewport shows {:?}",
+                position,
+                screen,
+                camera.get_path(),
+                rect
+            ),
+        };
+    }
+
+    panic!("{} is a {}, which isn't drawn anywhere", node.get_path(), node.get_class());
+}
+
+// Works for Node2D (through the active Camera2D), Node3D (through the
+// viewport's Camera3D) and Control nodes.
+#[track_caller]
+pub fn assert_on_screen(node: &Gd<Node>, expected: bool) {
+    let result = on_screen(node);
+
+    if result.visible != expected {
+        let notifier = notifier_state(node).map(|state| format!(" ({})", state)).unwrap_or_default();
+
+        panic!(
+            "expected {} to be {}, but {}{}",
+            node.get_path(),
+            if expected { "on screen" } else { "off screen" },
+            result.why,
+            notifier
+        );
+    }
+}
+
+#[macro_export]
+macro_rules! assert_on_screen {
+    ($node:expr) => {
+        $crate::visibility::assert_on_screen(&$node.clone().upcast(), true)
+    };
+}
+
+#[macro_export]
+macro_rules! assert_off_screen {
+    ($node:expr) => {
+        $crate::visibility::assert_on_screen(&$node.clone().upcast(), false)
+    };
+}
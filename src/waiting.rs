@@ -0,0 +1,124 @@
+use crate::panics::catch_expected_panic;
+use crate::snapshot::tree_to_string;
+use crate::state::test_state;
+use godot::prelude::*;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// When each waiting macro of the current test (by call site) started
+// waiting, and how often it has retried since.
+#[derive(Default)]
+struct Waits(HashMap<&'static str, (Instant, u32)>);
+
+struct Wait {
+    elapsed: Duration,
+    attempts: u32,
+    timed_out: bool,
+}
+
+fn attempt(key: &'static str, timeout_ms: u64) -> Wait {
+    test_state(|waits: &mut Waits| {
+        let (started_at, attempts) = waits.0.entry(key).or_insert((Instant::now(), 0));
+        *attempts += 1;
+
+        let elapsed = started_at.elapsed();
+        Wait {
+            elapsed,
+            attempts: *attempts,
+            timed_out: elapsed >= Duration::from_millis(timeout_ms),
+        }
+    })
+}
+
+fn finish(key: &'static str) {
+    test_state(|waits: &mut Waits| waits.0.remove(key));
+}
+
+// Called by wait_for_node!. None means "not there yet, check again next
+// frame".
+#[track_caller]
+pub fn poll_node(root: &Gd<Node>, path: &str, timeout_ms: u64, key: &'static str) -> Option<Gd<Node>> {
+    let wait = attempt(key, timeout_ms);
+
+    if let Some(node) = root.get_node_or_null(path.into()) {
+        finish(key);
+        return Some(node);
+    }
+
+    if wait.timed_out {
+        finish(key);
+        panic!(
+            "no node appeared at {:?} under {} within {}ms ({} checks). Actual tree:\n{}",
+            path,
+            root.get_path(),
+            wait.elapsed.as_millis(),
+            wait.attempts,
+            tree_to_string(root, &[])
+        );
+    }
+
+    None
+}
+
+// Called by eventually!. Returns how long to wait before retrying, or None
+// once the assertions pass.
+#[track_caller]
+pub fn poll_assertion(key: &'static str, timeout_ms: u64, assertion: impl FnOnce()) -> Option<f64> {
+    let wait = attempt(key, timeout_ms);
+
+    let Some(message) = catch_expected_panic(assertion) else {
+        finish(key);
+        return None;
+    };
+
+    if wait.timed_out {
+        finish(key);
+        panic!(
+            "eventually! gave up after {}ms ({} attempts). Last failure:\n{}",
+            wait.elapsed.as_millis(),
+            wait.attempts,
+            message
+        );
+    }
+
+    // Next frame at first, backing off to four times a second.
+    Some((0.002 * 2f64.powi(wait.attempts as i32 - 1)).min(0.25))
+}
+
+// Waits (across frames) until `root` has a node at `path`, and evaluates to
+// it. Fails with the tree as it is once `timeout_ms` have passed:
+//
+//     let enemy = wait_for_node!(root, "Level/Enemies/Enemy", 1000);
+#[macro_export]
+macro_rules! wait_for_node {
+    ($root:expr, $path:expr, $timeout_ms:expr) => {
+        match $crate::waiting::poll_node(
+            &$root.clone().upcast(),
+            $path,
+            $timeout_ms,
+            concat!(file!(), ":", line!(), ":", column!()),
+        ) {
+            Some(node) => node,
+            None => $crate::wait!(0.0),
+        }
+    };
+}
+
+// Retries the assertions in the block, on later frames, until they pass.
+// Fails with the last assertion failure once `timeout_ms` have passed:
+//
+//     eventually!({
+//         assert_eq!(enemy.bind().state, State::Fleeing);
+//     }, 2000);
+#[macro_export]
+macro_rules! eventually {
+    ($assertion:block, $timeout_ms:expr) => {
+        if let Some(delay) = $crate::waiting::poll_assertion(
+            concat!(file!(), ":", line!(), ":", column!()),
+            $timeout_ms,
+            || $assertion,
+        ) {
+            $crate::wait!(delay);
+        }
+    };
+}
@@ -0,0 +1,31 @@
+use godot::prelude::*;
+use std::cell::RefCell;
+
+type Callback = Box<dyn FnOnce(&Gd<Node>)>;
+
+thread_local! {
+    static CALLBACKS: RefCell<Vec<Callback>> = RefCell::new(Vec::new());
+}
+
+// Runs `callback` once, before the warm-up frames (see
+// TestConfig::warmup_frames) and the first test, with the runner node. For
+// one-time costs that would otherwise land in the first test, e.g.
+// instancing the game's heaviest scene once so its shaders get compiled:
+//
+//     on_warmup(|runner| {
+//         let level = load::<PackedScene>("res://levels/forest.tscn").instantiate().unwrap();
+//         runner.clone().add_child(level.clone());
+//         level.queue_free();
+//     });
+pub fn on_warmup(callback: impl FnOnce(&Gd<Node>) + 'static) {
+    CALLBACKS.with(|callbacks| callbacks.borrow_mut().push(Box::new(callback)));
+}
+
+// Called by the runner when the suite starts.
+pub(crate) fn run(runner: &Gd<Node>) {
+    let callbacks = CALLBACKS.with(|callbacks| std::mem::take(&mut *callbacks.borrow_mut()));
+
+    for callback in callbacks {
+        callback(runner);
+    }
+}